@@ -0,0 +1,163 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+};
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use crate::repository::stock::Stock;
+
+// What the holdings are grouped by
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum AllocationDimension {
+    Holding,
+    Sector,
+    Industry,
+    Country,
+    Tag,
+    Market,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct AllocationGroup {
+    pub name: String,
+    pub symbols: Vec<String>,
+    pub value_base: Decimal,
+    // Percentage of the value of the holdings
+    pub weight: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Allocation {
+    pub dimension: AllocationDimension,
+    pub base_currency: String,
+    pub total_base: Decimal,
+    // Biggest group first. A stock with several tags is in the group of each of them.
+    pub groups: Vec<AllocationGroup>,
+    // Currencies without exchange rate, their holdings are left out
+    pub missing_rates: Vec<String>,
+}
+
+const UNKNOWN: &str = "unknown";
+
+impl AllocationDimension {
+    pub fn parse(dimension: &str) -> Option<AllocationDimension> {
+        match dimension.to_lowercase().as_str() {
+            "holding" => Some(Self::Holding),
+            "sector" => Some(Self::Sector),
+            "industry" => Some(Self::Industry),
+            "country" => Some(Self::Country),
+            "tag" => Some(Self::Tag),
+            "market" => Some(Self::Market),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Holding => "holding",
+            Self::Sector => "sector",
+            Self::Industry => "industry",
+            Self::Country => "country",
+            Self::Tag => "tag",
+            Self::Market => "market",
+        }
+    }
+
+    // Groups a stock belongs to
+    pub fn keys(&self, stock: &Stock) -> Vec<String> {
+        let key = match self {
+            Self::Holding => &stock.symbol,
+            Self::Sector => &stock.sector,
+            Self::Industry => &stock.industry,
+            Self::Country => &stock.country,
+            Self::Market => &stock.market.symbol,
+            Self::Tag if stock.tags.is_empty() => "",
+            Self::Tag => return stock.tags.clone(),
+        };
+
+        if key.is_empty() {
+            vec![UNKNOWN.to_owned()]
+        } else {
+            vec![key.to_owned()]
+        }
+    }
+}
+
+// Weights of the holdings in the base currency, grouped by the dimension. `rate`
+// returns how many base currency units a unit of the given currency is worth.
+pub fn allocate<F>(stocks: &[Stock], dimension: AllocationDimension, base_currency: &str, rate: F) -> Allocation
+        where F: Fn(&str) -> Option<Decimal> {
+    let mut missing_rates = BTreeSet::new();
+    let mut groups: BTreeMap<String, (Vec<String>, Decimal)> = BTreeMap::new();
+    let mut total_base = Decimal::zero();
+
+    for stock in stocks {
+        let value_base = match rate(stock.currency()) {
+            Some(rate) => stock.market_value() * rate,
+            None => {
+                missing_rates.insert(stock.currency().to_owned());
+                continue;
+            }
+        };
+        total_base += value_base;
+
+        for key in dimension.keys(stock) {
+            let group = groups.entry(key).or_default();
+            group.0.push(stock.symbol.clone());
+            group.1 += value_base;
+        }
+    }
+
+    let mut groups: Vec<AllocationGroup> = groups.into_iter()
+        .map(|(name, (symbols, value_base))| AllocationGroup {
+            name,
+            symbols,
+            value_base,
+            weight: (value_base * Decimal::from(100))
+                .checked_div(total_base)
+                .unwrap_or_default()
+                .round_dp(2),
+        })
+        .collect();
+    groups.sort_by_key(|group| Reverse(group.value_base));
+
+    Allocation {
+        dimension,
+        base_currency: base_currency.to_owned(),
+        total_base,
+        groups,
+        missing_rates: missing_rates.into_iter().collect(),
+    }
+}
+
+#[test]
+fn test_allocate_by_sector_and_tag() {
+    let stock = |symbol: &str, price: i64, sector: &str, tags: &[&str], currency: &str| Stock {
+        symbol: symbol.into(),
+        price: Decimal::from(price),
+        quantity: Decimal::from(1),
+        sector: sector.into(),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        currency: currency.into(),
+        ..Stock::default()
+    };
+    let stocks = vec![
+        stock("AAPL", 60, "Technology", &["core", "us"], "USD"),
+        stock("KO", 30, "Consumer Defensive", &["dividend"], "USD"),
+        stock("MSFT", 10, "Technology", &[], "USD"),
+        stock("7203", 1000, "", &[], "JPY"),
+    ];
+    let rate = |currency: &str| if currency == "USD" { Some(Decimal::from(1)) } else { None };
+
+    let by_sector = allocate(&stocks, AllocationDimension::Sector, "USD", rate);
+    assert_eq!(Decimal::from(100), by_sector.total_base);
+    assert_eq!("Technology", by_sector.groups[0].name);
+    assert_eq!(vec!["AAPL".to_owned(), "MSFT".to_owned()], by_sector.groups[0].symbols);
+    assert_eq!(Decimal::from(70), by_sector.groups[0].weight);
+    assert_eq!(vec!["JPY".to_owned()], by_sector.missing_rates);
+
+    let by_tag = allocate(&stocks, AllocationDimension::Tag, "USD", rate);
+    let names: Vec<&str> = by_tag.groups.iter().map(|group| group.name.as_str()).collect();
+    assert_eq!(vec!["core", "us", "dividend", "unknown"], names);
+}
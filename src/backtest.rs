@@ -0,0 +1,427 @@
+use std::collections::BTreeMap;
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use crate::indicator::Indicator;
+use crate::returns::{self, PeriodReturn, ValuationPoint};
+use crate::risk::{self, RiskMetrics};
+use crate::repository::{
+    price_history::PriceBar,
+    transaction::{Transaction, TransactionKind},
+};
+
+// What a strategy does at the close of a day. Trades are made at the close.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Action {
+    Buy(String, Decimal),
+    Sell(String, Decimal),
+    // Money added to the account, e.g. a monthly contribution
+    Deposit(Decimal),
+}
+
+// Shares held and what they cost
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Position {
+    pub quantity: Decimal,
+    pub cost: Decimal,
+}
+
+// Cash and positions of a backtest, in the currency of the prices
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Account {
+    pub cash: Decimal,
+    pub positions: BTreeMap<String, Position>,
+}
+
+// Rule deciding the trades of a backtest from the bars seen so far
+pub trait Strategy {
+    // Symbols whose bars the strategy needs
+    fn symbols(&self) -> Vec<String>;
+
+    // Actions at the close of a day. `bars` has the bars of every symbol up to that
+    // day, oldest first.
+    fn on_day(&mut self, date: NaiveDate, bars: &BTreeMap<String, &[PriceBar]>, account: &Account) -> Vec<Action>;
+}
+
+// Buys with all the cash when the fast moving average crosses above the slow one and
+// sells everything when it crosses below
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct SmaCross {
+    pub symbol: String,
+    pub fast: usize,
+    pub slow: usize,
+}
+
+// Brings the holdings back to their weights, in percent, on the first trading day of
+// every month
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct PeriodicRebalance {
+    pub weights: BTreeMap<String, Decimal>,
+    #[serde(skip)]
+    last_month: Option<(i32, u32)>,
+}
+
+// Puts in an amount and invests it on the first trading day on or after a day of
+// every month
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Dca {
+    pub symbol: String,
+    pub amount: Decimal,
+    #[serde(default = "Dca::default_day")]
+    pub day: u32,
+    #[serde(skip)]
+    last_month: Option<(i32, u32)>,
+}
+
+// Strategies that can be run from a request, e.g. `{"sma_cross": {"symbol": "SPY",
+// "fast": 50, "slow": 200}}`
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyConfig {
+    SmaCross(SmaCross),
+    Rebalance(PeriodicRebalance),
+    Dca(Dca),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct BacktestConfig {
+    pub strategy: StrategyConfig,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    #[serde(default)]
+    pub initial_cash: Decimal,
+    // Paid on every trade
+    #[serde(default)]
+    pub commission: Decimal,
+}
+
+// Account at the close of a day
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct EquityPoint {
+    pub date: NaiveDate,
+    pub cash: Decimal,
+    pub holdings: Decimal,
+    pub value: Decimal,
+    pub deposits: Decimal,
+}
+
+// Returns and risk are measured on the equity curve, deposits don't count as gains
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct BacktestStats {
+    pub returns: PeriodReturn,
+    pub risk: RiskMetrics,
+    pub trades: usize,
+    pub winning_sells: usize,
+    pub losing_sells: usize,
+    pub realized_gain: Decimal,
+    pub commissions: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct BacktestResult {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub equity: Vec<EquityPoint>,
+    // Sells carry their gain against the average cost
+    pub trades: Vec<Transaction>,
+    pub stats: BacktestStats,
+}
+
+impl From<&str> for BacktestConfig {
+    fn from(json: &str) -> Self {
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+}
+
+impl StrategyConfig {
+    pub fn build(&self) -> Box<dyn Strategy> {
+        match self {
+            Self::SmaCross(strategy) => Box::new(strategy.clone()),
+            Self::Rebalance(strategy) => Box::new(strategy.clone()),
+            Self::Dca(strategy) => Box::new(strategy.clone()),
+        }
+    }
+}
+
+impl Dca {
+    fn default_day() -> u32 {
+        1
+    }
+}
+
+impl Account {
+    pub fn quantity(&self, symbol: &str) -> Decimal {
+        self.positions.get(symbol).map(|position| position.quantity).unwrap_or_default()
+    }
+
+    // Value of the positions at the last closes
+    pub fn holdings_value(&self, bars: &BTreeMap<String, &[PriceBar]>) -> Decimal {
+        self.positions.iter()
+            .filter_map(|(symbol, position)| last_close(bars, symbol).map(|close| position.quantity * close))
+            .sum()
+    }
+}
+
+fn last_close(bars: &BTreeMap<String, &[PriceBar]>, symbol: &str) -> Option<Decimal> {
+    bars.get(symbol).and_then(|bars| bars.last()).map(|bar| bar.close)
+}
+
+// Whole shares the cash buys after paying the commission
+fn affordable(cash: Decimal, price: Decimal, commission: Decimal) -> Decimal {
+    if price <= Decimal::zero() || cash <= commission {
+        return Decimal::zero();
+    }
+
+    (cash - commission).div_trunc(price, 0)
+}
+
+impl Strategy for SmaCross {
+    fn symbols(&self) -> Vec<String> {
+        vec![self.symbol.clone()]
+    }
+
+    fn on_day(&mut self, date: NaiveDate, bars: &BTreeMap<String, &[PriceBar]>, account: &Account) -> Vec<Action> {
+        let bars = match bars.get(&self.symbol) {
+            Some(bars) if bars.last().map(|bar| bar.date) == Some(date) => *bars,
+            _ => return vec![],
+        };
+
+        let last_two = |indicator: Indicator| -> Option<(Decimal, Decimal)> {
+            match indicator.calculate(bars).as_slice() {
+                [.., previous, last] if last.date == date => Some((previous.value, last.value)),
+                _ => None,
+            }
+        };
+
+        let (fast, slow) = match (last_two(Indicator::Sma(self.fast)), last_two(Indicator::Sma(self.slow))) {
+            (Some(fast), Some(slow)) => (fast, slow),
+            _ => return vec![],
+        };
+
+        let quantity = account.quantity(&self.symbol);
+        if fast.0 <= slow.0 && fast.1 > slow.1 && quantity == Decimal::zero() {
+            vec![Action::Buy(self.symbol.clone(), affordable(account.cash, bars[bars.len() - 1].close, Decimal::zero()))]
+        } else if fast.0 >= slow.0 && fast.1 < slow.1 && quantity > Decimal::zero() {
+            vec![Action::Sell(self.symbol.clone(), quantity)]
+        } else {
+            vec![]
+        }
+    }
+}
+
+impl Strategy for PeriodicRebalance {
+    fn symbols(&self) -> Vec<String> {
+        self.weights.keys().cloned().collect()
+    }
+
+    fn on_day(&mut self, date: NaiveDate, bars: &BTreeMap<String, &[PriceBar]>, account: &Account) -> Vec<Action> {
+        let month = (date.year(), date.month());
+        if self.last_month == Some(month) {
+            return vec![];
+        }
+        self.last_month = Some(month);
+
+        let total = account.cash + account.holdings_value(bars);
+        let mut sells = vec![];
+        let mut buys = vec![];
+        for (symbol, weight) in &self.weights {
+            let close = match last_close(bars, symbol) {
+                Some(close) if close > Decimal::zero() => close,
+                _ => continue,
+            };
+
+            let target = (total * *weight / Decimal::from(100)).div_trunc(close, 0);
+            let held = account.quantity(symbol);
+            if target > held {
+                buys.push(Action::Buy(symbol.clone(), target - held));
+            } else if target < held {
+                sells.push(Action::Sell(symbol.clone(), held - target));
+            }
+        }
+
+        // Sells first, so the buys have the cash
+        sells.extend(buys);
+        sells
+    }
+}
+
+impl Strategy for Dca {
+    fn symbols(&self) -> Vec<String> {
+        vec![self.symbol.clone()]
+    }
+
+    fn on_day(&mut self, date: NaiveDate, bars: &BTreeMap<String, &[PriceBar]>, account: &Account) -> Vec<Action> {
+        let month = (date.year(), date.month());
+        if date.day() < self.day || self.last_month == Some(month) {
+            return vec![];
+        }
+
+        let close = match bars.get(&self.symbol).and_then(|bars| bars.last()) {
+            Some(bar) if bar.date == date && bar.close > Decimal::zero() => bar.close,
+            _ => return vec![],
+        };
+        self.last_month = Some(month);
+
+        // Cash left over from the months before is invested too
+        let quantity = affordable(account.cash + self.amount, close, Decimal::zero());
+        let mut actions = vec![Action::Deposit(self.amount)];
+        if quantity > Decimal::zero() {
+            actions.push(Action::Buy(self.symbol.clone(), quantity));
+        }
+        actions
+    }
+}
+
+// Runs the strategy over the days with bars from `start` to `end`. Buys are limited
+// to the whole shares the cash covers and sells to the shares held.
+pub fn run(strategy: &mut dyn Strategy, bars: &BTreeMap<String, Vec<PriceBar>>, config: &BacktestConfig, risk_free_rate: Decimal) -> BacktestResult {
+    let mut dates: Vec<NaiveDate> = bars.values()
+        .flat_map(|bars| bars.iter().map(|bar| bar.date))
+        .filter(|date| *date >= config.start && *date <= config.end)
+        .collect();
+    dates.sort();
+    dates.dedup();
+
+    let mut account = Account { cash: config.initial_cash, ..Account::default() };
+    let mut trades = vec![];
+    let mut equity = vec![];
+    let mut points = vec![];
+    if let Some(first) = dates.first() {
+        points.push(ValuationPoint {
+            date: *first - Duration::days(1),
+            value: config.initial_cash,
+            flows_in: Decimal::zero(),
+            flows_out: Decimal::zero(),
+        });
+    }
+
+    for date in dates {
+        let seen: BTreeMap<String, &[PriceBar]> = bars.iter()
+            .map(|(symbol, bars)| (symbol.clone(), &bars[..bars.partition_point(|bar| bar.date <= date)]))
+            .collect();
+        let mut deposits = Decimal::zero();
+
+        for action in strategy.on_day(date, &seen, &account) {
+            let (symbol, kind, quantity) = match action {
+                Action::Deposit(amount) => {
+                    account.cash += amount;
+                    deposits += amount;
+                    continue;
+                },
+                Action::Buy(symbol, quantity) => (symbol, TransactionKind::Buy, quantity),
+                Action::Sell(symbol, quantity) => (symbol, TransactionKind::Sell, quantity),
+            };
+
+            let price = match last_close(&seen, &symbol) {
+                Some(price) if price > Decimal::zero() => price,
+                _ => continue,
+            };
+            let quantity = match kind {
+                TransactionKind::Buy => quantity.min(affordable(account.cash, price, config.commission)),
+                TransactionKind::Sell => quantity.min(account.quantity(&symbol)),
+            };
+            if quantity <= Decimal::zero() {
+                continue;
+            }
+
+            let mut trade = Transaction {
+                symbol: symbol.clone(),
+                kind,
+                quantity,
+                price,
+                commission: config.commission,
+                date,
+                ..Transaction::default()
+            };
+            let amount = trade.cash_amount();
+
+            let position = account.positions.entry(symbol).or_default();
+            match kind {
+                TransactionKind::Buy => position.cost += -amount,
+                TransactionKind::Sell => {
+                    let cost = (position.cost * quantity).checked_div(position.quantity).unwrap_or_default();
+                    trade.realized_gain = amount - cost;
+                    position.cost -= cost;
+                },
+            }
+            position.quantity += match kind {
+                TransactionKind::Buy => quantity,
+                TransactionKind::Sell => -quantity,
+            };
+            account.cash += amount;
+            trades.push(trade);
+        }
+
+        let holdings = account.holdings_value(&seen);
+        equity.push(EquityPoint { date, cash: account.cash, holdings, value: account.cash + holdings, deposits });
+        points.push(ValuationPoint { date, value: account.cash + holdings, flows_in: deposits, flows_out: Decimal::zero() });
+    }
+
+    let sells: Vec<&Transaction> = trades.iter().filter(|trade| trade.kind == TransactionKind::Sell).collect();
+    let value = points.last().map(|point| point.value).unwrap_or_default();
+    let stats = BacktestStats {
+        returns: returns::period_return(&points),
+        risk: risk::measure(&returns::growth_index(&points), None, risk_free_rate, value),
+        trades: trades.len(),
+        winning_sells: sells.iter().filter(|trade| trade.realized_gain > Decimal::zero()).count(),
+        losing_sells: sells.iter().filter(|trade| trade.realized_gain < Decimal::zero()).count(),
+        realized_gain: sells.iter().map(|trade| trade.realized_gain).sum(),
+        commissions: trades.iter().map(|trade| trade.commission).sum(),
+    };
+
+    BacktestResult {
+        start: config.start,
+        end: config.end,
+        equity,
+        trades,
+        stats,
+    }
+}
+
+#[test]
+fn test_run_backtest() {
+    let date = |day| NaiveDate::from_ymd_opt(2020, 1, 1).unwrap() + Duration::days(day);
+    let bars = |symbol: &str, closes: &[i64]| -> Vec<PriceBar> {
+        closes.iter().enumerate()
+            .map(|(day, close)| PriceBar {
+                symbol: symbol.into(),
+                date: date(day as i64),
+                close: Decimal::from(*close),
+                ..PriceBar::default()
+            })
+            .collect()
+    };
+    let config = |strategy: StrategyConfig| BacktestConfig {
+        strategy,
+        start: date(0),
+        end: date(60),
+        initial_cash: Decimal::from(1000),
+        commission: Decimal::from(1),
+    };
+
+    // Up, down, then up again: a buy on the first cross and a sell on the second
+    let mut closes = vec![10; 3];
+    closes.extend([8, 12, 14, 16, 12, 8, 6, 5]);
+    let history: BTreeMap<String, Vec<PriceBar>> = vec![("SPY".to_owned(), bars("SPY", &closes))].into_iter().collect();
+    let config_cross = config(StrategyConfig::SmaCross(SmaCross { symbol: "SPY".into(), fast: 2, slow: 3 }));
+    let result = run(config_cross.strategy.build().as_mut(), &history, &config_cross, Decimal::zero());
+
+    assert_eq!(vec![(TransactionKind::Buy, Decimal::from(71), Decimal::from(14)), (TransactionKind::Sell, Decimal::from(71), Decimal::from(8))],
+        result.trades.iter().map(|trade| (trade.kind, trade.quantity, trade.price)).collect::<Vec<_>>());
+    assert_eq!(Decimal::from(-428), result.stats.realized_gain);
+    assert_eq!(1, result.stats.losing_sells);
+    assert_eq!(Decimal::from(572), result.equity.last().unwrap().value);
+    assert_eq!(Some(Decimal::new(-428, 1)), result.stats.returns.time_weighted);
+
+    // Deposits are invested every month without counting as gains
+    let history: BTreeMap<String, Vec<PriceBar>> = vec![("VWCE".to_owned(), bars("VWCE", &[100; 61]))].into_iter().collect();
+    let dca = Dca { symbol: "VWCE".into(), amount: Decimal::from(250), day: 15, last_month: None };
+    let config_dca = BacktestConfig { initial_cash: Decimal::zero(), commission: Decimal::zero(), ..config(StrategyConfig::Dca(dca)) };
+    let result = run(config_dca.strategy.build().as_mut(), &history, &config_dca, Decimal::zero());
+
+    assert_eq!(vec![(date(14), Decimal::from(2)), (date(45), Decimal::from(3))],
+        result.trades.iter().map(|trade| (trade.date, trade.quantity)).collect::<Vec<_>>());
+    assert_eq!(Decimal::from(500), result.stats.returns.flows_in);
+    assert_eq!(Decimal::zero(), result.stats.returns.gain);
+    assert_eq!(Decimal::from(500), result.equity.last().unwrap().value);
+}
@@ -0,0 +1,171 @@
+use std::collections::{BTreeMap, HashMap};
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use crate::returns::{self, PeriodReturn, ValuationPoint};
+use crate::risk;
+
+const TRADING_DAYS: f64 = 252.0;
+
+// Value on a day and the time weighted return since the start, in percent
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct SeriesPoint {
+    pub date: NaiveDate,
+    pub value: Decimal,
+    pub cumulative_return: Decimal,
+}
+
+// The benchmark bought and sold with the money that went in and out of the portfolio.
+// Alpha and tracking error are annualized percents.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct BenchmarkComparison {
+    pub symbol: String,
+    pub returns: PeriodReturn,
+    pub beta: Option<Decimal>,
+    pub alpha: Option<Decimal>,
+    pub tracking_error: Option<Decimal>,
+    pub series: Vec<SeriesPoint>,
+}
+
+// Values are in the base currency, which the benchmarks are taken to be priced in
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Comparison {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub base_currency: String,
+    pub returns: PeriodReturn,
+    pub series: Vec<SeriesPoint>,
+    pub benchmarks: Vec<BenchmarkComparison>,
+    // Currencies without exchange rate, their holdings are left out of the portfolio
+    pub missing_rates: Vec<String>,
+}
+
+fn to_decimal(value: f64) -> Option<Decimal> {
+    Decimal::from_f64(value).map(|value| value.round_dp(2))
+}
+
+// What the portfolio would have been worth had its money gone into the benchmark. As
+// in the growth index, money put in buys at the close of the day before and money taken
+// out sells at the close of the day. The earliest price stands for the days before it.
+pub fn replay_flows(points: &[ValuationPoint], prices: &BTreeMap<NaiveDate, Decimal>) -> Vec<ValuationPoint> {
+    let mut units = Decimal::zero();
+    let mut previous_price = None;
+
+    points.iter()
+        .map(|point| {
+            let price = prices.range(..=point.date).next_back()
+                .or_else(|| prices.iter().next())
+                .map(|(_, price)| *price)
+                .unwrap_or_default();
+            let units_for = |amount: Decimal, price: Decimal| amount.checked_div(price).unwrap_or_default();
+
+            match previous_price {
+                None => units = units_for(point.value, price),
+                Some(previous_price) => units += units_for(point.flows_in, previous_price) - units_for(point.flows_out, price),
+            }
+            previous_price = Some(price);
+
+            ValuationPoint {
+                value: units * price,
+                ..point.clone()
+            }
+        })
+        .collect()
+}
+
+pub fn series(points: &[ValuationPoint]) -> Vec<SeriesPoint> {
+    let index: HashMap<NaiveDate, Decimal> = returns::growth_index(points).into_iter().collect();
+    let mut growth = Decimal::from(1);
+
+    points.iter()
+        .map(|point| {
+            if let Some(point_growth) = index.get(&point.date) {
+                growth = *point_growth;
+            }
+
+            SeriesPoint {
+                date: point.date,
+                value: point.value,
+                cumulative_return: ((growth - Decimal::from(1)) * Decimal::from(100)).round_dp(2),
+            }
+        })
+        .collect()
+}
+
+// Compares the portfolio with each benchmark, given their prices. `risk_free_rate` is
+// the annual percent alpha is measured over.
+pub fn compare(points: &[ValuationPoint], benchmarks: &[(String, BTreeMap<NaiveDate, Decimal>)], risk_free_rate: Decimal) -> (PeriodReturn, Vec<SeriesPoint>, Vec<BenchmarkComparison>) {
+    let portfolio_returns = risk::daily_returns(&returns::growth_index(points));
+    let risk_free_rate = risk_free_rate.to_f64() / 100.0;
+
+    let benchmarks = benchmarks.iter()
+        .map(|(symbol, prices)| {
+            let benchmark_points = replay_flows(points, prices);
+            let benchmark_returns = risk::daily_returns(&returns::growth_index(&benchmark_points));
+
+            let benchmark_by_date: HashMap<NaiveDate, f64> = benchmark_returns.iter().cloned().collect();
+            let (paired_portfolio, paired_benchmark): (Vec<f64>, Vec<f64>) = portfolio_returns.iter()
+                .filter_map(|(date, value)| benchmark_by_date.get(date).map(|benchmark| (*value, *benchmark)))
+                .unzip();
+            let differences: Vec<f64> = paired_portfolio.iter().zip(&paired_benchmark)
+                .map(|(value, benchmark)| value - benchmark)
+                .collect();
+
+            // Jensen's alpha, the return beyond what the beta to the benchmark explains
+            let beta = risk::beta(&portfolio_returns, &benchmark_returns);
+            let alpha = beta.map(|beta| {
+                let portfolio_return = risk::mean(&paired_portfolio) * TRADING_DAYS;
+                let benchmark_return = risk::mean(&paired_benchmark) * TRADING_DAYS;
+                portfolio_return - (risk_free_rate + beta * (benchmark_return - risk_free_rate))
+            });
+            let tracking_error = risk::std_dev(&differences).map(|std_dev| std_dev * TRADING_DAYS.sqrt());
+
+            BenchmarkComparison {
+                symbol: symbol.clone(),
+                returns: returns::period_return(&benchmark_points),
+                beta: beta.and_then(to_decimal),
+                alpha: alpha.and_then(|alpha| to_decimal(alpha * 100.0)),
+                tracking_error: tracking_error.and_then(|tracking_error| to_decimal(tracking_error * 100.0)),
+                series: series(&benchmark_points),
+            }
+        })
+        .collect();
+
+    (returns::period_return(points), series(points), benchmarks)
+}
+
+#[test]
+fn test_compare() {
+    let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+    let point = |day, value: i64, flows_in: i64| ValuationPoint {
+        date: date(day),
+        value: Decimal::from(value),
+        flows_in: Decimal::from(flows_in),
+        flows_out: Decimal::zero(),
+    };
+    // Bought on the 2nd and doubled the position on the 4th
+    let points = vec![
+        point(1, 0, 0),
+        point(2, 1000, 1000),
+        point(3, 1100, 0),
+        point(4, 2200, 1100),
+        point(5, 2420, 0),
+    ];
+    let prices: BTreeMap<NaiveDate, Decimal> = vec![
+        (date(2), Decimal::from(100)),
+        (date(3), Decimal::from(100)),
+        (date(4), Decimal::from(110)),
+        (date(5), Decimal::from(121)),
+    ].into_iter().collect();
+
+    let (portfolio, portfolio_series, benchmarks) = compare(&points, &[("SPY".to_owned(), prices)], Decimal::zero());
+
+    assert_eq!(Some(Decimal::from(21)), portfolio.time_weighted);
+    assert_eq!(Decimal::from(21), portfolio_series[4].cumulative_return);
+    let benchmark = &benchmarks[0];
+    assert_eq!(Decimal::from(2541), benchmark.returns.end_value);
+    assert_eq!(Some(Decimal::from(21)), benchmark.returns.time_weighted);
+    assert_eq!(vec![Decimal::zero(), Decimal::zero(), Decimal::zero(), Decimal::from(10), Decimal::from(21)],
+        benchmark.series.iter().map(|point| point.cumulative_return).collect::<Vec<_>>());
+    assert!(benchmark.tracking_error.unwrap() > Decimal::zero());
+}
@@ -0,0 +1,195 @@
+use std::collections::{BTreeSet, HashMap};
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use crate::risk;
+
+// Fewest days both holdings need a return on to correlate them
+const MIN_OBSERVATIONS: usize = 3;
+
+// Prices of a holding over the window and what it's worth in the base currency
+#[derive(Debug, PartialEq, Clone)]
+pub struct HoldingSeries {
+    pub symbol: String,
+    pub currency: String,
+    pub value_base: Option<Decimal>,
+    pub prices: Vec<(NaiveDate, Decimal)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct CorrelatedPair {
+    pub first: String,
+    pub second: String,
+    pub correlation: Decimal,
+}
+
+// The matrix follows the order of the symbols and has no value for the pairs without
+// enough days in common. The diversification ratio is the weighted volatility of the
+// holdings over the one of the portfolio: 1 when they all move together, higher the
+// more they offset each other.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Correlation {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub symbols: Vec<String>,
+    pub matrix: Vec<Vec<Option<Decimal>>>,
+    pub average_correlation: Option<Decimal>,
+    pub diversification_ratio: Option<Decimal>,
+    pub threshold: Decimal,
+    // Pairs correlated above the threshold, most correlated first
+    pub correlated_pairs: Vec<CorrelatedPair>,
+    // Currencies without exchange rate, their holdings are left out of the weights
+    pub missing_rates: Vec<String>,
+}
+
+fn to_decimal(value: f64) -> Option<Decimal> {
+    Decimal::from_f64(value).map(|value| value.round_dp(4))
+}
+
+// Pearson correlation of the returns of the days both series have one
+pub fn pearson(first: &[(NaiveDate, f64)], second: &[(NaiveDate, f64)]) -> Option<f64> {
+    let second: HashMap<NaiveDate, f64> = second.iter().cloned().collect();
+    let (first, second): (Vec<f64>, Vec<f64>) = first.iter()
+        .filter_map(|(date, value)| second.get(date).map(|other| (*value, *other)))
+        .unzip();
+    if first.len() < MIN_OBSERVATIONS {
+        return None;
+    }
+
+    let (first_mean, second_mean) = (risk::mean(&first), risk::mean(&second));
+    let covariance: f64 = first.iter().zip(&second)
+        .map(|(first, second)| (first - first_mean) * (second - second_mean))
+        .sum();
+    let first_variance: f64 = first.iter().map(|value| (value - first_mean).powi(2)).sum();
+    let second_variance: f64 = second.iter().map(|value| (value - second_mean).powi(2)).sum();
+
+    if first_variance > 0.0 && second_variance > 0.0 {
+        Some(covariance / (first_variance * second_variance).sqrt())
+    } else {
+        None
+    }
+}
+
+// Correlates every pair of holdings and flags the ones above `threshold`
+pub fn correlate(holdings: &[HoldingSeries], start: NaiveDate, end: NaiveDate, threshold: Decimal) -> Correlation {
+    let returns: Vec<Vec<(NaiveDate, f64)>> = holdings.iter()
+        .map(|holding| risk::daily_returns(&holding.prices))
+        .collect();
+    let volatilities: Vec<Option<f64>> = returns.iter()
+        .map(|returns| risk::std_dev(&returns.iter().map(|(_, value)| *value).collect::<Vec<f64>>()))
+        .collect();
+
+    let total_base: Decimal = holdings.iter().filter_map(|holding| holding.value_base).sum();
+    let weights: Vec<f64> = holdings.iter()
+        .map(|holding| match (holding.value_base, total_base > Decimal::zero()) {
+            (Some(value), true) => value.to_f64() / total_base.to_f64(),
+            _ => 0.0,
+        })
+        .collect();
+
+    let size = holdings.len();
+    let mut matrix = vec![vec![None; size]; size];
+    let mut correlations = vec![vec![None; size]; size];
+    let mut correlated_pairs = vec![];
+    let mut weighted_correlation = (0.0, 0.0);
+
+    for first in 0..size {
+        correlations[first][first] = volatilities[first].map(|_| 1.0);
+        matrix[first][first] = correlations[first][first].and_then(to_decimal);
+
+        for second in first + 1..size {
+            let correlation = match pearson(&returns[first], &returns[second]) {
+                Some(correlation) => correlation,
+                None => continue,
+            };
+            let rounded = to_decimal(correlation);
+            correlations[first][second] = Some(correlation);
+            correlations[second][first] = Some(correlation);
+            matrix[first][second] = rounded;
+            matrix[second][first] = rounded;
+
+            let weight = weights[first] * weights[second];
+            weighted_correlation.0 += weight * correlation;
+            weighted_correlation.1 += weight;
+
+            if let Some(rounded) = rounded.filter(|rounded| *rounded > threshold) {
+                correlated_pairs.push(CorrelatedPair {
+                    first: holdings[first].symbol.clone(),
+                    second: holdings[second].symbol.clone(),
+                    correlation: rounded,
+                });
+            }
+        }
+    }
+    correlated_pairs.sort_by_key(|pair| std::cmp::Reverse(pair.correlation));
+
+    // Volatility of the portfolio with the weights, from the correlations known
+    let mut weighted_volatility = 0.0;
+    let mut portfolio_variance = 0.0;
+    for first in 0..size {
+        let first_volatility = volatilities[first].unwrap_or_default();
+        weighted_volatility += weights[first] * first_volatility;
+
+        for second in 0..size {
+            let second_volatility = volatilities[second].unwrap_or_default();
+            let correlation = correlations[first][second].unwrap_or_default();
+            portfolio_variance += weights[first] * weights[second] * first_volatility * second_volatility * correlation;
+        }
+    }
+
+    let missing_rates: BTreeSet<String> = holdings.iter()
+        .filter(|holding| holding.value_base.is_none())
+        .map(|holding| holding.currency.clone())
+        .collect();
+
+    Correlation {
+        start,
+        end,
+        symbols: holdings.iter().map(|holding| holding.symbol.clone()).collect(),
+        matrix,
+        average_correlation: if weighted_correlation.1 > 0.0 {
+            to_decimal(weighted_correlation.0 / weighted_correlation.1)
+        } else {
+            None
+        },
+        diversification_ratio: if portfolio_variance > 0.0 {
+            to_decimal(weighted_volatility / portfolio_variance.sqrt())
+        } else {
+            None
+        },
+        threshold,
+        correlated_pairs,
+        missing_rates: missing_rates.into_iter().collect(),
+    }
+}
+
+#[test]
+fn test_correlate() {
+    let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+    let holding = |symbol: &str, prices: &[i64]| HoldingSeries {
+        symbol: symbol.into(),
+        currency: "USD".into(),
+        value_base: Some(Decimal::from(1000)),
+        prices: prices.iter()
+            .enumerate()
+            .map(|(day, price)| (date(day as u32 + 1), Decimal::from(*price)))
+            .collect(),
+    };
+    let holdings = vec![
+        holding("GOOG", &[100, 110, 99, 109, 98]),
+        // Same moves as GOOG
+        holding("GOOGL", &[200, 220, 198, 218, 196]),
+        // Opposite moves
+        holding("GLD", &[100, 90, 99, 89, 98]),
+    ];
+
+    let correlation = correlate(&holdings, date(1), date(5), Decimal::new(8, 1));
+
+    assert_eq!(vec!["GOOG", "GOOGL", "GLD"], correlation.symbols);
+    assert_eq!(Some(Decimal::from(1)), correlation.matrix[0][0]);
+    assert_eq!(Some(Decimal::from(1)), correlation.matrix[0][1]);
+    assert!(correlation.matrix[0][2].unwrap() < Decimal::new(-9, 1));
+    assert_eq!(correlation.matrix[2][0], correlation.matrix[0][2]);
+    assert_eq!(vec![CorrelatedPair { first: "GOOG".into(), second: "GOOGL".into(), correlation: Decimal::from(1) }], correlation.correlated_pairs);
+    assert!(correlation.diversification_ratio.unwrap() > Decimal::from(1));
+}
@@ -0,0 +1,308 @@
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign},
+    str::FromStr,
+};
+use rust_decimal::{prelude::ToPrimitive, RoundingStrategy};
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use r2d2_sqlite::rusqlite::types::{
+    FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef,
+};
+
+// Fixed-point decimal used for prices, amounts and quantities.
+//
+// Rounding rules:
+// - Every value keeps at most `Decimal::SCALE` decimal places. Values parsed from
+//   text, converted from floats or produced by a multiplication or a division are
+//   rounded to that scale using banker's rounding (half to even).
+// - Additions and subtractions are exact.
+// - `round_dp` must be used explicitly to present a value with fewer decimals.
+//
+// Values are serialized as strings ("123.45") so neither JSON nor SQLite turn
+// them into binary floats. Floats are only accepted as input (e.g. from the API)
+// and are read through their shortest decimal representation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Decimal(rust_decimal::Decimal);
+
+#[derive(Debug, PartialEq)]
+pub struct ParseDecimalError(String);
+
+impl Decimal {
+    pub const SCALE: u32 = 8;
+    const ROUNDING: RoundingStrategy = RoundingStrategy::MidpointNearestEven;
+
+    pub fn new(num: i64, scale: u32) -> Decimal {
+        Decimal(rust_decimal::Decimal::new(num, scale)).rescaled()
+    }
+
+    pub fn zero() -> Decimal {
+        Decimal(rust_decimal::Decimal::ZERO)
+    }
+
+    // Rounds to `dp` decimal places using banker's rounding
+    pub fn round_dp(self, dp: u32) -> Decimal {
+        Decimal(self.0.round_dp_with_strategy(dp, Self::ROUNDING))
+    }
+
+    // Divides dropping the decimals of the quotient beyond `dp`, so it's never rounded up
+    pub fn div_trunc(self, other: Decimal, dp: u32) -> Decimal {
+        Decimal((self.0 / other.0).round_dp_with_strategy(dp, RoundingStrategy::ToZero))
+    }
+
+    // `None` when the product doesn't fit, unlike `*` which panics
+    pub fn checked_mul(self, other: Decimal) -> Option<Decimal> {
+        self.0.checked_mul(other.0).map(|value| Decimal(value).rescaled())
+    }
+
+    pub fn checked_div(self, other: Decimal) -> Option<Decimal> {
+        self.0.checked_div(other.0).map(|value| Decimal(value).rescaled())
+    }
+
+    pub fn from_f64(value: f64) -> Option<Decimal> {
+        if !value.is_finite() {
+            return None;
+        }
+
+        // The shortest representation is the literal the float was parsed from
+        value.to_string().parse().ok()
+    }
+
+    // Only for calculations fixed point can't do, like fractional powers
+    pub fn to_f64(self) -> f64 {
+        self.0.to_f64().unwrap_or_default()
+    }
+
+    fn rescaled(self) -> Decimal {
+        if self.0.scale() > Self::SCALE {
+            Decimal(self.0.round_dp_with_strategy(Self::SCALE, Self::ROUNDING))
+        } else {
+            self
+        }
+    }
+}
+
+impl fmt::Display for ParseDecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid decimal: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseDecimalError {}
+
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let trimmed = value.trim();
+        let parsed = if trimmed.contains(['e', 'E']) {
+            rust_decimal::Decimal::from_scientific(trimmed)
+        } else {
+            rust_decimal::Decimal::from_str(trimmed)
+        };
+
+        parsed
+            .map(|decimal| Decimal(decimal).rescaled())
+            .map_err(|_| ParseDecimalError(value.to_owned()))
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        Decimal(value.into())
+    }
+}
+
+impl From<i32> for Decimal {
+    fn from(value: i32) -> Self {
+        Decimal(value.into())
+    }
+}
+
+impl Add for Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: Decimal) -> Decimal {
+        Decimal(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Decimal {
+    fn add_assign(&mut self, other: Decimal) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: Decimal) -> Decimal {
+        Decimal(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Decimal {
+    fn sub_assign(&mut self, other: Decimal) {
+        self.0 -= other.0;
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal(-self.0)
+    }
+}
+
+impl Mul for Decimal {
+    type Output = Decimal;
+
+    fn mul(self, other: Decimal) -> Decimal {
+        Decimal(self.0 * other.0).rescaled()
+    }
+}
+
+impl Div for Decimal {
+    type Output = Decimal;
+
+    fn div(self, other: Decimal) -> Decimal {
+        Decimal(self.0 / other.0).rescaled()
+    }
+}
+
+impl Sum for Decimal {
+    fn sum<I: Iterator<Item = Decimal>>(iter: I) -> Decimal {
+        iter.fold(Decimal::zero(), Add::add)
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+struct DecimalVisitor;
+
+impl<'de> Visitor<'de> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a decimal number or a string containing one")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Decimal, E> {
+        value.parse().map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Decimal, E> {
+        Ok(Decimal::from(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Decimal, E> {
+        value.to_string().parse().map_err(E::custom)
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Decimal, E> {
+        Decimal::from_f64(value).ok_or_else(|| E::custom(format!("Invalid decimal: {}", value)))
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(DecimalVisitor)
+        } else {
+            deserializer.deserialize_str(DecimalVisitor)
+        }
+    }
+}
+
+impl ToSql for Decimal {
+    fn to_sql(&self) -> r2d2_sqlite::rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl FromSql for Decimal {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Text(text) => std::str::from_utf8(text)
+                .map_err(|e| FromSqlError::Other(Box::new(e)))?
+                .parse()
+                .map_err(|e| FromSqlError::Other(Box::new(e))),
+            ValueRef::Integer(value) => Ok(Decimal::from(value)),
+            ValueRef::Real(value) => Decimal::from_f64(value).ok_or(FromSqlError::InvalidType),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+#[test]
+fn test_rounding_is_half_to_even() {
+    assert_eq!(Decimal::new(2, 0), "2.5".parse::<Decimal>().unwrap().round_dp(0));
+    assert_eq!(Decimal::new(4, 0), "3.5".parse::<Decimal>().unwrap().round_dp(0));
+    assert_eq!(Decimal::new(12, 9).round_dp(Decimal::SCALE), Decimal::new(12, 9));
+    assert_eq!(Decimal::new(1, 8), Decimal::new(1, 7) / Decimal::new(10, 0));
+    assert_eq!(Decimal::zero(), Decimal::new(5, 9));
+}
+
+#[test]
+fn test_from_float_literal() {
+    assert_eq!(Decimal::new(1234567891, 2), Decimal::from_f64(12345678.91).unwrap());
+    assert_eq!(Decimal::new(3, 1), Decimal::from_f64(0.1 + 0.2).unwrap().round_dp(2));
+    let from_json: Decimal = serde_json::from_str("175.07").unwrap();
+    assert_eq!(Decimal::new(17507, 2), from_json);
+}
+
+#[test]
+fn test_checked_mul_overflow() {
+    let huge: Decimal = "70000000000000000000".parse().unwrap();
+    assert_eq!(Some(Decimal::new(25, 1)), Decimal::new(5, 1).checked_mul(Decimal::from(5)));
+    assert_eq!(None, huge.checked_mul(huge));
+    assert_eq!(None, Decimal::from(1).checked_div(Decimal::zero()));
+}
+
+#[cfg(test)]
+use proptest::prelude::*;
+
+#[cfg(test)]
+pub fn any_decimal() -> impl Strategy<Value = Decimal> {
+    (any::<i64>(), 0..=Decimal::SCALE).prop_map(|(num, scale)| Decimal::new(num, scale))
+}
+
+#[cfg(test)]
+proptest! {
+    #[test]
+    fn test_string_round_trip(value in any_decimal()) {
+        prop_assert_eq!(value, value.to_string().parse::<Decimal>().unwrap());
+    }
+
+    #[test]
+    fn test_json_round_trip(value in any_decimal()) {
+        let json = serde_json::to_string(&value).unwrap();
+        prop_assert_eq!(value, serde_json::from_str::<Decimal>(&json).unwrap());
+    }
+
+    #[test]
+    fn test_bincode_round_trip(value in any_decimal()) {
+        let bytes = bincode::serialize(&value).unwrap();
+        prop_assert_eq!(value, bincode::deserialize::<Decimal>(&bytes).unwrap());
+    }
+
+    #[test]
+    fn test_sum_is_exact(cents in proptest::collection::vec(-1_000_000_000i64..1_000_000_000, 0..50)) {
+        let total: Decimal = cents.iter().map(|c| Decimal::new(*c, 2)).sum();
+        prop_assert_eq!(Decimal::new(cents.iter().sum(), 2), total);
+    }
+}
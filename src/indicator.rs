@@ -0,0 +1,303 @@
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use crate::repository::price_history::PriceBar;
+
+// Decimals of the calculated values
+const INDICATOR_DP: u32 = 4;
+
+// Technical indicator and its periods in days
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Indicator {
+    Sma(usize),
+    Ema(usize),
+    Rsi(usize),
+    // Fast, slow and signal periods
+    Macd(usize, usize, usize),
+    // Period and width in standard deviations
+    Bollinger(usize, Decimal),
+    Atr(usize),
+}
+
+// Value of an indicator on a day. MACD fills the signal and the histogram, and the
+// Bollinger Bands the upper and lower bands around the moving average.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct IndicatorValue {
+    pub date: NaiveDate,
+    pub value: Decimal,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub histogram: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upper: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lower: Option<Decimal>,
+}
+
+impl Indicator {
+    // Reads the name and the parameters, which default to the usual periods
+    pub fn parse(name: &str, params: &[&str]) -> Option<Self> {
+        let period = |index: usize, default: usize| -> Option<usize> {
+            match params.get(index) {
+                Some(param) => param.parse().ok(),
+                None => Some(default),
+            }
+        };
+
+        let indicator = match name.to_lowercase().as_str() {
+            "sma" => Self::Sma(period(0, 20)?),
+            "ema" => Self::Ema(period(0, 20)?),
+            "rsi" => Self::Rsi(period(0, 14)?),
+            "macd" => Self::Macd(period(0, 12)?, period(1, 26)?, period(2, 9)?),
+            "bollinger" | "bb" => {
+                let width = match params.get(1) {
+                    Some(param) => param.parse().ok()?,
+                    None => Decimal::from(2),
+                };
+                Self::Bollinger(period(0, 20)?, width)
+            },
+            "atr" => Self::Atr(period(0, 14)?),
+            _ => return None,
+        };
+
+        Some(indicator).filter(Self::is_valid)
+    }
+
+    // Periods of at least a day, and bands with some width
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Self::Sma(period) | Self::Ema(period) | Self::Rsi(period) | Self::Atr(period) => period > 0,
+            Self::Macd(fast, slow, signal) => fast > 0 && slow > 0 && signal > 0,
+            Self::Bollinger(period, width) => period > 0 && width > Decimal::zero(),
+        }
+    }
+
+    // Calculates the indicator over the bars, oldest first. The days before there are
+    // enough bars are left out.
+    pub fn calculate(&self, bars: &[PriceBar]) -> Vec<IndicatorValue> {
+        if !self.is_valid() {
+            return vec![];
+        }
+
+        let closes: Vec<f64> = bars.iter().map(|bar| bar.close.to_f64()).collect();
+
+        let values = match *self {
+            Self::Sma(period) => single(sma(&closes, period)),
+            Self::Ema(period) => single(ema(&closes, period)),
+            Self::Rsi(period) => single(rsi(&closes, period)),
+            Self::Macd(fast, slow, signal) => macd(&closes, fast, slow, signal),
+            Self::Bollinger(period, width) => bollinger(&closes, period, width.to_f64()),
+            Self::Atr(period) => single(atr(bars, period)),
+        };
+
+        values.into_iter()
+            .zip(bars)
+            .filter_map(|(value, bar)| value.map(|value| IndicatorValue { date: bar.date, ..value }))
+            .collect()
+    }
+}
+
+impl IndicatorValue {
+    // Value of an indicator with a single line, the date is filled by `calculate`
+    fn from_value(value: f64) -> Option<Self> {
+        Some(IndicatorValue {
+            value: to_decimal(value)?,
+            ..IndicatorValue::default()
+        })
+    }
+}
+
+fn single(values: Vec<Option<f64>>) -> Vec<Option<IndicatorValue>> {
+    values.into_iter()
+        .map(|value| value.and_then(IndicatorValue::from_value))
+        .collect()
+}
+
+fn to_decimal(value: f64) -> Option<Decimal> {
+    Decimal::from_f64(value).map(|value| value.round_dp(INDICATOR_DP))
+}
+
+fn sma(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    (0..values.len())
+        .map(|index| {
+            if index + 1 < period {
+                return None;
+            }
+            Some(values[index + 1 - period..=index].iter().sum::<f64>() / period as f64)
+        })
+        .collect()
+}
+
+// Seeded with the simple average of the first days
+fn ema(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let smoothing = 2.0 / (period as f64 + 1.0);
+    let mut previous: Option<f64> = None;
+    let mut seed = vec![];
+
+    values.iter()
+        .map(|value| {
+            previous = match previous {
+                Some(previous) => Some(value * smoothing + previous * (1.0 - smoothing)),
+                None => {
+                    seed.push(*value);
+                    if seed.len() == period {
+                        Some(seed.iter().sum::<f64>() / period as f64)
+                    } else {
+                        None
+                    }
+                }
+            };
+            previous
+        })
+        .collect()
+}
+
+// Averages smoothed the way Wilder did, where each day weighs one period-th
+fn wilder(values: &[f64], first: usize, period: usize) -> Vec<Option<f64>> {
+    let mut average: Option<f64> = None;
+
+    (0..values.len())
+        .map(|index| {
+            if index + 1 < first + period {
+                return None;
+            }
+            average = Some(match average {
+                Some(average) => (average * (period - 1) as f64 + values[index]) / period as f64,
+                None => values[first..=index].iter().sum::<f64>() / period as f64,
+            });
+            average
+        })
+        .collect()
+}
+
+fn rsi(closes: &[f64], period: usize) -> Vec<Option<f64>> {
+    let changes: Vec<f64> = std::iter::once(0.0)
+        .chain(closes.windows(2).map(|window| window[1] - window[0]))
+        .collect();
+    let gains: Vec<f64> = changes.iter().map(|change| change.max(0.0)).collect();
+    let losses: Vec<f64> = changes.iter().map(|change| (-change).max(0.0)).collect();
+
+    wilder(&gains, 1, period).into_iter()
+        .zip(wilder(&losses, 1, period))
+        .map(|(gain, loss)| match (gain, loss) {
+            (Some(gain), Some(loss)) if loss > 0.0 => Some(100.0 - 100.0 / (1.0 + gain / loss)),
+            (Some(_), Some(_)) => Some(100.0),
+            _ => None,
+        })
+        .collect()
+}
+
+fn macd(closes: &[f64], fast: usize, slow: usize, signal: usize) -> Vec<Option<IndicatorValue>> {
+    let lines: Vec<Option<f64>> = ema(closes, fast).into_iter()
+        .zip(ema(closes, slow))
+        .map(|(fast, slow)| Some(fast? - slow?))
+        .collect();
+
+    // The signal starts once there are enough days of MACD
+    let start = lines.iter().position(|line| line.is_some()).unwrap_or(lines.len());
+    let signals: Vec<Option<f64>> = vec![None; start].into_iter()
+        .chain(ema(&lines[start..].iter().flatten().cloned().collect::<Vec<f64>>(), signal))
+        .collect();
+
+    lines.into_iter()
+        .zip(signals)
+        .map(|(line, signal)| {
+            let line = line?;
+            Some(IndicatorValue {
+                signal: signal.and_then(to_decimal),
+                histogram: signal.and_then(|signal| to_decimal(line - signal)),
+                ..IndicatorValue::from_value(line)?
+            })
+        })
+        .collect()
+}
+
+fn bollinger(closes: &[f64], period: usize, width: f64) -> Vec<Option<IndicatorValue>> {
+    sma(closes, period).into_iter()
+        .enumerate()
+        .map(|(index, middle)| {
+            let middle = middle?;
+            let window = &closes[index + 1 - period..=index];
+            let deviation = (window.iter().map(|close| (close - middle).powi(2)).sum::<f64>() / period as f64).sqrt();
+
+            Some(IndicatorValue {
+                upper: to_decimal(middle + width * deviation),
+                lower: to_decimal(middle - width * deviation),
+                ..IndicatorValue::from_value(middle)?
+            })
+        })
+        .collect()
+}
+
+fn atr(bars: &[PriceBar], period: usize) -> Vec<Option<f64>> {
+    let ranges: Vec<f64> = bars.iter()
+        .enumerate()
+        .map(|(index, bar)| {
+            let (high, low) = (bar.high.to_f64(), bar.low.to_f64());
+            match index {
+                0 => high - low,
+                _ => {
+                    let previous_close = bars[index - 1].close.to_f64();
+                    (high - low).max((high - previous_close).abs()).max((low - previous_close).abs())
+                }
+            }
+        })
+        .collect();
+
+    wilder(&ranges, 1, period)
+}
+
+#[test]
+fn test_calculate() {
+    let bar = |day: u32, close: i64| PriceBar {
+        symbol: "AAPL".into(),
+        date: NaiveDate::from_ymd_opt(2020, 1, day).unwrap(),
+        open: Decimal::from(close),
+        high: Decimal::from(close + 1),
+        low: Decimal::from(close - 1),
+        close: Decimal::from(close),
+        volume: Decimal::zero(),
+    };
+    let bars: Vec<PriceBar> = [10, 11, 12, 11, 13, 15]
+        .iter()
+        .enumerate()
+        .map(|(day, close)| bar(day as u32 + 1, *close))
+        .collect();
+
+    let sma = Indicator::Sma(3).calculate(&bars);
+    assert_eq!(4, sma.len());
+    assert_eq!(bars[2].date, sma[0].date);
+    assert_eq!(Decimal::from(11), sma[0].value);
+    assert_eq!(Decimal::from(13), sma[3].value);
+
+    // Seeded with 11, then halfway to each close
+    let ema = Indicator::Ema(3).calculate(&bars);
+    assert_eq!(vec![Decimal::from(11), Decimal::from(11), Decimal::from(12), Decimal::new(135, 1)],
+        ema.iter().map(|value| value.value).collect::<Vec<_>>());
+
+    // Gains of 1, 1 and losses of 1 over the first three changes
+    let rsi = Indicator::Rsi(3).calculate(&bars);
+    assert_eq!(Decimal::new(666667, 4).round_dp(2), rsi[0].value.round_dp(2));
+    assert_eq!(bars[3].date, rsi[0].date);
+
+    let bollinger = Indicator::Bollinger(3, Decimal::from(2)).calculate(&bars);
+    assert_eq!(Decimal::from(11), bollinger[0].value);
+    assert_eq!(Some(Decimal::new(126330, 4)), bollinger[0].upper);
+
+    let macd = Indicator::Macd(2, 3, 2).calculate(&bars);
+    assert_eq!(4, macd.len());
+    assert_eq!(None, macd[0].signal);
+    assert!(macd[1].signal.is_some());
+
+    // The ranges are 2 until the closes gap up and range 3 from the close before
+    let atr = Indicator::Atr(3).calculate(&bars);
+    assert_eq!(Decimal::from(2), atr[0].value);
+    assert_eq!(Decimal::new(23333, 4), atr[1].value);
+
+    assert_eq!(Some(Indicator::Macd(12, 26, 9)), Indicator::parse("MACD", &[]));
+    assert_eq!(None, Indicator::parse("sma", &["0"]));
+    assert_eq!(None, Indicator::parse("bb", &["20", "0"]));
+    assert!(Indicator::Rsi(0).calculate(&bars).is_empty());
+}
@@ -1,34 +1,173 @@
+mod allocation;
+mod backtest;
+mod comparison;
+mod correlation;
+mod decimal;
+mod indicator;
+mod rebalance;
 mod repository;
+mod returns;
+mod risk;
 mod server;
+mod summary;
+mod tax;
+mod valuation;
 
-use log::{debug, info};
+use std::{collections::BTreeSet, fmt};
+use log::{debug, info, error};
 use crossbeam_channel::Sender;
 use serde::{Serialize, Deserialize};
 use hyper_tls::HttpsConnector;
-use tokio;
-use server::ResponseWrapper;
+use server::{Event, ResponseWrapper};
+use allocation::AllocationDimension;
+use returns::ReturnPeriod;
+use indicator::Indicator;
+use rebalance::RebalanceOptions;
+use backtest::{BacktestConfig, SmaCross, StrategyConfig};
 use repository::{
     stock::Stock,
+    market::{Market, MarketHoliday},
+    transaction::Transaction,
+    cash::CashMovement,
+    dividend::Dividend,
+    corporate_action::CorporateAction,
+    watchlist::WatchlistItem,
+    target::AllocationTarget,
+    stop::HoldingStop,
+    order::Order,
+    plan::InvestmentPlan,
+    alert::{Alert, AlertCondition},
+    webhook::{self, Webhook},
     HttpClient
 };
 use r2d2_sqlite::SqliteConnectionManager;
-use r2d2;
 
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum Operation {
     GetPortfolio,
+    GetPortfolioByMarket,
     ListAvailable,
     UpdatePrices,
     DeleteStock(String),
     AddStock(Stock),
+    UpdateStock(Stock),
+    ListMarkets,
+    AddMarket(Market),
+    UpdateMarket(Market),
+    DeleteMarket(u16),
+    AddHoliday(MarketHoliday),
+    RemoveHoliday(u16, chrono::NaiveDate),
+    ListHolidays(u16),
+    GetValuation,
+    SetBaseCurrency(String),
+    SetRiskFreeRate(decimal::Decimal),
+    GetFxHistory(String, String),
+    Deposit(CashMovement),
+    Withdraw(CashMovement),
+    GetCash,
+    GetCashMovements,
+    Buy(Transaction),
+    Sell(Transaction),
+    GetTransactions(Option<String>),
+    GetTaxReport(i32, bool),
+    PlaceOrder(Order),
+    CancelOrder(u32),
+    ListOrders(bool),
+    SetSlippage(decimal::Decimal),
+    SetCommission(decimal::Decimal),
+    AddDividend(Dividend),
+    GetDividends(Option<String>),
+    ImportDividends(Option<String>),
+    ProcessDividends,
+    GetDividendReport,
+    AddPlan(InvestmentPlan),
+    DeletePlan(u32),
+    ListPlans,
+    RunPlans,
+    GetPlanReport,
+    AddCorporateAction(CorporateAction),
+    GetCorporateActions(Option<String>),
+    ImportHistory(Option<String>),
+    GetHistory(String, bool),
+    GetIndicator(String, Indicator),
+    ListPortfolios,
+    CreatePortfolio(String),
+    RenamePortfolio(String, String),
+    DeletePortfolio(String),
+    SelectPortfolio(String),
+    GetAllocation(AllocationDimension),
+    SetTarget(AllocationTarget),
+    RemoveTarget(AllocationDimension, String),
+    ListTargets,
+    Rebalance(RebalanceOptions),
+    SetStop(HoldingStop),
+    RemoveStop(String),
+    ListStops,
+    GetSummary(bool),
+    GetReturns(ReturnPeriod),
+    GetRisk(Option<String>, ReturnPeriod),
+    ListBenchmarks,
+    AddBenchmark(String),
+    RemoveBenchmark(String),
+    Compare(ReturnPeriod),
+    GetCorrelation(ReturnPeriod, decimal::Decimal),
+    Backtest(BacktestConfig),
+    TagStock(String, String),
+    UntagStock(String, String),
+    ImportProfiles(Option<String>),
+    GetProfile(String),
+    ListWatchlists,
+    CreateWatchlist(String),
+    DeleteWatchlist(String),
+    GetWatchlist(String),
+    AddToWatchlist(String, WatchlistItem),
+    UpdateWatchlistItem(String, WatchlistItem),
+    RemoveFromWatchlist(String, String),
+    AddAlert(Alert),
+    DeleteAlert(u32),
+    ListAlerts,
+    AddWebhook(Webhook),
+    DeleteWebhook(u32),
+    ListWebhooks,
+    GetDeliveries(Option<u32>),
+    Subscribe(Vec<String>),
+    Unsubscribe,
     Help,
     Error
 }
 
+// Operation of a connection along with the portfolio it applies to
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    // Name of the portfolio, the default one if not given
+    pub portfolio: Option<String>,
+    pub operation: Operation,
+}
+
+impl Request {
+    // Parses a command, which applies to the selected portfolio unless it starts
+    // with the name of another one, e.g. `@retirement get_portfolio`
+    pub fn parse(message: &str, selected: Option<&str>) -> Request {
+        match message.strip_prefix('@') {
+            Some(rest) => {
+                let (portfolio, command) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                Request {
+                    portfolio: Some(portfolio.to_owned()),
+                    operation: Operation::from(command.trim_start().to_owned()),
+                }
+            },
+            None => Request {
+                portfolio: selected.map(str::to_owned),
+                operation: Operation::from(message.to_owned()),
+            }
+        }
+    }
+}
+
 pub trait ByteOperations<'a>  {
     fn to_bytes(&self) -> Vec<u8>;
-    fn from_bytes(vec: &'a Vec<u8>) -> Self;
+    fn from_bytes(vec: &'a [u8]) -> Self;
 }
 
 impl<'a, T> ByteOperations<'a> for T where 
@@ -38,22 +177,107 @@ impl<'a, T> ByteOperations<'a> for T where
         bincode::serialize(&self).unwrap()
     }
 
-    fn from_bytes(vec: &'a Vec<u8>) -> T {
-        bincode::deserialize(vec.as_slice()).unwrap()
+    fn from_bytes(vec: &'a [u8]) -> T {
+        bincode::deserialize(vec).unwrap()
     }
 }
 
-impl ToString for Operation {
-    fn to_string(&self) -> String {
-        match self {
-            Self::GetPortfolio => "get_portfolio".into(),
-            Self::ListAvailable => "list_available".into(),
-            Self::UpdatePrices => "update_prices".into(),
-            Self::AddStock(_) => "add_stock".into(),
-            Self::DeleteStock(_) => "delete_stock".into(),
-            Self::Help => "help".into(),
-            Self::Error => "error".into(),
-        }
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::GetPortfolio => "get_portfolio",
+            Self::GetPortfolioByMarket => "get_portfolio_by_market",
+            Self::ListAvailable => "list_available",
+            Self::UpdatePrices => "update_prices",
+            Self::AddStock(_) => "add_stock",
+            Self::UpdateStock(_) => "update_stock",
+            Self::DeleteStock(_) => "delete_stock",
+            Self::ListMarkets => "list_markets",
+            Self::AddMarket(_) => "add_market",
+            Self::UpdateMarket(_) => "update_market",
+            Self::DeleteMarket(_) => "delete_market",
+            Self::AddHoliday(_) => "add_holiday",
+            Self::RemoveHoliday(_, _) => "remove_holiday",
+            Self::ListHolidays(_) => "list_holidays",
+            Self::GetValuation => "get_valuation",
+            Self::SetBaseCurrency(_) => "set_base_currency",
+            Self::SetRiskFreeRate(_) => "set_risk_free_rate",
+            Self::GetFxHistory(_, _) => "get_fx_history",
+            Self::Deposit(_) => "deposit",
+            Self::Withdraw(_) => "withdraw",
+            Self::GetCash => "get_cash",
+            Self::GetCashMovements => "get_cash_movements",
+            Self::Buy(_) => "buy",
+            Self::Sell(_) => "sell",
+            Self::GetTransactions(_) => "get_transactions",
+            Self::GetTaxReport(_, _) => "tax_report",
+            Self::PlaceOrder(_) => "place_order",
+            Self::CancelOrder(_) => "cancel_order",
+            Self::ListOrders(_) => "list_orders",
+            Self::SetSlippage(_) => "set_slippage",
+            Self::SetCommission(_) => "set_commission",
+            Self::AddDividend(_) => "add_dividend",
+            Self::GetDividends(_) => "get_dividends",
+            Self::ImportDividends(_) => "import_dividends",
+            Self::ProcessDividends => "process_dividends",
+            Self::GetDividendReport => "get_dividend_report",
+            Self::AddPlan(_) => "add_plan",
+            Self::DeletePlan(_) => "delete_plan",
+            Self::ListPlans => "list_plans",
+            Self::RunPlans => "run_plans",
+            Self::GetPlanReport => "plan_report",
+            Self::AddCorporateAction(_) => "add_corporate_action",
+            Self::GetCorporateActions(_) => "get_corporate_actions",
+            Self::ImportHistory(_) => "import_history",
+            Self::GetHistory(_, _) => "get_history",
+            Self::GetIndicator(_, _) => "indicator",
+            Self::ListPortfolios => "list_portfolios",
+            Self::CreatePortfolio(_) => "create_portfolio",
+            Self::RenamePortfolio(_, _) => "rename_portfolio",
+            Self::DeletePortfolio(_) => "delete_portfolio",
+            Self::SelectPortfolio(_) => "select_portfolio",
+            Self::GetAllocation(_) => "allocation",
+            Self::SetTarget(_) => "set_target",
+            Self::RemoveTarget(_, _) => "remove_target",
+            Self::ListTargets => "list_targets",
+            Self::Rebalance(_) => "rebalance",
+            Self::SetStop(_) => "set_stop",
+            Self::RemoveStop(_) => "remove_stop",
+            Self::ListStops => "list_stops",
+            Self::GetSummary(_) => "summary",
+            Self::GetReturns(_) => "returns",
+            Self::GetRisk(_, _) => "risk",
+            Self::ListBenchmarks => "list_benchmarks",
+            Self::AddBenchmark(_) => "add_benchmark",
+            Self::RemoveBenchmark(_) => "remove_benchmark",
+            Self::Compare(_) => "compare",
+            Self::GetCorrelation(_, _) => "correlation",
+            Self::Backtest(_) => "backtest",
+            Self::TagStock(_, _) => "tag_stock",
+            Self::UntagStock(_, _) => "untag_stock",
+            Self::ImportProfiles(_) => "import_profiles",
+            Self::GetProfile(_) => "profile",
+            Self::ListWatchlists => "list_watchlists",
+            Self::CreateWatchlist(_) => "create_watchlist",
+            Self::DeleteWatchlist(_) => "delete_watchlist",
+            Self::GetWatchlist(_) => "get_watchlist",
+            Self::AddToWatchlist(_, _) => "add_to_watchlist",
+            Self::UpdateWatchlistItem(_, _) => "update_watchlist_item",
+            Self::RemoveFromWatchlist(_, _) => "remove_from_watchlist",
+            Self::AddAlert(_) => "add_alert",
+            Self::DeleteAlert(_) => "delete_alert",
+            Self::ListAlerts => "list_alerts",
+            Self::AddWebhook(_) => "add_webhook",
+            Self::DeleteWebhook(_) => "delete_webhook",
+            Self::ListWebhooks => "list_webhooks",
+            Self::GetDeliveries(_) => "get_deliveries",
+            Self::Subscribe(_) => "subscribe",
+            Self::Unsubscribe => "unsubscribe",
+            Self::Help => "help",
+            Self::Error => "error",
+        };
+
+        write!(f, "{}", name)
     }
 }
 
@@ -62,8 +286,26 @@ impl From<String> for Operation {
     fn from(val: String) -> Operation {
         match val.as_str() {
             "get_portfolio" => Self::GetPortfolio,
+            "get_portfolio_by_market" => Self::GetPortfolioByMarket,
             "list_available" => Self::ListAvailable,
             "update_prices" => Self::UpdatePrices,
+            "list_markets" => Self::ListMarkets,
+            "get_valuation" => Self::GetValuation,
+            "get_cash" => Self::GetCash,
+            "get_cash_movements" => Self::GetCashMovements,
+            "process_dividends" => Self::ProcessDividends,
+            "get_dividend_report" => Self::GetDividendReport,
+            "list_plans" => Self::ListPlans,
+            "run_plans" => Self::RunPlans,
+            "plan_report" => Self::GetPlanReport,
+            "list_portfolios" => Self::ListPortfolios,
+            "list_watchlists" => Self::ListWatchlists,
+            "list_benchmarks" => Self::ListBenchmarks,
+            "list_targets" => Self::ListTargets,
+            "list_stops" => Self::ListStops,
+            "list_alerts" => Self::ListAlerts,
+            "list_webhooks" => Self::ListWebhooks,
+            "unsubscribe" => Self::Unsubscribe,
             op if op.starts_with("delete_stock") => {
                 let parts = op.split_whitespace().collect::<Vec<&str>>();
                 let stock = parts.get(1).unwrap();
@@ -75,12 +317,451 @@ impl From<String> for Operation {
                 let stock = Stock::from(stock_str.as_str());
                 Self::AddStock(stock)
             },
+            op if op.starts_with("update_stock") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let stock_str = parts[1..].join(" ");
+                let stock = Stock::from(stock_str.as_str());
+                Self::UpdateStock(stock)
+            },
+            op if op.starts_with("add_market") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let market_str = parts[1..].join(" ");
+                Self::AddMarket(Market::from(market_str.as_str()))
+            },
+            op if op.starts_with("update_market") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let market_str = parts[1..].join(" ");
+                Self::UpdateMarket(Market::from(market_str.as_str()))
+            },
+            op if op.starts_with("delete_market") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1).and_then(|id| id.parse().ok()) {
+                    Some(id) => Self::DeleteMarket(id),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("add_holiday") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let holiday_str = parts[1..].join(" ");
+                Self::AddHoliday(MarketHoliday::from(holiday_str.as_str()))
+            },
+            op if op.starts_with("remove_holiday") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let market_id = parts.get(1).and_then(|id| id.parse().ok());
+                let date = parts.get(2).and_then(|date| date.parse().ok());
+                match (market_id, date) {
+                    (Some(market_id), Some(date)) => Self::RemoveHoliday(market_id, date),
+                    _ => Self::Error
+                }
+            },
+            op if op.starts_with("list_holidays") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1).and_then(|id| id.parse().ok()) {
+                    Some(id) => Self::ListHolidays(id),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("add_plan") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let mut plan = InvestmentPlan::from(parts[1..].join(" ").as_str());
+                plan.symbol = plan.symbol.to_uppercase();
+                Self::AddPlan(plan)
+            },
+            op if op.starts_with("delete_plan") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1).and_then(|id| id.parse().ok()) {
+                    Some(id) => Self::DeletePlan(id),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("set_base_currency") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    Some(currency) => Self::SetBaseCurrency(currency.to_uppercase()),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("set_risk_free_rate") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1).and_then(|rate| rate.parse().ok()) {
+                    Some(rate) => Self::SetRiskFreeRate(rate),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("get_fx_history") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match (parts.get(1), parts.get(2)) {
+                    (Some(base), Some(quote)) => Self::GetFxHistory(base.to_uppercase(), quote.to_uppercase()),
+                    _ => Self::Error
+                }
+            },
+            op if op.starts_with("deposit") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let movement_str = parts[1..].join(" ");
+                Self::Deposit(CashMovement::from(movement_str.as_str()))
+            },
+            op if op.starts_with("withdraw") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let movement_str = parts[1..].join(" ");
+                Self::Withdraw(CashMovement::from(movement_str.as_str()))
+            },
+            op if op.starts_with("buy") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let transaction_str = parts[1..].join(" ");
+                Self::Buy(Transaction::from(transaction_str.as_str()))
+            },
+            op if op.starts_with("sell") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let transaction_str = parts[1..].join(" ");
+                Self::Sell(Transaction::from(transaction_str.as_str()))
+            },
+            op if op.starts_with("get_transactions") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                Self::GetTransactions(parts.get(1).map(|symbol| symbol.to_string()))
+            },
+            op if op.starts_with("tax_report") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match (parts.get(1).and_then(|year| year.parse().ok()), parts.get(2)) {
+                    (Some(year), None) => Self::GetTaxReport(year, false),
+                    (Some(year), Some(&"csv")) => Self::GetTaxReport(year, true),
+                    _ => Self::Error
+                }
+            },
+            op if op.starts_with("place_order") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let mut order = Order::from(parts[1..].join(" ").as_str());
+                order.symbol = order.symbol.to_uppercase();
+                Self::PlaceOrder(order)
+            },
+            op if op.starts_with("cancel_order") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1).and_then(|id| id.parse().ok()) {
+                    Some(id) => Self::CancelOrder(id),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("list_orders") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    None => Self::ListOrders(false),
+                    Some(&"all") => Self::ListOrders(true),
+                    Some(_) => Self::Error
+                }
+            },
+            op if op.starts_with("set_slippage") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1).and_then(|slippage| slippage.parse().ok()) {
+                    Some(slippage) => Self::SetSlippage(slippage),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("set_commission") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1).and_then(|commission| commission.parse().ok()) {
+                    Some(commission) => Self::SetCommission(commission),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("add_dividend") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let dividend_str = parts[1..].join(" ");
+                Self::AddDividend(Dividend::from(dividend_str.as_str()))
+            },
+            op if op.starts_with("get_dividends") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                Self::GetDividends(parts.get(1).map(|symbol| symbol.to_string()))
+            },
+            op if op.starts_with("import_dividends") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                Self::ImportDividends(parts.get(1).map(|symbol| symbol.to_string()))
+            },
+            op if op.starts_with("add_corporate_action") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let action_str = parts[1..].join(" ");
+                Self::AddCorporateAction(CorporateAction::from(action_str.as_str()))
+            },
+            op if op.starts_with("get_corporate_actions") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                Self::GetCorporateActions(parts.get(1).map(|symbol| symbol.to_string()))
+            },
+            op if op.starts_with("import_history") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                Self::ImportHistory(parts.get(1).map(|symbol| symbol.to_string()))
+            },
+            op if op.starts_with("get_history") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    Some(symbol) => Self::GetHistory(symbol.to_string(), parts.get(2) == Some(&"raw")),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("indicator") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match (parts.get(1), parts.get(2)) {
+                    (Some(symbol), Some(name)) => Indicator::parse(name, &parts[3..])
+                        .map(|indicator| Self::GetIndicator(symbol.to_uppercase(), indicator))
+                        .unwrap_or(Self::Error),
+                    _ => Self::Error
+                }
+            },
+            op if op.starts_with("create_portfolio") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    Some(name) => Self::CreatePortfolio(name.to_string()),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("rename_portfolio") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match (parts.get(1), parts.get(2)) {
+                    (Some(name), Some(new_name)) => Self::RenamePortfolio(name.to_string(), new_name.to_string()),
+                    _ => Self::Error
+                }
+            },
+            op if op.starts_with("delete_portfolio") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    Some(name) => Self::DeletePortfolio(name.to_string()),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("select_portfolio") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    Some(name) => Self::SelectPortfolio(name.to_string()),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("allocation") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1).and_then(|dimension| AllocationDimension::parse(dimension)) {
+                    Some(dimension) => Self::GetAllocation(dimension),
+                    None => Self::Error
+                }
+            },
+            // Without symbols it subscribes to every one
+            op if op.starts_with("subscribe") => {
+                let symbols = op.split_whitespace()
+                    .skip(1)
+                    .map(str::to_uppercase)
+                    .collect();
+                Self::Subscribe(symbols)
+            },
+            op if op.starts_with("add_alert") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let alert_str = parts[1..].join(" ");
+                Self::AddAlert(Alert::from(alert_str.as_str()))
+            },
+            op if op.starts_with("delete_alert") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1).and_then(|id| id.parse().ok()) {
+                    Some(id) => Self::DeleteAlert(id),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("add_webhook") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let webhook_str = parts[1..].join(" ");
+                Self::AddWebhook(Webhook::from(webhook_str.as_str()))
+            },
+            op if op.starts_with("delete_webhook") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1).and_then(|id| id.parse().ok()) {
+                    Some(id) => Self::DeleteWebhook(id),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("get_deliveries") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1).map(|id| id.parse()) {
+                    None => Self::GetDeliveries(None),
+                    Some(Ok(id)) => Self::GetDeliveries(Some(id)),
+                    Some(Err(_)) => Self::Error
+                }
+            },
+            op if op.starts_with("set_target") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let target_str = parts[1..].join(" ");
+                Self::SetTarget(AllocationTarget::from(target_str.as_str()))
+            },
+            // Keys such as sectors may have spaces
+            op if op.starts_with("remove_target") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match (parts.get(1).and_then(|dimension| AllocationDimension::parse(dimension)), parts.len() > 2) {
+                    (Some(dimension), true) => Self::RemoveTarget(dimension, parts[2..].join(" ")),
+                    _ => Self::Error
+                }
+            },
+            op if op.starts_with("rebalance") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.len() {
+                    1 => Self::Rebalance(RebalanceOptions::default()),
+                    _ => Self::Rebalance(RebalanceOptions::from(parts[1..].join(" ").as_str())),
+                }
+            },
+            op if op.starts_with("set_stop") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let mut stop = HoldingStop::from(parts[1..].join(" ").as_str());
+                stop.symbol = stop.symbol.to_uppercase();
+                Self::SetStop(stop)
+            },
+            op if op.starts_with("remove_stop") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    Some(symbol) => Self::RemoveStop(symbol.to_uppercase()),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("summary") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    None => Self::GetSummary(false),
+                    Some(&"text") => Self::GetSummary(true),
+                    Some(_) => Self::Error
+                }
+            },
+            op if op.starts_with("returns") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                parse_period(&parts[1..], ReturnPeriod::Inception)
+                    .map(Self::GetReturns)
+                    .unwrap_or(Self::Error)
+            },
+            op if op.starts_with("compare") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                parse_period(&parts[1..], ReturnPeriod::Inception)
+                    .map(Self::Compare)
+                    .unwrap_or(Self::Error)
+            },
+            op if op.starts_with("correlation") => {
+                let mut period = ReturnPeriod::Year;
+                let mut threshold = decimal::Decimal::new(8, 1);
+                for part in op.split_whitespace().skip(1) {
+                    match (ReturnPeriod::parse(part), part.parse()) {
+                        (Some(part_period), _) => period = part_period,
+                        (None, Ok(part_threshold)) => threshold = part_threshold,
+                        (None, Err(_)) => return Self::Error,
+                    }
+                }
+                Self::GetCorrelation(period, threshold)
+            },
+            op if op.starts_with("backtest") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                let config_str = parts[1..].join(" ");
+                Self::Backtest(BacktestConfig::from(config_str.as_str()))
+            },
+            op if op.starts_with("add_benchmark") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    Some(symbol) => Self::AddBenchmark(symbol.to_uppercase()),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("remove_benchmark") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    Some(symbol) => Self::RemoveBenchmark(symbol.to_uppercase()),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("risk") => {
+                let mut benchmark = None;
+                let mut period = ReturnPeriod::Year;
+                for part in op.split_whitespace().skip(1) {
+                    match ReturnPeriod::parse(part) {
+                        Some(part_period) => period = part_period,
+                        None => benchmark = Some(part.to_uppercase()),
+                    }
+                }
+                Self::GetRisk(benchmark, period)
+            },
+            op if op.starts_with("tag_stock") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match (parts.get(1), parts.get(2)) {
+                    (Some(symbol), Some(tag)) => Self::TagStock(symbol.to_string(), tag.to_string()),
+                    _ => Self::Error
+                }
+            },
+            op if op.starts_with("untag_stock") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match (parts.get(1), parts.get(2)) {
+                    (Some(symbol), Some(tag)) => Self::UntagStock(symbol.to_string(), tag.to_string()),
+                    _ => Self::Error
+                }
+            },
+            op if op.starts_with("import_profiles") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                Self::ImportProfiles(parts.get(1).map(|symbol| symbol.to_string()))
+            },
+            op if op.starts_with("profile") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    Some(symbol) => Self::GetProfile(symbol.to_uppercase()),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("create_watchlist") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    Some(name) => Self::CreateWatchlist(name.to_string()),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("delete_watchlist") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    Some(name) => Self::DeleteWatchlist(name.to_string()),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("get_watchlist") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    Some(name) => Self::GetWatchlist(name.to_string()),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("add_to_watchlist") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    Some(name) => Self::AddToWatchlist(name.to_string(), WatchlistItem::from(parts[2..].join(" ").as_str())),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("update_watchlist_item") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match parts.get(1) {
+                    Some(name) => Self::UpdateWatchlistItem(name.to_string(), WatchlistItem::from(parts[2..].join(" ").as_str())),
+                    None => Self::Error
+                }
+            },
+            op if op.starts_with("remove_from_watchlist") => {
+                let parts = op.split_whitespace().collect::<Vec<&str>>();
+                match (parts.get(1), parts.get(2)) {
+                    (Some(name), Some(symbol)) => Self::RemoveFromWatchlist(name.to_string(), symbol.to_string()),
+                    _ => Self::Error
+                }
+            },
             "help" | "?" => Self::Help,
             _ => Self::Error
         }
     }
 }
 
+// Reads a named period or a custom range of two dates, the default if there's none
+fn parse_period(parts: &[&str], default: ReturnPeriod) -> Option<ReturnPeriod> {
+    let date = |part: &str| part.parse::<chrono::NaiveDate>().ok();
+
+    match parts {
+        [] => Some(default),
+        [period] => ReturnPeriod::parse(period),
+        [from, to] => match (date(from), date(to)) {
+            (Some(from), Some(to)) if from <= to => Some(ReturnPeriod::Custom(from, to)),
+            _ => None
+        },
+        _ => None
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct Job {
     id: u32,
@@ -108,7 +789,8 @@ fn get_hyper_connection() -> HttpClient{
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     env_logger::init();
     let db_pool = get_db_pool_connection();
-    let rx_ch = server::launch_tcp_server();
+    repository::init_storage(&db_pool.get().unwrap()).expect("Couldn't initialize the storage.");
+    let (rx_ch, events_ch) = server::launch_tcp_server();
 
     loop {
         info!(target: "Main", "Waiting for messages...");
@@ -119,24 +801,120 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let job = Job::from_bytes(&received_str);
         debug!(target: "Main", "Deserialized{:?}", job);
 
-        let operation = Operation::from_bytes(&job.payload);
-        info!(target: "Main", "Got operation {} from connection {}", operation.to_string(), job.id);
+        let Request { portfolio: portfolio_name, operation } = Request::from_bytes(&job.payload);
+        info!(target: "Main", "Got operation {} from connection {} on portfolio {:?}", operation.to_string(), job.id, portfolio_name);
 
         let pool = db_pool.clone();
+        let events = events_ch.clone();
 
         tokio::task::spawn(async move {
+            // The portfolio, watchlist, profile, alert and subscription operations and the help don't depend on the selected portfolio
+            match &operation {
+                Operation::ListPortfolios => return process_list_portfolios(tx_ch, job.id, &pool),
+                Operation::CreatePortfolio(name) => return process_create_portfolio(tx_ch, job.id, name, &pool),
+                Operation::RenamePortfolio(name, new_name) => return process_rename_portfolio(tx_ch, job.id, name, new_name, &pool),
+                Operation::DeletePortfolio(name) => return process_delete_portfolio(tx_ch, job.id, name, &pool),
+                Operation::SelectPortfolio(name) => return process_select_portfolio(tx_ch, job.id, name, &pool),
+                Operation::GetProfile(symbol) => return process_get_profile(tx_ch, job.id, symbol, &pool).await,
+                Operation::ListWatchlists => return process_list_watchlists(tx_ch, job.id, &pool),
+                Operation::CreateWatchlist(name) => return process_create_watchlist(tx_ch, job.id, name, &pool),
+                Operation::DeleteWatchlist(name) => return process_delete_watchlist(tx_ch, job.id, name, &pool),
+                Operation::GetWatchlist(name) => return process_get_watchlist(tx_ch, job.id, name, &pool),
+                Operation::AddToWatchlist(name, item) => return process_add_to_watchlist(tx_ch, job.id, name, item, &pool),
+                Operation::UpdateWatchlistItem(name, item) => return process_update_watchlist_item(tx_ch, job.id, name, item, &pool),
+                Operation::RemoveFromWatchlist(name, symbol) => return process_remove_from_watchlist(tx_ch, job.id, name, symbol, &pool),
+                Operation::AddAlert(alert) => return process_add_alert(tx_ch, job.id, alert, &pool),
+                Operation::DeleteAlert(alert_id) => return process_delete_alert(tx_ch, job.id, *alert_id, &pool),
+                Operation::ListAlerts => return process_list_alerts(tx_ch, job.id, &pool),
+                Operation::AddWebhook(webhook) => return process_add_webhook(tx_ch, job.id, webhook, &pool),
+                Operation::DeleteWebhook(webhook_id) => return process_delete_webhook(tx_ch, job.id, *webhook_id, &pool),
+                Operation::ListWebhooks => return process_list_webhooks(tx_ch, job.id, &pool),
+                Operation::GetDeliveries(alert_id) => return process_get_deliveries(tx_ch, job.id, *alert_id, &pool),
+                // The server keeps track of the subscriptions
+                Operation::Subscribe(_) | Operation::Unsubscribe => return send_wrapped_response(&tx_ch, job.id, "true"),
+                Operation::Help => return process_help(tx_ch, job.id),
+                _ => {}
+            }
+
+            let portfolio = match repository::get_portfolio(&pool.get().unwrap(), portfolio_name.as_deref()) {
+                Ok(Some(portfolio)) => portfolio.id,
+                _ => {
+                    error!(target: "Main", "Portfolio {:?} not found", portfolio_name);
+                    return send_wrapped_response(&tx_ch, job.id, "false");
+                }
+            };
+
             match operation {
-                Operation::GetPortfolio => process_get_portfolio(tx_ch, job.id, &pool),
+                Operation::GetPortfolio => process_get_portfolio(tx_ch, job.id, portfolio, &pool),
+                Operation::GetPortfolioByMarket => process_get_portfolio_by_market(tx_ch, job.id, portfolio, &pool),
                 Operation::ListAvailable => process_list_available(tx_ch, job.id).await,
-                Operation::AddStock(stock) => process_add_stock(tx_ch, job.id, &stock, &pool),
-                Operation::DeleteStock(symbol) => process_delete_stock(tx_ch, job.id, &symbol, &pool),
-                Operation::Help => process_help(tx_ch, job.id),
+                Operation::UpdatePrices => process_update_prices(tx_ch, job.id, &events, &pool).await,
+                Operation::AddStock(stock) => process_add_stock(tx_ch, job.id, portfolio, &stock, &pool),
+                Operation::UpdateStock(stock) => process_update_stock(tx_ch, job.id, portfolio, &stock, &pool),
+                Operation::DeleteStock(symbol) => process_delete_stock(tx_ch, job.id, portfolio, &symbol, &pool),
+                Operation::ListMarkets => process_list_markets(tx_ch, job.id, &pool),
+                Operation::AddMarket(market) => process_add_market(tx_ch, job.id, &market, &pool),
+                Operation::UpdateMarket(market) => process_update_market(tx_ch, job.id, &market, &pool),
+                Operation::DeleteMarket(id) => process_delete_market(tx_ch, job.id, id, &pool),
+                Operation::AddHoliday(holiday) => process_add_holiday(tx_ch, job.id, &holiday, &pool),
+                Operation::RemoveHoliday(market_id, date) => process_remove_holiday(tx_ch, job.id, market_id, date, &pool),
+                Operation::ListHolidays(market_id) => process_list_holidays(tx_ch, job.id, market_id, &pool),
+                Operation::GetValuation => process_get_valuation(tx_ch, job.id, portfolio, &pool),
+                Operation::SetBaseCurrency(currency) => process_set_base_currency(tx_ch, job.id, &currency, &pool),
+                Operation::SetRiskFreeRate(rate) => process_set_risk_free_rate(tx_ch, job.id, rate, &pool),
+                Operation::GetFxHistory(base, quote) => process_get_fx_history(tx_ch, job.id, &base, &quote, &pool),
+                Operation::Deposit(movement) => process_deposit(tx_ch, job.id, portfolio, &movement, &pool),
+                Operation::Withdraw(movement) => process_withdraw(tx_ch, job.id, portfolio, &movement, &pool),
+                Operation::GetCash => process_get_cash(tx_ch, job.id, portfolio, &pool),
+                Operation::GetCashMovements => process_get_cash_movements(tx_ch, job.id, portfolio, &pool),
+                Operation::Buy(transaction) => process_buy(tx_ch, job.id, portfolio, &transaction, &pool),
+                Operation::Sell(transaction) => process_sell(tx_ch, job.id, portfolio, &transaction, &pool),
+                Operation::GetTransactions(symbol) => process_get_transactions(tx_ch, job.id, portfolio, symbol.as_deref(), &pool),
+                Operation::GetTaxReport(year, csv) => process_get_tax_report(tx_ch, job.id, portfolio, year, csv, &pool),
+                Operation::PlaceOrder(order) => process_place_order(tx_ch, job.id, portfolio, &order, &pool),
+                Operation::CancelOrder(order_id) => process_cancel_order(tx_ch, job.id, portfolio, order_id, &pool),
+                Operation::ListOrders(all) => process_list_orders(tx_ch, job.id, portfolio, all, &pool),
+                Operation::SetSlippage(slippage) => process_set_slippage(tx_ch, job.id, slippage, &pool),
+                Operation::SetCommission(commission) => process_set_commission(tx_ch, job.id, commission, &pool),
+                Operation::AddDividend(dividend) => process_add_dividend(tx_ch, job.id, portfolio, &dividend, &pool),
+                Operation::GetDividends(symbol) => process_get_dividends(tx_ch, job.id, portfolio, symbol.as_deref(), &pool),
+                Operation::ImportDividends(symbol) => process_import_dividends(tx_ch, job.id, portfolio, symbol.as_deref(), &pool).await,
+                Operation::ProcessDividends => process_process_dividends(tx_ch, job.id, portfolio, &pool),
+                Operation::GetDividendReport => process_get_dividend_report(tx_ch, job.id, portfolio, &pool),
+                Operation::AddPlan(plan) => process_add_plan(tx_ch, job.id, portfolio, &plan, &pool),
+                Operation::DeletePlan(plan_id) => process_delete_plan(tx_ch, job.id, portfolio, plan_id, &pool),
+                Operation::ListPlans => process_list_plans(tx_ch, job.id, portfolio, &pool),
+                Operation::RunPlans => process_run_plans(tx_ch, job.id, portfolio, &pool),
+                Operation::GetPlanReport => process_get_plan_report(tx_ch, job.id, portfolio, &pool),
+                Operation::AddCorporateAction(action) => process_add_corporate_action(tx_ch, job.id, portfolio, &action, &pool),
+                Operation::GetCorporateActions(symbol) => process_get_corporate_actions(tx_ch, job.id, portfolio, symbol.as_deref(), &pool),
+                Operation::ImportHistory(symbol) => process_import_history(tx_ch, job.id, portfolio, symbol.as_deref(), &pool).await,
+                Operation::GetHistory(symbol, raw) => process_get_history(tx_ch, job.id, &symbol, raw, &pool),
+                Operation::GetIndicator(symbol, indicator) => process_get_indicator(tx_ch, job.id, &symbol, indicator, &pool),
+                Operation::GetAllocation(dimension) => process_get_allocation(tx_ch, job.id, portfolio, dimension, &pool),
+                Operation::SetTarget(target) => process_set_target(tx_ch, job.id, portfolio, &target, &pool),
+                Operation::RemoveTarget(dimension, key) => process_remove_target(tx_ch, job.id, portfolio, dimension, &key, &pool),
+                Operation::ListTargets => process_list_targets(tx_ch, job.id, portfolio, &pool),
+                Operation::Rebalance(options) => process_rebalance(tx_ch, job.id, portfolio, &options, &pool),
+                Operation::SetStop(stop) => process_set_stop(tx_ch, job.id, portfolio, &stop, &pool),
+                Operation::RemoveStop(symbol) => process_remove_stop(tx_ch, job.id, portfolio, &symbol, &pool),
+                Operation::ListStops => process_list_stops(tx_ch, job.id, portfolio, &pool),
+                Operation::GetSummary(text) => process_get_summary(tx_ch, job.id, portfolio, text, &pool),
+                Operation::GetReturns(period) => process_get_returns(tx_ch, job.id, portfolio, period, &pool),
+                Operation::GetRisk(benchmark, period) => process_get_risk(tx_ch, job.id, portfolio, benchmark.as_deref(), period, &pool),
+                Operation::ListBenchmarks => process_list_benchmarks(tx_ch, job.id, portfolio, &pool),
+                Operation::AddBenchmark(symbol) => process_add_benchmark(tx_ch, job.id, portfolio, &symbol, &pool),
+                Operation::RemoveBenchmark(symbol) => process_remove_benchmark(tx_ch, job.id, portfolio, &symbol, &pool),
+                Operation::Compare(period) => process_compare(tx_ch, job.id, portfolio, period, &pool),
+                Operation::GetCorrelation(period, threshold) => process_get_correlation(tx_ch, job.id, portfolio, period, threshold, &pool),
+                Operation::Backtest(config) => process_backtest(tx_ch, job.id, &config, &pool),
+                Operation::TagStock(symbol, tag) => process_tag_stock(tx_ch, job.id, portfolio, &symbol, &tag, &pool),
+                Operation::UntagStock(symbol, tag) => process_untag_stock(tx_ch, job.id, portfolio, &symbol, &tag, &pool),
+                Operation::ImportProfiles(symbol) => process_import_profiles(tx_ch, job.id, portfolio, symbol.as_deref(), &pool).await,
                 _ => {}
             }
         }).await.unwrap()
     }
-
-    Ok(())
 }
 
 async fn process_list_available(tx: Sender<Vec<u8>>, id: u32) {
@@ -147,69 +925,1254 @@ async fn process_list_available(tx: Sender<Vec<u8>>, id: u32) {
     send_response(&tx, id, list_json);
 }
 
-fn process_add_stock(tx: Sender<Vec<u8>>, id: u32, stock: &Stock, pool: &r2d2::Pool<SqliteConnectionManager>) {
+fn process_add_stock(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, stock: &Stock, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::add_stock(&connection, portfolio, stock)
+        .map(|_| { "true"})
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_update_stock(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, stock: &Stock, pool: &r2d2::Pool<SqliteConnectionManager>) {
     let connection = pool.get().unwrap();
-    let response = repository::add_stock(&connection, stock)
+    let response = repository::update_stock(&connection, portfolio, stock)
         .map(|_| { "true"})
-        .or_else::<&str, _>(|_| { Ok("false") })
-        .unwrap();
-    send_wrapped_response(&tx, id, &response);
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
 }
 
-fn process_delete_stock(tx: Sender<Vec<u8>>, id: u32, stock: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+fn process_delete_stock(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, stock: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
     let connection = pool.get().unwrap();
-    let response = repository::delete_stock(&connection, stock)
+    let response = repository::delete_stock(&connection, portfolio, stock)
         .map(|_| { "true" })
-        .or_else::<&str, _>(|_| { Ok("false") })
-        .unwrap();
+        .unwrap_or("false");
 
     send_wrapped_response(&tx, id, response);
 }
 
-fn process_get_portfolio(tx: Sender<Vec<u8>>, id: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+fn process_get_portfolio(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
     let connection = pool.get().unwrap();
-    let response = repository::get_stored_stocks(&connection).unwrap();
+    let response = repository::get_stored_stocks(&connection, portfolio).unwrap();
 
     let serialized_response = serde_json::to_vec(&response).unwrap();
     send_response(&tx, id, serialized_response);
 }
 
-fn process_help(tx: Sender<Vec<u8>>, id: u32) {
-    let response = format!("Available commands:{}, {}, {}",
-        Operation::ListAvailable.to_string(), 
-        Operation::GetPortfolio.to_string(),
-        Operation::Help.to_string());
-    
-    send_wrapped_response(&tx, id, response.as_str());
+fn process_get_portfolio_by_market(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_portfolio_by_market(&connection, portfolio).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
 }
 
-fn send_wrapped_response(tx: &Sender<Vec<u8>>, id: u32, response: &str) {
-    let wrapped = ResponseWrapper {
-        response: response
-    };
+// Updates the prices and the exchange rates, filling the paper orders against the quotes,
+// then pushes the alerts and the stops they fire
+async fn process_update_prices(tx: Sender<Vec<u8>>, id: u32, events: &Sender<Event>, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let http_client = get_hyper_connection();
+    // Every portfolio is updated, whichever connection asked for it. The plans are on
+    // holdings, so their stocks are quoted with them.
+    let portfolios = repository::get_portfolios(&connection).unwrap();
+    let mut stocks = vec![];
+    let mut cash = vec![];
+    let mut symbols = BTreeSet::new();
+    for portfolio in portfolios.iter() {
+        stocks.extend(repository::get_stored_stocks(&connection, portfolio.id).unwrap());
+        cash.extend(repository::get_cash_balances(&connection, portfolio.id).unwrap());
+        symbols.extend(repository::get_benchmarks(&connection, portfolio.id).unwrap());
+    }
+    symbols.extend(stocks.iter().map(|stock| stock.symbol.clone()));
+    symbols.extend(repository::get_watched_symbols(&connection).unwrap());
+    symbols.extend(repository::get_alert_symbols(&connection).unwrap());
+    symbols.extend(repository::get_order_symbols(&connection).unwrap());
+    let mut updated = true;
 
-    info!(target: "Main", "Wrapped response: {:?}", &wrapped);
-    let serialized = serde_json::to_vec(&wrapped).unwrap();
-    send_response(tx, id, serialized);
+    for symbol in symbols.iter() {
+        match repository::get_current_price(&http_client, symbol).await {
+            Ok(price) => match repository::update_price(&connection, symbol, price) {
+                Ok(_) => {
+                    send_event(events, Event::Price { symbol: symbol.clone(), price, date: repository::today() });
+                    match repository::fill_orders(&connection, symbol, price, repository::today()) {
+                        Ok(closed) => for order in closed {
+                            send_event(events, Event::Order(order));
+                        },
+                        Err(e) => {
+                            error!(target: "Main", "Couldn't fill the orders of {}: {}", symbol, e);
+                            updated = false;
+                        }
+                    }
+                },
+                Err(_) => updated = false,
+            },
+            Err(e) => {
+                error!(target: "Main", "Couldn't get the price of {}: {}", symbol, e);
+                updated = false;
+            }
+        }
+    }
+
+    // The cash is valued in the base currency too, even with no stocks in its currency
+    let base_currency = repository::get_base_currency(&connection).unwrap();
+    let mut currencies = repository::get_currencies(&stocks);
+    currencies.extend(cash.into_iter().map(|balance| balance.currency));
+    for currency in currencies {
+        if currency.is_empty() || currency == base_currency {
+            continue;
+        }
+
+        match repository::get_current_fx_rate(&http_client, &currency, &base_currency).await {
+            Ok(fx_rate) => updated &= repository::add_fx_rate(&connection, &fx_rate).is_ok(),
+            Err(e) => {
+                error!(target: "Main", "Couldn't get the rate of {}{}: {}", currency, base_currency, e);
+                updated = false;
+            }
+        }
+    }
+
+    for portfolio in portfolios.iter() {
+        updated &= repository::process_dividends(&connection, portfolio.id, repository::today()).is_ok();
+        updated &= repository::run_plans(&connection, portfolio.id, repository::today()).is_ok();
+    }
+
+    match repository::evaluate_alerts(&connection, repository::today()) {
+        Ok(fired) => for alert in fired {
+            let webhooks = repository::get_alert_webhooks(&connection, alert.alert_id).unwrap_or_default();
+            let event = Event::Alert(alert);
+            let payload = serde_json::to_vec(&event).unwrap();
+            for webhook in webhooks {
+                tokio::spawn(process_webhook_delivery(webhook, payload.clone(), pool.clone()));
+            }
+            send_event(events, event);
+        },
+        Err(e) => {
+            error!(target: "Main", "Couldn't evaluate the alerts: {}", e);
+            updated = false;
+        }
+    }
+
+    match repository::evaluate_stops(&connection, repository::today()) {
+        Ok(crossed) => for stop in crossed {
+            send_event(events, Event::Stop(stop));
+        },
+        Err(e) => {
+            error!(target: "Main", "Couldn't evaluate the stops: {}", e);
+            updated = false;
+        }
+    }
+
+    send_wrapped_response(&tx, id, if updated { "true" } else { "false" });
 }
 
-fn send_response(tx: &Sender<Vec<u8>>, id: u32, response: Vec<u8>) {        
-    info!(target: "Main", "Sending to connection {}: {:?}", id, &response);
-    
-    let job_response = Job {
-        id,
-        payload: response
-    };
-    
-    let bytes = job_response.to_bytes();
-    tx.send(bytes).unwrap();
+fn process_add_alert(tx: Sender<Vec<u8>>, id: u32, alert: &Alert, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::add_alert(&connection, alert)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
 }
 
-#[test]
-fn test_str_to_operation_list_available() {
-    let raw = "list_available".to_owned();
-    let operation = Operation::from(raw);
-    assert_eq!(Operation::ListAvailable, operation);
+fn process_delete_alert(tx: Sender<Vec<u8>>, id: u32, alert_id: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::delete_alert(&connection, alert_id)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_list_alerts(tx: Sender<Vec<u8>>, id: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_alerts(&connection).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_add_webhook(tx: Sender<Vec<u8>>, id: u32, webhook: &Webhook, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::add_webhook(&connection, webhook)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_delete_webhook(tx: Sender<Vec<u8>>, id: u32, webhook_id: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::delete_webhook(&connection, webhook_id)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_list_webhooks(tx: Sender<Vec<u8>>, id: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_webhooks(&connection).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_get_deliveries(tx: Sender<Vec<u8>>, id: u32, alert_id: Option<u32>, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_deliveries(&connection, alert_id).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+// Posts an alert to a webhook in the background, so the retries don't hold up the
+// price update, and records every attempt
+async fn process_webhook_delivery(webhook: Webhook, payload: Vec<u8>, pool: r2d2::Pool<SqliteConnectionManager>) {
+    let http_client = get_hyper_connection();
+    let deliveries = repository::deliver_webhook(&http_client, &webhook, &payload, webhook::BACKOFF, webhook::TIMEOUT).await;
+
+    if let Err(e) = repository::add_deliveries(&pool.get().unwrap(), &deliveries) {
+        error!(target: "Main", "Couldn't record the deliveries to webhook {}: {}", webhook.id, e);
+    }
+}
+
+fn process_list_markets(tx: Sender<Vec<u8>>, id: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_stored_markets(&connection).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_add_market(tx: Sender<Vec<u8>>, id: u32, market: &Market, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::add_market(&connection, market)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_update_market(tx: Sender<Vec<u8>>, id: u32, market: &Market, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::update_market(&connection, market)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_delete_market(tx: Sender<Vec<u8>>, id: u32, market_id: u16, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::delete_market(&connection, market_id)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_add_holiday(tx: Sender<Vec<u8>>, id: u32, holiday: &MarketHoliday, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::add_holiday(&connection, holiday)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_remove_holiday(tx: Sender<Vec<u8>>, id: u32, market_id: u16, date: chrono::NaiveDate, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::remove_holiday(&connection, market_id, date)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_list_holidays(tx: Sender<Vec<u8>>, id: u32, market_id: u16, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_holidays(&connection, market_id).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_get_valuation(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_valuation(&connection, portfolio).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_set_base_currency(tx: Sender<Vec<u8>>, id: u32, currency: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::set_base_currency(&connection, currency)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_set_risk_free_rate(tx: Sender<Vec<u8>>, id: u32, rate: decimal::Decimal, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::set_risk_free_rate(&connection, rate)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_get_fx_history(tx: Sender<Vec<u8>>, id: u32, base: &str, quote: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_fx_history(&connection, base, quote).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_deposit(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, movement: &CashMovement, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::deposit(&connection, portfolio, movement)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_withdraw(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, movement: &CashMovement, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::withdraw(&connection, portfolio, movement)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_get_cash(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_cash_balances(&connection, portfolio).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_get_cash_movements(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_cash_movements(&connection, portfolio).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_buy(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, transaction: &Transaction, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::buy_stock(&connection, portfolio, transaction)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_sell(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, transaction: &Transaction, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::sell_stock(&connection, portfolio, transaction)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_get_transactions(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, symbol: Option<&str>, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_transactions(&connection, portfolio, symbol).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+// The report goes as JSON, or as CSV with a row per lot when asked for it
+fn process_get_tax_report(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, year: i32, csv: bool, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_tax_report(&connection, portfolio, year).unwrap();
+
+    let serialized_response = if csv {
+        response.to_csv().into_bytes()
+    } else {
+        serde_json::to_vec(&response).unwrap()
+    };
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_place_order(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, order: &Order, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::place_order(&connection, portfolio, order)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_cancel_order(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, order_id: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::cancel_order(&connection, portfolio, order_id)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_list_orders(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, all: bool, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_orders(&connection, portfolio, all).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_set_slippage(tx: Sender<Vec<u8>>, id: u32, slippage: decimal::Decimal, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::set_slippage(&connection, slippage)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_set_commission(tx: Sender<Vec<u8>>, id: u32, commission: decimal::Decimal, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::set_commission(&connection, commission)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_add_dividend(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, dividend: &Dividend, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::add_dividend(&connection, portfolio, dividend)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_get_dividends(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, symbol: Option<&str>, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_dividends(&connection, portfolio, symbol).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+async fn process_import_dividends(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, symbol: Option<&str>, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let http_client = get_hyper_connection();
+    let symbols = match symbol {
+        Some(symbol) => vec![symbol.to_owned()],
+        None => repository::get_stored_stocks(&connection, portfolio).unwrap()
+            .into_iter()
+            .map(|stock| stock.symbol)
+            .collect(),
+    };
+    let mut imported = true;
+
+    for symbol in symbols {
+        match repository::get_dividend_calendar(&http_client, &symbol).await {
+            Ok(dividends) => {
+                for dividend in dividends {
+                    imported &= repository::add_dividend(&connection, portfolio, &dividend).is_ok();
+                }
+            },
+            Err(e) => {
+                error!(target: "Main", "Couldn't get the dividends of {}: {}", symbol, e);
+                imported = false;
+            }
+        }
+    }
+
+    send_wrapped_response(&tx, id, if imported { "true" } else { "false" });
+}
+
+fn process_process_dividends(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::process_dividends(&connection, portfolio, repository::today()).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_add_plan(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, plan: &InvestmentPlan, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::add_plan(&connection, portfolio, plan)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_delete_plan(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, plan_id: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::delete_plan(&connection, portfolio, plan_id)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_list_plans(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_plans(&connection, portfolio).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_run_plans(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::run_plans(&connection, portfolio, repository::today()).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_get_plan_report(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_plan_report(&connection, portfolio, repository::today()).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_get_dividend_report(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_dividend_report(&connection, portfolio).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_add_corporate_action(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, action: &CorporateAction, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::add_corporate_action(&connection, portfolio, action)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_get_corporate_actions(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, symbol: Option<&str>, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_corporate_actions(&connection, portfolio, symbol).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+async fn process_import_history(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, symbol: Option<&str>, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let http_client = get_hyper_connection();
+    let symbols = match symbol {
+        Some(symbol) => vec![symbol.to_owned()],
+        None => repository::get_stored_stocks(&connection, portfolio).unwrap()
+            .into_iter()
+            .map(|stock| stock.symbol)
+            .chain(repository::get_benchmarks(&connection, portfolio).unwrap())
+            .collect(),
+    };
+    let mut imported = true;
+
+    for symbol in symbols {
+        match repository::get_historical_prices(&http_client, &symbol).await {
+            Ok(bars) => imported &= repository::add_price_history(&connection, &bars).is_ok(),
+            Err(e) => {
+                error!(target: "Main", "Couldn't get the price history of {}: {}", symbol, e);
+                imported = false;
+            }
+        }
+    }
+
+    send_wrapped_response(&tx, id, if imported { "true" } else { "false" });
+}
+
+fn process_get_history(tx: Sender<Vec<u8>>, id: u32, symbol: &str, raw: bool, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_price_history(&connection, symbol, raw).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_get_indicator(tx: Sender<Vec<u8>>, id: u32, symbol: &str, indicator: Indicator, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_indicator(&connection, symbol, indicator).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_list_portfolios(tx: Sender<Vec<u8>>, id: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_portfolios(&connection).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_create_portfolio(tx: Sender<Vec<u8>>, id: u32, name: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::add_portfolio(&connection, name)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_rename_portfolio(tx: Sender<Vec<u8>>, id: u32, name: &str, new_name: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::rename_portfolio(&connection, name, new_name)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_delete_portfolio(tx: Sender<Vec<u8>>, id: u32, name: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::delete_portfolio(&connection, name)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+// Only checks the portfolio exists, the connection keeps track of its selection
+fn process_select_portfolio(tx: Sender<Vec<u8>>, id: u32, name: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = match repository::get_portfolio(&connection, Some(name)) {
+        Ok(Some(_)) => "true",
+        _ => "false",
+    };
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_get_allocation(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, dimension: AllocationDimension, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_allocation(&connection, portfolio, dimension).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_set_target(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, target: &AllocationTarget, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::set_target(&connection, portfolio, target)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_remove_target(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, dimension: AllocationDimension, key: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::remove_target(&connection, portfolio, dimension, key)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_list_targets(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_targets(&connection, portfolio).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_set_stop(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, stop: &HoldingStop, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::set_stop(&connection, portfolio, stop)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_remove_stop(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, symbol: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::remove_stop(&connection, portfolio, symbol)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_list_stops(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_stops(&connection, portfolio).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_rebalance(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, options: &RebalanceOptions, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_rebalance_plan(&connection, portfolio, options).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+// The summary goes as JSON, or as a table when asked for text
+fn process_get_summary(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, text: bool, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_summary(&connection, portfolio).unwrap();
+
+    let serialized_response = if text {
+        response.to_string().into_bytes()
+    } else {
+        serde_json::to_vec(&response).unwrap()
+    };
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_get_returns(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, period: ReturnPeriod, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_returns(&connection, portfolio, period).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_get_risk(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, benchmark: Option<&str>, period: ReturnPeriod, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_risk(&connection, portfolio, benchmark, period).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_list_benchmarks(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_benchmarks(&connection, portfolio).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_add_benchmark(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, symbol: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::add_benchmark(&connection, portfolio, symbol)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_remove_benchmark(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, symbol: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::remove_benchmark(&connection, portfolio, symbol)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_compare(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, period: ReturnPeriod, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_comparison(&connection, portfolio, period).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_get_correlation(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, period: ReturnPeriod, threshold: decimal::Decimal, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_correlation(&connection, portfolio, period, threshold).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_backtest(tx: Sender<Vec<u8>>, id: u32, config: &BacktestConfig, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::run_backtest(&connection, config).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_tag_stock(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, symbol: &str, tag: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::tag_stock(&connection, portfolio, symbol, tag)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_untag_stock(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, symbol: &str, tag: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::untag_stock(&connection, portfolio, symbol, tag)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+async fn process_import_profiles(tx: Sender<Vec<u8>>, id: u32, portfolio: u32, symbol: Option<&str>, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let http_client = get_hyper_connection();
+    let stocks = repository::get_stored_stocks(&connection, portfolio).unwrap()
+        .into_iter()
+        .filter(|stock| symbol.is_none_or(|symbol| stock.symbol == symbol));
+    let mut imported = true;
+
+    for mut stock in stocks {
+        match repository::fill_stock_profile(&http_client, &mut stock).await {
+            Ok(true) => imported &= repository::update_stock(&connection, portfolio, &stock).is_ok(),
+            Ok(false) => {
+                error!(target: "Main", "There's no profile of {}", stock.symbol);
+                imported = false;
+            },
+            Err(e) => {
+                error!(target: "Main", "Couldn't get the profile of {}: {}", stock.symbol, e);
+                imported = false;
+            }
+        }
+    }
+
+    send_wrapped_response(&tx, id, if imported { "true" } else { "false" });
+}
+
+// Answers with the cached profile while it's fresh, or with the stale one if the API fails
+async fn process_get_profile(tx: Sender<Vec<u8>>, id: u32, symbol: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let cached = repository::get_cached_profile(&connection, symbol).unwrap_or_default();
+
+    let profile = match cached {
+        Some(profile) if profile.is_fresh(chrono::Local::now().naive_local()) => Some(profile),
+        cached => {
+            let http_client = get_hyper_connection();
+            match repository::get_company_profile(&http_client, symbol).await {
+                Ok(Some(profile)) => {
+                    if let Err(e) = repository::cache_profile(&connection, &profile) {
+                        error!(target: "Main", "Couldn't cache the profile of {}: {}", symbol, e);
+                    }
+                    Some(profile)
+                },
+                Ok(None) => cached,
+                Err(e) => {
+                    error!(target: "Main", "Couldn't get the profile of {}: {}", symbol, e);
+                    cached
+                }
+            }
+        }
+    };
+
+    match profile {
+        Some(profile) => send_response(&tx, id, serde_json::to_vec(&profile).unwrap()),
+        None => send_wrapped_response(&tx, id, "false"),
+    }
+}
+
+fn process_list_watchlists(tx: Sender<Vec<u8>>, id: u32, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::get_watchlists(&connection).unwrap();
+
+    let serialized_response = serde_json::to_vec(&response).unwrap();
+    send_response(&tx, id, serialized_response);
+}
+
+fn process_create_watchlist(tx: Sender<Vec<u8>>, id: u32, name: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::add_watchlist(&connection, name)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_delete_watchlist(tx: Sender<Vec<u8>>, id: u32, name: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::delete_watchlist(&connection, name)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_get_watchlist(tx: Sender<Vec<u8>>, id: u32, name: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    match repository::get_watchlist(&connection, name) {
+        Ok(response) => send_response(&tx, id, serde_json::to_vec(&response).unwrap()),
+        Err(_) => send_wrapped_response(&tx, id, "false"),
+    }
+}
+
+fn process_add_to_watchlist(tx: Sender<Vec<u8>>, id: u32, name: &str, item: &WatchlistItem, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::add_to_watchlist(&connection, name, item)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_update_watchlist_item(tx: Sender<Vec<u8>>, id: u32, name: &str, item: &WatchlistItem, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::update_watchlist_item(&connection, name, item)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_remove_from_watchlist(tx: Sender<Vec<u8>>, id: u32, name: &str, symbol: &str, pool: &r2d2::Pool<SqliteConnectionManager>) {
+    let connection = pool.get().unwrap();
+    let response = repository::remove_from_watchlist(&connection, name, symbol)
+        .map(|_| { "true" })
+        .unwrap_or("false");
+    send_wrapped_response(&tx, id, response);
+}
+
+fn process_help(tx: Sender<Vec<u8>>, id: u32) {
+    let commands = [
+        Operation::ListAvailable,
+        Operation::GetPortfolio,
+        Operation::GetPortfolioByMarket,
+        Operation::UpdatePrices,
+        Operation::AddStock(Stock::default()),
+        Operation::UpdateStock(Stock::default()),
+        Operation::DeleteStock(String::new()),
+        Operation::ListMarkets,
+        Operation::AddMarket(Market::default()),
+        Operation::UpdateMarket(Market::default()),
+        Operation::DeleteMarket(0),
+        Operation::AddHoliday(MarketHoliday::default()),
+        Operation::RemoveHoliday(0, repository::today()),
+        Operation::ListHolidays(0),
+        Operation::GetValuation,
+        Operation::SetBaseCurrency(String::new()),
+        Operation::SetRiskFreeRate(decimal::Decimal::zero()),
+        Operation::GetFxHistory(String::new(), String::new()),
+        Operation::Deposit(CashMovement::default()),
+        Operation::Withdraw(CashMovement::default()),
+        Operation::GetCash,
+        Operation::GetCashMovements,
+        Operation::Buy(Transaction::default()),
+        Operation::Sell(Transaction::default()),
+        Operation::GetTransactions(None),
+        Operation::GetTaxReport(2020, false),
+        Operation::PlaceOrder(Order::default()),
+        Operation::CancelOrder(0),
+        Operation::ListOrders(false),
+        Operation::SetSlippage(decimal::Decimal::zero()),
+        Operation::SetCommission(decimal::Decimal::zero()),
+        Operation::AddDividend(Dividend::default()),
+        Operation::GetDividends(None),
+        Operation::ImportDividends(None),
+        Operation::ProcessDividends,
+        Operation::GetDividendReport,
+        Operation::AddPlan(InvestmentPlan::default()),
+        Operation::DeletePlan(0),
+        Operation::ListPlans,
+        Operation::RunPlans,
+        Operation::GetPlanReport,
+        Operation::AddCorporateAction(CorporateAction::default()),
+        Operation::GetCorporateActions(None),
+        Operation::ImportHistory(None),
+        Operation::GetHistory(String::new(), false),
+        Operation::GetIndicator(String::new(), Indicator::Sma(20)),
+        Operation::ListPortfolios,
+        Operation::CreatePortfolio(String::new()),
+        Operation::RenamePortfolio(String::new(), String::new()),
+        Operation::DeletePortfolio(String::new()),
+        Operation::SelectPortfolio(String::new()),
+        Operation::GetAllocation(AllocationDimension::Sector),
+        Operation::SetTarget(AllocationTarget {
+            dimension: AllocationDimension::Holding,
+            key: String::new(),
+            weight: decimal::Decimal::zero(),
+            tolerance: decimal::Decimal::zero(),
+        }),
+        Operation::RemoveTarget(AllocationDimension::Holding, String::new()),
+        Operation::ListTargets,
+        Operation::Rebalance(RebalanceOptions::default()),
+        Operation::SetStop(HoldingStop::default()),
+        Operation::RemoveStop(String::new()),
+        Operation::ListStops,
+        Operation::GetSummary(false),
+        Operation::GetReturns(ReturnPeriod::YearToDate),
+        Operation::GetRisk(None, ReturnPeriod::Year),
+        Operation::ListBenchmarks,
+        Operation::AddBenchmark(String::new()),
+        Operation::RemoveBenchmark(String::new()),
+        Operation::Compare(ReturnPeriod::YearToDate),
+        Operation::GetCorrelation(ReturnPeriod::Year, decimal::Decimal::new(8, 1)),
+        Operation::Backtest(BacktestConfig {
+            strategy: StrategyConfig::SmaCross(SmaCross::default()),
+            start: repository::today(),
+            end: repository::today(),
+            initial_cash: decimal::Decimal::zero(),
+            commission: decimal::Decimal::zero(),
+        }),
+        Operation::TagStock(String::new(), String::new()),
+        Operation::UntagStock(String::new(), String::new()),
+        Operation::ImportProfiles(None),
+        Operation::GetProfile(String::new()),
+        Operation::ListWatchlists,
+        Operation::CreateWatchlist(String::new()),
+        Operation::DeleteWatchlist(String::new()),
+        Operation::GetWatchlist(String::new()),
+        Operation::AddToWatchlist(String::new(), WatchlistItem::default()),
+        Operation::UpdateWatchlistItem(String::new(), WatchlistItem::default()),
+        Operation::RemoveFromWatchlist(String::new(), String::new()),
+        Operation::AddAlert(Alert {
+            id: 0,
+            symbol: String::new(),
+            condition: AlertCondition::Above(decimal::Decimal::zero()),
+            last_triggered: None,
+        }),
+        Operation::DeleteAlert(0),
+        Operation::ListAlerts,
+        Operation::AddWebhook(Webhook::default()),
+        Operation::DeleteWebhook(0),
+        Operation::ListWebhooks,
+        Operation::GetDeliveries(None),
+        Operation::Subscribe(vec![]),
+        Operation::Unsubscribe,
+        Operation::Help,
+    ];
+    let response = format!("Available commands: {}",
+        commands.iter().map(Operation::to_string).collect::<Vec<String>>().join(", "));
+    
+    send_wrapped_response(&tx, id, response.as_str());
+}
+
+fn send_wrapped_response(tx: &Sender<Vec<u8>>, id: u32, response: &str) {
+    let wrapped = ResponseWrapper {
+        response
+    };
+
+    info!(target: "Main", "Wrapped response: {:?}", &wrapped);
+    let serialized = serde_json::to_vec(&wrapped).unwrap();
+    send_response(tx, id, serialized);
+}
+
+fn send_response(tx: &Sender<Vec<u8>>, id: u32, response: Vec<u8>) {        
+    info!(target: "Main", "Sending to connection {}: {:?}", id, &response);
+    
+    let job_response = Job {
+        id,
+        payload: response
+    };
+    
+    let bytes = job_response.to_bytes();
+    tx.send(bytes).unwrap();
+}
+
+// Pushes an event to the connections that subscribed to it
+fn send_event(events: &Sender<Event>, event: Event) {
+    info!(target: "Main", "Sending event: {:?}", event);
+    events.send(event).unwrap();
+}
+
+#[test]
+fn test_str_to_operation_list_available() {
+    let raw = "list_available".to_owned();
+    let operation = Operation::from(raw);
+    assert_eq!(Operation::ListAvailable, operation);
+}
+
+#[test]
+fn test_str_to_operation_plans() {
+    let plan = InvestmentPlan {
+        symbol: "VWCE".into(),
+        amount: decimal::Decimal::from(200),
+        day: 1,
+        start: chrono::NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(),
+        deposit: true,
+        ..InvestmentPlan::default()
+    };
+    assert_eq!(Operation::AddPlan(plan), Operation::from(r#"add_plan {"symbol": "vwce", "amount": "200", "start": "2021-01-01", "deposit": true}"#.to_owned()));
+    assert_eq!(Operation::DeletePlan(2), Operation::from("delete_plan 2".to_owned()));
+    assert_eq!(Operation::RemoveHoliday(1, chrono::NaiveDate::from_ymd_opt(2021, 12, 24).unwrap()), Operation::from("remove_holiday 1 2021-12-24".to_owned()));
+    assert_eq!(Operation::Error, Operation::from("remove_holiday 1".to_owned()));
+}
+
+#[test]
+fn test_str_to_operation_delete_market() {
+    let raw = "delete_market 3".to_owned();
+    let operation = Operation::from(raw);
+    assert_eq!(Operation::DeleteMarket(3), operation);
+}
+
+#[test]
+fn test_str_to_operation_add_market() {
+    let raw = r#"add_market {"id": 0, "symbol": "NYSE", "currency": "USD"}"#.to_owned();
+    let operation = Operation::from(raw);
+    assert_eq!(Operation::AddMarket(Market { id: 0, symbol: "NYSE".into(), currency: "USD".into() }), operation);
+}
+
+#[cfg(test)]
+proptest::proptest! {
+    #[test]
+    fn test_add_stock_round_trips_through_protocol(price in decimal::any_decimal()) {
+        let stock = Stock { symbol: "KO".into(), price, initial_price: price, ..Stock::default() };
+        let raw = format!("add_stock {}", serde_json::to_string(&stock).unwrap());
+        let operation = Operation::from(raw);
+
+        proptest::prop_assert_eq!(Operation::AddStock(stock), Operation::from_bytes(&operation.to_bytes()));
+    }
+}
+
+#[test]
+fn test_str_to_operation_get_fx_history() {
+    let raw = "get_fx_history eur usd".to_owned();
+    let operation = Operation::from(raw);
+    assert_eq!(Operation::GetFxHistory("EUR".into(), "USD".into()), operation);
+}
+
+#[test]
+fn test_str_to_operation_buy() {
+    let raw = r#"buy {"symbol": "KO", "quantity": "10", "price": "45.10", "date": "2020-05-04"}"#.to_owned();
+    let operation = Operation::from(raw);
+    assert_eq!(Operation::Buy(Transaction {
+        symbol: "KO".into(),
+        quantity: decimal::Decimal::from(10),
+        price: decimal::Decimal::new(4510, 2),
+        date: chrono::NaiveDate::from_ymd_opt(2020, 5, 4).unwrap(),
+        ..Transaction::default()
+    }), operation);
+}
+
+#[test]
+fn test_str_to_operation_get_history() {
+    assert_eq!(Operation::GetHistory("AAPL".into(), true), Operation::from("get_history AAPL raw".to_owned()));
+    assert_eq!(Operation::GetHistory("AAPL".into(), false), Operation::from("get_history AAPL".to_owned()));
+    assert_eq!(Operation::Error, Operation::from("get_history".to_owned()));
+}
+
+#[test]
+fn test_parse_request_portfolio() {
+    assert_eq!(Request { portfolio: Some("ira".into()), operation: Operation::GetCash },
+        Request::parse("@ira get_cash", Some("family")));
+    assert_eq!(Request { portfolio: Some("family".into()), operation: Operation::GetCash },
+        Request::parse("get_cash", Some("family")));
+    assert_eq!(Request { portfolio: None, operation: Operation::RenamePortfolio("ira".into(), "roth".into()) },
+        Request::parse("rename_portfolio ira roth", None));
+    assert_eq!(Operation::Error, Request::parse("@ira", None).operation);
+}
+
+#[test]
+fn test_str_to_operation_add_to_watchlist() {
+    let raw = r#"add_to_watchlist chips {"symbol": "AMD", "target_price": "80.5", "note": "buy the dip"}"#.to_owned();
+    let operation = Operation::from(raw);
+    assert_eq!(Operation::AddToWatchlist("chips".into(), WatchlistItem {
+        symbol: "AMD".into(),
+        target_price: Some(decimal::Decimal::new(805, 1)),
+        note: "buy the dip".into(),
+    }), operation);
+}
+
+#[test]
+fn test_str_to_operation_tax_report() {
+    assert_eq!(Operation::GetTaxReport(2021, false), Operation::from("tax_report 2021".to_owned()));
+    assert_eq!(Operation::GetTaxReport(2021, true), Operation::from("tax_report 2021 csv".to_owned()));
+    assert_eq!(Operation::Error, Operation::from("tax_report".to_owned()));
+    assert_eq!(Operation::Error, Operation::from("tax_report 2021 pdf".to_owned()));
+}
+
+#[test]
+fn test_str_to_operation_summary() {
+    assert_eq!(Operation::GetSummary(false), Operation::from("summary".to_owned()));
+    assert_eq!(Operation::GetSummary(true), Operation::from("summary text".to_owned()));
+    assert_eq!(Operation::Error, Operation::from("summary xml".to_owned()));
+}
+
+#[test]
+fn test_str_to_operation_returns() {
+    let date = |month, day| chrono::NaiveDate::from_ymd_opt(2020, month, day).unwrap();
+
+    assert_eq!(Operation::GetReturns(ReturnPeriod::Inception), Operation::from("returns".to_owned()));
+    assert_eq!(Operation::GetReturns(ReturnPeriod::MonthToDate), Operation::from("returns MTD".to_owned()));
+    assert_eq!(Operation::GetReturns(ReturnPeriod::Custom(date(1, 1), date(6, 30))), Operation::from("returns 2020-01-01 2020-06-30".to_owned()));
+    assert_eq!(Operation::Error, Operation::from("returns 2020-06-30 2020-01-01".to_owned()));
+    assert_eq!(Operation::Error, Operation::from("returns 2Y".to_owned()));
+}
+
+#[test]
+fn test_str_to_operation_risk() {
+    assert_eq!(Operation::GetRisk(None, ReturnPeriod::Year), Operation::from("risk".to_owned()));
+    assert_eq!(Operation::GetRisk(Some("SPY".into()), ReturnPeriod::YearToDate), Operation::from("risk spy YTD".to_owned()));
+    assert_eq!(Operation::SetRiskFreeRate(decimal::Decimal::new(25, 1)), Operation::from("set_risk_free_rate 2.5".to_owned()));
+}
+
+#[test]
+fn test_str_to_operation_compare() {
+    assert_eq!(Operation::Compare(ReturnPeriod::Year), Operation::from("compare 1Y".to_owned()));
+    assert_eq!(Operation::AddBenchmark("SPY".into()), Operation::from("add_benchmark spy".to_owned()));
+    assert_eq!(Operation::Error, Operation::from("compare 1Y 2Y 3Y".to_owned()));
+}
+
+#[test]
+fn test_str_to_operation_indicator() {
+    assert_eq!(Operation::GetIndicator("AAPL".into(), Indicator::Sma(50)), Operation::from("indicator aapl SMA 50".to_owned()));
+    assert_eq!(Operation::GetIndicator("AAPL".into(), Indicator::Bollinger(20, decimal::Decimal::new(25, 1))), Operation::from("indicator AAPL bollinger 20 2.5".to_owned()));
+    assert_eq!(Operation::Error, Operation::from("indicator AAPL vwap".to_owned()));
+}
+
+#[test]
+fn test_str_to_operation_backtest() {
+    let config = BacktestConfig {
+        strategy: StrategyConfig::SmaCross(SmaCross { symbol: "SPY".into(), fast: 50, slow: 200 }),
+        start: chrono::NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(),
+        end: chrono::NaiveDate::from_ymd_opt(2020, 12, 31).unwrap(),
+        initial_cash: decimal::Decimal::from(10000),
+        commission: decimal::Decimal::zero(),
+    };
+    let raw = r#"backtest {"strategy": {"sma_cross": {"symbol": "SPY", "fast": 50, "slow": 200}}, "start": "2015-01-01", "end": "2020-12-31", "initial_cash": "10000"}"#;
+    assert_eq!(Operation::Backtest(config), Operation::from(raw.to_owned()));
+}
+
+#[test]
+fn test_str_to_operation_correlation() {
+    assert_eq!(Operation::GetCorrelation(ReturnPeriod::Year, decimal::Decimal::new(8, 1)), Operation::from("correlation".to_owned()));
+    assert_eq!(Operation::GetCorrelation(ReturnPeriod::YearToDate, decimal::Decimal::new(9, 1)), Operation::from("correlation YTD 0.9".to_owned()));
+    assert_eq!(Operation::Error, Operation::from("correlation high".to_owned()));
+}
+
+#[test]
+fn test_str_to_operation_allocation() {
+    assert_eq!(Operation::GetAllocation(AllocationDimension::Market), Operation::from("allocation Market".to_owned()));
+    assert_eq!(Operation::Error, Operation::from("allocation color".to_owned()));
+}
+
+#[test]
+fn test_str_to_operation_rebalance() {
+    assert_eq!(Operation::RemoveTarget(AllocationDimension::Sector, "Consumer Defensive".into()), Operation::from("remove_target sector Consumer Defensive".to_owned()));
+    assert_eq!(Operation::Error, Operation::from("remove_target sector".to_owned()));
+    assert_eq!(Operation::Rebalance(RebalanceOptions::default()), Operation::from("rebalance".to_owned()));
+    let options = RebalanceOptions { dimension: AllocationDimension::Tag, cash_only: true, ..RebalanceOptions::default() };
+    assert_eq!(Operation::Rebalance(options), Operation::from(r#"rebalance {"dimension": "tag", "cash_only": true}"#.to_owned()));
+}
+
+#[test]
+fn test_str_to_operation_orders() {
+    let order = Order {
+        symbol: "AAPL".into(),
+        side: repository::transaction::TransactionKind::Sell,
+        order_type: repository::order::OrderType::Limit,
+        limit_price: Some(decimal::Decimal::from(150)),
+        quantity: decimal::Decimal::from(10),
+        time_in_force: repository::order::TimeInForce::Gtc,
+        date: repository::today(),
+        ..Order::default()
+    };
+    assert_eq!(Operation::PlaceOrder(order), Operation::from(r#"place_order {"symbol": "aapl", "side": "sell", "order_type": "limit", "limit_price": "150", "quantity": "10", "time_in_force": "gtc"}"#.to_owned()));
+    assert_eq!(Operation::CancelOrder(4), Operation::from("cancel_order 4".to_owned()));
+    assert_eq!(Operation::ListOrders(true), Operation::from("list_orders all".to_owned()));
+    assert_eq!(Operation::SetSlippage(decimal::Decimal::new(5, 2)), Operation::from("set_slippage 0.05".to_owned()));
+    assert_eq!(Operation::Error, Operation::from("set_commission".to_owned()));
+}
+
+#[test]
+fn test_str_to_operation_stops() {
+    let stop = HoldingStop { symbol: "AAPL".into(), trailing_percent: Some(decimal::Decimal::from(8)), sell: true, ..HoldingStop::default() };
+    assert_eq!(Operation::SetStop(stop), Operation::from(r#"set_stop {"symbol": "aapl", "trailing_percent": "8", "sell": true}"#.to_owned()));
+    assert_eq!(Operation::RemoveStop("AAPL".into()), Operation::from("remove_stop aapl".to_owned()));
+    assert_eq!(Operation::Error, Operation::from("remove_stop".to_owned()));
+}
+
+#[test]
+fn test_str_to_operation_alerts() {
+    let alert = Alert {
+        id: 0,
+        symbol: "AAPL".into(),
+        condition: AlertCondition::CrossAbove(Indicator::Sma(50)),
+        last_triggered: None,
+    };
+    assert_eq!(Operation::AddAlert(alert), Operation::from(r#"add_alert {"symbol": "AAPL", "condition": {"cross_above": {"sma": 50}}}"#.to_owned()));
+    assert_eq!(Operation::DeleteAlert(3), Operation::from("delete_alert 3".to_owned()));
+}
+
+#[test]
+fn test_str_to_operation_webhooks() {
+    let webhook = Webhook { alert_id: 2, url: "https://example.com/hook".into(), secret: "s3cret".into(), ..Webhook::default() };
+    assert_eq!(Operation::AddWebhook(webhook), Operation::from(r#"add_webhook {"alert_id": 2, "url": "https://example.com/hook", "secret": "s3cret"}"#.to_owned()));
+    assert_eq!(Operation::GetDeliveries(None), Operation::from("get_deliveries".to_owned()));
+    assert_eq!(Operation::GetDeliveries(Some(2)), Operation::from("get_deliveries 2".to_owned()));
+    assert_eq!(Operation::Error, Operation::from("delete_webhook".to_owned()));
+}
+
+#[test]
+fn test_str_to_operation_subscribe() {
+    assert_eq!(Operation::Subscribe(vec![]), Operation::from("subscribe".to_owned()));
+    assert_eq!(Operation::Subscribe(vec!["AAPL".into(), "MSFT".into()]), Operation::from("subscribe aapl msft".to_owned()));
+    assert_eq!(Operation::Unsubscribe, Operation::from("unsubscribe".to_owned()));
 }
 
 #[test]
@@ -0,0 +1,309 @@
+use std::collections::BTreeSet;
+use serde::{Serialize, Deserialize};
+use crate::allocation::AllocationDimension;
+use crate::decimal::Decimal;
+use crate::repository::{
+    stock::Stock,
+    cash::CashBalance,
+    target::AllocationTarget,
+    transaction::TransactionKind,
+};
+
+// How the trades are worked out
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct RebalanceOptions {
+    #[serde(default = "RebalanceOptions::default_dimension")]
+    pub dimension: AllocationDimension,
+    // Only buy, with the cash available
+    #[serde(default)]
+    pub cash_only: bool,
+    // Smallest trade worth making, in the base currency
+    #[serde(default)]
+    pub min_trade: Decimal,
+    // Quantities are multiples of the lot size
+    #[serde(default = "RebalanceOptions::default_lot_size")]
+    pub lot_size: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TargetDrift {
+    pub key: String,
+    pub symbols: Vec<String>,
+    pub value_base: Decimal,
+    // Percents of the holdings plus the cash, the drift in percentage points
+    pub weight: Decimal,
+    pub target_weight: Decimal,
+    pub tolerance: Decimal,
+    pub drift: Decimal,
+    pub out_of_band: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct SuggestedTrade {
+    pub symbol: String,
+    pub kind: TransactionKind,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub currency: String,
+    pub value_base: Decimal,
+}
+
+// Holdings without a target are left as they are
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct RebalancePlan {
+    pub dimension: AllocationDimension,
+    pub base_currency: String,
+    pub total_base: Decimal,
+    pub cash_base: Decimal,
+    pub drifts: Vec<TargetDrift>,
+    // Sells first
+    pub trades: Vec<SuggestedTrade>,
+    pub cash_after_base: Decimal,
+    // Currencies without exchange rate, their holdings and cash are left out
+    pub missing_rates: Vec<String>,
+}
+
+impl Default for RebalanceOptions {
+    fn default() -> Self {
+        RebalanceOptions {
+            dimension: Self::default_dimension(),
+            cash_only: false,
+            min_trade: Decimal::zero(),
+            lot_size: Self::default_lot_size(),
+        }
+    }
+}
+
+impl From<&str> for RebalanceOptions {
+    fn from(json: &str) -> Self {
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+}
+
+impl RebalanceOptions {
+    pub fn is_valid(&self) -> bool {
+        self.lot_size > Decimal::zero() && self.min_trade >= Decimal::zero()
+    }
+
+    fn default_dimension() -> AllocationDimension {
+        AllocationDimension::Holding
+    }
+
+    fn default_lot_size() -> Decimal {
+        Decimal::from(1)
+    }
+}
+
+fn percent(part: Decimal, whole: Decimal) -> Decimal {
+    (part * Decimal::from(100))
+        .checked_div(whole)
+        .unwrap_or_default()
+        .round_dp(2)
+}
+
+// Suggests the trades that bring the weights that drifted out of their band back to
+// their targets. A stock counts for the first of its groups with a target. `rate`
+// returns how many base currency units a unit of the given currency is worth.
+pub fn rebalance<F>(stocks: &[Stock], cash: &[CashBalance], targets: &[AllocationTarget], options: &RebalanceOptions, base_currency: &str, rate: F) -> RebalancePlan
+        where F: Fn(&str) -> Option<Decimal> {
+    let mut missing_rates = BTreeSet::new();
+
+    let mut cash_base = Decimal::zero();
+    for balance in cash {
+        match rate(&balance.currency) {
+            Some(rate) => cash_base += balance.balance * rate,
+            None => { missing_rates.insert(balance.currency.clone()); }
+        }
+    }
+
+    // Stocks along with their rate, value in the base currency and target
+    let mut holdings = vec![];
+    let mut total_base = cash_base;
+    for stock in stocks {
+        let rate = match rate(stock.currency()) {
+            Some(rate) => rate,
+            None => {
+                missing_rates.insert(stock.currency().to_owned());
+                continue;
+            }
+        };
+        let value_base = stock.market_value() * rate;
+        total_base += value_base;
+
+        let target = options.dimension.keys(stock).into_iter()
+            .find_map(|key| targets.iter().position(|target| target.key == key));
+        holdings.push((stock, rate, value_base, target));
+    }
+
+    let mut drifts = vec![];
+    // Value to trade of every holding, positive to buy
+    let mut trade_values = vec![Decimal::zero(); holdings.len()];
+    for (index, target) in targets.iter().enumerate() {
+        let members: Vec<usize> = (0..holdings.len())
+            .filter(|member| holdings[*member].3 == Some(index))
+            .collect();
+        let value_base: Decimal = members.iter().map(|member| holdings[*member].2).sum();
+        let weight = percent(value_base, total_base);
+        let drift = weight - target.weight;
+        let out_of_band = drift > target.tolerance || -drift > target.tolerance;
+
+        if out_of_band {
+            let difference = target.weight * total_base / Decimal::from(100) - value_base;
+            let tradable: Vec<usize> = members.iter()
+                .cloned()
+                .filter(|member| holdings[*member].0.price > Decimal::zero())
+                .collect();
+
+            // Split by the value of each holding, evenly if there's none yet
+            for member in &tradable {
+                let share = holdings[*member].2.checked_div(value_base)
+                    .unwrap_or_else(|| Decimal::from(1) / Decimal::from(tradable.len() as i64));
+                trade_values[*member] = difference * share;
+            }
+        }
+
+        drifts.push(TargetDrift {
+            key: target.key.clone(),
+            symbols: members.iter().map(|member| holdings[*member].0.symbol.clone()).collect(),
+            value_base,
+            weight,
+            target_weight: target.weight,
+            tolerance: target.tolerance,
+            drift,
+            out_of_band,
+        });
+    }
+
+    // Stocks without a price can't be traded
+    let lots = |value_base: Decimal, rate: Decimal, price: Decimal| -> Decimal {
+        let lot_price = price * options.lot_size;
+        if lot_price <= Decimal::zero() {
+            return Decimal::zero();
+        }
+
+        (value_base.checked_div(rate).unwrap_or_default())
+            .div_trunc(lot_price, 0) * options.lot_size
+    };
+
+    let mut sells = vec![];
+    let mut proceeds = Decimal::zero();
+    if !options.cash_only {
+        for (index, (stock, rate, _, _)) in holdings.iter().enumerate() {
+            if trade_values[index] >= Decimal::zero() {
+                continue;
+            }
+
+            let quantity = lots(-trade_values[index], *rate, stock.price).min(stock.quantity);
+            let value_base = quantity * stock.price * *rate;
+            if quantity > Decimal::zero() && value_base >= options.min_trade {
+                proceeds += value_base;
+                sells.push(SuggestedTrade {
+                    symbol: stock.symbol.clone(),
+                    kind: TransactionKind::Sell,
+                    quantity,
+                    price: stock.price,
+                    currency: stock.currency().to_owned(),
+                    value_base,
+                });
+            }
+        }
+    }
+
+    // The buys are scaled down to the cash there is
+    let available = (cash_base + proceeds).max(Decimal::zero());
+    let wanted: Decimal = trade_values.iter().filter(|value| **value > Decimal::zero()).cloned().sum();
+    let scale = if wanted > available {
+        available.checked_div(wanted).unwrap_or_default()
+    } else {
+        Decimal::from(1)
+    };
+
+    let mut buys = vec![];
+    let mut spent = Decimal::zero();
+    for (index, (stock, rate, _, _)) in holdings.iter().enumerate() {
+        if trade_values[index] <= Decimal::zero() {
+            continue;
+        }
+
+        let quantity = lots(trade_values[index] * scale, *rate, stock.price);
+        let value_base = quantity * stock.price * *rate;
+        if quantity > Decimal::zero() && value_base >= options.min_trade {
+            spent += value_base;
+            buys.push(SuggestedTrade {
+                symbol: stock.symbol.clone(),
+                kind: TransactionKind::Buy,
+                quantity,
+                price: stock.price,
+                currency: stock.currency().to_owned(),
+                value_base,
+            });
+        }
+    }
+
+    RebalancePlan {
+        dimension: options.dimension,
+        base_currency: base_currency.to_owned(),
+        total_base,
+        cash_base,
+        drifts,
+        trades: sells.into_iter().chain(buys).collect(),
+        cash_after_base: cash_base + proceeds - spent,
+        missing_rates: missing_rates.into_iter().collect(),
+    }
+}
+
+#[test]
+fn test_rebalance() {
+    let stock = |symbol: &str, price: i64, quantity: i64, sector: &str| Stock {
+        symbol: symbol.into(),
+        price: Decimal::from(price),
+        quantity: Decimal::from(quantity),
+        sector: sector.into(),
+        currency: "USD".into(),
+        ..Stock::default()
+    };
+    let stocks = vec![
+        stock("AAPL", 100, 60, "Technology"),
+        stock("MSFT", 50, 40, "Technology"),
+        stock("KO", 30, 10, "Consumer Defensive"),
+    ];
+    let cash = vec![CashBalance { currency: "USD".into(), balance: Decimal::from(700) }];
+    let target = |key: &str, weight: i64| AllocationTarget {
+        dimension: AllocationDimension::Sector,
+        key: key.into(),
+        weight: Decimal::from(weight),
+        tolerance: Decimal::from(5),
+    };
+    let targets = vec![target("Technology", 60), target("Consumer Defensive", 40)];
+    let rate = |_: &str| Some(Decimal::from(1));
+
+    // 8000 of technology and 300 of consumer defensive out of 9000
+    let options = RebalanceOptions { dimension: AllocationDimension::Sector, ..RebalanceOptions::default() };
+    let plan = rebalance(&stocks, &cash, &targets, &options, "USD", rate);
+
+    assert_eq!(Decimal::from(9000), plan.total_base);
+    assert_eq!(Decimal::new(8889, 2), plan.drifts[0].weight);
+    assert_eq!(Decimal::new(2889, 2), plan.drifts[0].drift);
+    assert!(plan.drifts[1].out_of_band);
+    // 2600 of technology is sold, 3/4 of it AAPL, and the 3250 of cash left buy KO
+    assert_eq!(TransactionKind::Sell, plan.trades[0].kind);
+    assert_eq!(("AAPL".to_owned(), Decimal::from(19)), (plan.trades[0].symbol.clone(), plan.trades[0].quantity));
+    assert_eq!(("MSFT".to_owned(), Decimal::from(13)), (plan.trades[1].symbol.clone(), plan.trades[1].quantity));
+    assert_eq!(("KO".to_owned(), Decimal::from(108)), (plan.trades[2].symbol.clone(), plan.trades[2].quantity));
+    assert_eq!(Decimal::from(10), plan.cash_after_base);
+
+    // Only the 700 of cash is spent
+    let options = RebalanceOptions { cash_only: true, ..options };
+    let plan = rebalance(&stocks, &cash, &targets, &options, "USD", rate);
+    assert_eq!(1, plan.trades.len());
+    assert_eq!(Decimal::from(23), plan.trades[0].quantity);
+
+    // Trades smaller than the minimum are left out
+    let options = RebalanceOptions { min_trade: Decimal::from(1000), ..options };
+    assert!(rebalance(&stocks, &cash, &targets, &options, "USD", rate).trades.is_empty());
+
+    // Stocks without a price aren't traded
+    let stocks = vec![stock("AAPL", 100, 60, "Technology"), stock("KO", 0, 0, "Consumer Defensive")];
+    let options = RebalanceOptions { dimension: AllocationDimension::Sector, ..RebalanceOptions::default() };
+    assert!(rebalance(&stocks, &cash, &targets, &options, "USD", rate).trades.iter().all(|trade| trade.symbol != "KO"));
+}
@@ -1,63 +1,2279 @@
+mod migration;
 pub mod market;
 pub mod stock;
+pub mod fx;
+pub mod setting;
+pub mod transaction;
+pub mod cash;
+pub mod dividend;
+pub mod corporate_action;
+pub mod price_history;
+pub mod portfolio;
+pub mod watchlist;
+pub mod profile;
+pub mod benchmark;
+pub mod target;
+pub mod alert;
+pub mod webhook;
+pub mod stop;
+pub mod order;
+pub mod plan;
 pub mod error;
 
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Duration;
+use chrono::{Local, NaiveDate};
+
 use hyper_tls::HttpsConnector;
 use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
 use error::PersistanceError;
 use stock::Stock;
-use market::Market;
+use market::{Market, MarketHoliday, MarketPortfolio};
+use fx::FxRate;
+use transaction::{Transaction, TransactionKind};
+use cash::{CashMovement, CashMovementKind, CashBalance};
+use dividend::{Dividend, DividendReport};
+use corporate_action::{CorporateAction, CorporateActionKind};
+use price_history::PriceBar;
+use portfolio::{Portfolio, DEFAULT_PORTFOLIO_ID};
+use watchlist::{Watchlist, WatchlistItem, WatchlistEntry};
+use profile::CompanyProfile;
+use target::AllocationTarget;
+use alert::{Alert, AlertEvent};
+use webhook::{Webhook, Delivery};
+use stop::{HoldingStop, StopEvent};
+use order::{Order, OrderStatus, TimeInForce};
+use plan::{InvestmentPlan, Contribution, ContributionStatus, PlanReport};
+use crate::decimal::Decimal;
+use crate::valuation::{self, Valuation};
+use crate::allocation::{self, Allocation, AllocationDimension};
+use crate::summary::{self, Summary};
+use crate::returns::{self, HoldingHistory, ReturnPeriod, Returns};
+use crate::risk::{self, HoldingRisk, Risk};
+use crate::comparison::{self, Comparison};
+use crate::indicator::{Indicator, IndicatorValue};
+use crate::correlation::{self, Correlation, HoldingSeries};
+use crate::rebalance::{self, RebalanceOptions, RebalancePlan};
+use crate::backtest::{self, BacktestConfig, BacktestResult};
+use crate::tax::{self, TaxReport};
+
+pub type DbConn = PooledConnection<SqliteConnectionManager>;
+pub type HttpClient = hyper::Client<HttpsConnector<hyper::client::HttpConnector>, hyper::Body>;
+
+// Creates the tables used by the local storage
+pub fn init_storage(db_conn: &DbConn) -> Result<(), PersistanceError> {
+    migration::migrate(db_conn)?;
+    portfolio::portfolio_db::create_table_if_not_exists(db_conn)?;
+    market::market_db::create_table_if_not_exists(db_conn)?;
+    stock::stock_db::create_table_if_not_exists(db_conn)?;
+    fx::fx_db::create_table_if_not_exists(db_conn)?;
+    setting::setting_db::create_table_if_not_exists(db_conn)?;
+    transaction::transaction_db::create_table_if_not_exists(db_conn)?;
+    cash::cash_db::create_table_if_not_exists(db_conn)?;
+    dividend::dividend_db::create_table_if_not_exists(db_conn)?;
+    corporate_action::corporate_action_db::create_table_if_not_exists(db_conn)?;
+    price_history::price_history_db::create_table_if_not_exists(db_conn)?;
+    watchlist::watchlist_db::create_table_if_not_exists(db_conn)?;
+    profile::profile_db::create_table_if_not_exists(db_conn)?;
+    benchmark::benchmark_db::create_table_if_not_exists(db_conn)?;
+    target::target_db::create_table_if_not_exists(db_conn)?;
+    alert::alert_db::create_table_if_not_exists(db_conn)?;
+    webhook::webhook_db::create_table_if_not_exists(db_conn)?;
+    stop::stop_db::create_table_if_not_exists(db_conn)?;
+    order::order_db::create_table_if_not_exists(db_conn)?;
+    plan::plan_db::create_table_if_not_exists(db_conn)
+}
+
+// Runs the operation inside a database transaction, which is rolled back if it fails
+fn in_transaction<T, F>(db_conn: &DbConn, operation: F) -> Result<T, PersistanceError>
+        where F: FnOnce() -> Result<T, PersistanceError> {
+    if !db_conn.is_autocommit() {
+        // Already inside a transaction, which will commit or roll back everything
+        return operation();
+    }
+
+    db_conn.execute_batch("BEGIN").map_err(PersistanceError::CouldNotUpdate)?;
+
+    match operation() {
+        Ok(result) => {
+            db_conn.execute_batch("COMMIT").map_err(PersistanceError::CouldNotUpdate)?;
+            Ok(result)
+        },
+        Err(e) => {
+            db_conn.execute_batch("ROLLBACK").map_err(PersistanceError::CouldNotUpdate)?;
+            Err(e)
+        }
+    }
+}
+
+// Stores a stock in the local storage
+pub fn add_stock(db_conn: &DbConn, portfolio_id: u32, stock: &Stock) -> Result<(), PersistanceError> {
+    if !stock.is_valid() {
+        return Err(PersistanceError::InvalidAmount);
+    }
+
+    stock::stock_db::add(db_conn, portfolio_id, stock)
+}
+
+// Deletes a stock from the local storage
+pub fn delete_stock(db_conn: &DbConn, portfolio_id: u32, symbol: &str) -> Result<(), PersistanceError> {
+    in_transaction(db_conn, || {
+        stop::stop_db::delete(db_conn, portfolio_id, symbol)?;
+        order::order_db::cancel_by_symbol(db_conn, portfolio_id, symbol)?;
+        plan::plan_db::delete_by_symbol(db_conn, portfolio_id, symbol)?;
+        stock::stock_db::delete(db_conn, portfolio_id, symbol)
+    })
+}
+
+// Updates the stored data of a stock
+pub fn update_stock(db_conn: &DbConn, portfolio_id: u32, stock: &Stock) -> Result<(), PersistanceError> {
+    if !stock.is_valid() {
+        return Err(PersistanceError::InvalidAmount);
+    }
+
+    match stock::stock_db::get(db_conn, portfolio_id, &stock.symbol)? {
+        Some(_) => stock::stock_db::update(db_conn, portfolio_id, stock),
+        None => Err(PersistanceError::KeyNotFoundError)
+    }
+}
+
+// Returns a vector with all the stored stocks
+pub fn get_stored_stocks(db_conn: &DbConn, portfolio_id: u32)  -> Result<Vec<Stock>, PersistanceError> {
+    stock::stock_db::get_all(db_conn, portfolio_id)
+}
+
+// Returns the stored stocks grouped by market and currency, with their subtotals
+pub fn get_portfolio_by_market(db_conn: &DbConn, portfolio_id: u32) -> Result<Vec<MarketPortfolio>, PersistanceError> {
+    let stocks = stock::stock_db::get_all(db_conn, portfolio_id)?;
+    let rates = get_rates_to_base(db_conn, get_currencies(&stocks), today())?;
+    let mut groups = market::group_by_market(stocks);
+
+    for group in groups.iter_mut() {
+        let rate = rates.get(&group.currency).cloned().flatten();
+        group.total_value_base = rate.map(|rate| group.total_value * rate);
+        group.total_cost_base = rate.map(|rate| group.total_cost * rate);
+    }
+
+    Ok(groups)
+}
+
+// Returns the value of the stored stocks and cash in their currency and in the base currency
+pub fn get_valuation(db_conn: &DbConn, portfolio_id: u32) -> Result<Valuation, PersistanceError> {
+    let stocks = stock::stock_db::get_all(db_conn, portfolio_id)?;
+    let cash = get_cash_balances(db_conn, portfolio_id)?;
+    let base_currency = get_base_currency(db_conn)?;
+
+    let mut currencies = get_currencies(&stocks);
+    currencies.extend(cash.iter().map(|balance| balance.currency.clone()));
+    let rates = get_rates_to_base(db_conn, currencies, today())?;
+
+    Ok(valuation::value_portfolio(&stocks, &cash, &base_currency, |currency| rates.get(currency).cloned().flatten()))
+}
+
+// Profit and loss of the holdings, the day change is against the last close before today
+pub fn get_summary(db_conn: &DbConn, portfolio_id: u32) -> Result<Summary, PersistanceError> {
+    let stocks = stock::stock_db::get_all(db_conn, portfolio_id)?;
+    let base_currency = get_base_currency(db_conn)?;
+    let rates = get_rates_to_base(db_conn, get_currencies(&stocks), today())?;
+
+    let mut previous_closes = HashMap::new();
+    for stock in &stocks {
+        if let Some(bar) = price_history::price_history_db::get_latest_before(db_conn, &stock.symbol, today())? {
+            let actions = corporate_action::corporate_action_db::get_by_symbol(db_conn, portfolio_id, &stock.symbol)?;
+            let bar = price_history::adjust_for_splits(vec![bar], &actions).remove(0);
+            previous_closes.insert(stock.symbol.clone(), bar.close);
+        }
+    }
+
+    Ok(summary::summarize(&stocks, &previous_closes, &base_currency, |currency| rates.get(currency).cloned().flatten()))
+}
+
+// Gathers what the ledger knows about every stock ever traded in the portfolio
+pub fn get_holding_histories(db_conn: &DbConn, portfolio_id: u32) -> Result<Vec<HoldingHistory>, PersistanceError> {
+    let mut transactions_by_symbol: BTreeMap<String, Vec<Transaction>> = BTreeMap::new();
+    for transaction in transaction::transaction_db::get_all(db_conn, portfolio_id)? {
+        transactions_by_symbol.entry(transaction.symbol.clone()).or_default().push(transaction);
+    }
+
+    let mut histories = vec![];
+    for (symbol, transactions) in transactions_by_symbol {
+        let currency = transactions[0].currency.clone();
+        let actions = corporate_action::corporate_action_db::get_by_symbol(db_conn, portfolio_id, &symbol)?;
+        let dividends = dividend::dividend_db::get_by_symbol(db_conn, portfolio_id, &symbol)?;
+        let bars = price_history::price_history_db::get_by_symbol(db_conn, &symbol)?;
+
+        histories.push(HoldingHistory::new(&symbol, &currency, transactions, actions, dividends, &bars));
+    }
+
+    Ok(histories)
+}
+
+// Rates to the base currency of the currencies of the holdings on every day they're valued on
+fn get_rates_by_date(db_conn: &DbConn, histories: &[HoldingHistory], start: NaiveDate, end: NaiveDate) -> Result<HashMap<NaiveDate, HashMap<String, Option<Decimal>>>, PersistanceError> {
+    let currencies: BTreeSet<String> = histories.iter().map(|history| history.currency.clone()).collect();
+    let mut rates = HashMap::new();
+
+    for date in returns::valuation_dates(histories, start, end) {
+        rates.insert(date, get_rates_to_base(db_conn, currencies.clone(), date)?);
+    }
+
+    Ok(rates)
+}
+
+// First and last day of the period, which starts with the first transaction if it's since inception
+fn get_period_range(histories: &[HoldingHistory], period: ReturnPeriod) -> (NaiveDate, NaiveDate) {
+    let inception = histories.iter()
+        .flat_map(|history| history.transactions.iter().map(|transaction| transaction.date))
+        .min()
+        .unwrap_or_else(today);
+
+    period.range(today(), inception)
+}
+
+// Time and money weighted returns of the holdings over the period, from the ledger and the price history
+pub fn get_returns(db_conn: &DbConn, portfolio_id: u32, period: ReturnPeriod) -> Result<Returns, PersistanceError> {
+    let histories = get_holding_histories(db_conn, portfolio_id)?;
+    let (start, end) = get_period_range(&histories, period);
+    let base_currency = get_base_currency(db_conn)?;
+    let rates = get_rates_by_date(db_conn, &histories, start, end)?;
+
+    Ok(returns::calculate_returns(&histories, start, end, &base_currency, |currency, date| {
+        rates.get(&date).and_then(|rates| rates.get(currency).cloned().flatten())
+    }))
+}
+
+// Risk of the held stocks, from their prices, and of the whole portfolio, from its daily
+// growth over the period. The beta is measured against the given benchmark, or the
+// first one of the portfolio.
+pub fn get_risk(db_conn: &DbConn, portfolio_id: u32, benchmark: Option<&str>, period: ReturnPeriod) -> Result<Risk, PersistanceError> {
+    let benchmark = match benchmark {
+        Some(symbol) => Some(symbol.to_owned()),
+        None => get_benchmarks(db_conn, portfolio_id)?.into_iter().next(),
+    };
+    let histories = get_holding_histories(db_conn, portfolio_id)?;
+    let (start, end) = get_period_range(&histories, period);
+    let base_currency = get_base_currency(db_conn)?;
+    let risk_free_rate = get_risk_free_rate(db_conn)?;
+    let rates = get_rates_by_date(db_conn, &histories, start, end)?;
+    let in_period = |bar: &PriceBar| bar.date >= start && bar.date <= end;
+
+    let benchmark_series: Option<Vec<(NaiveDate, Decimal)>> = match &benchmark {
+        Some(symbol) => Some(price_history::price_history_db::get_by_symbol(db_conn, symbol)?
+            .iter()
+            .filter(|bar| in_period(bar))
+            .map(|bar| (bar.date, bar.close))
+            .collect()),
+        None => None,
+    };
+
+    let mut holdings = vec![];
+    for stock in stock::stock_db::get_all(db_conn, portfolio_id)? {
+        if stock.quantity <= Decimal::zero() {
+            continue;
+        }
+
+        let series: Vec<(NaiveDate, Decimal)> = get_price_history(db_conn, &stock.symbol, false)?
+            .iter()
+            .filter(|bar| in_period(bar))
+            .map(|bar| (bar.date, bar.close))
+            .collect();
+
+        holdings.push(HoldingRisk {
+            symbol: stock.symbol.clone(),
+            currency: stock.currency().to_owned(),
+            metrics: risk::measure(&series, benchmark_series.as_deref(), risk_free_rate, stock.market_value()),
+        });
+    }
+
+    let mut missing_rates = BTreeSet::new();
+    let rate = |currency: &str, date: NaiveDate| rates.get(&date).and_then(|rates| rates.get(currency).cloned().flatten());
+    let points = returns::portfolio_points(&histories, start, end, &rate, &mut missing_rates);
+    let value = points.last().map(|point| point.value).unwrap_or_default();
+
+    Ok(Risk {
+        start,
+        end,
+        base_currency,
+        benchmark,
+        risk_free_rate,
+        total: risk::measure(&returns::growth_index(&points), benchmark_series.as_deref(), risk_free_rate, value),
+        holdings,
+        missing_rates: missing_rates.into_iter().collect(),
+    })
+}
+
+// Cumulative returns of the portfolio over the period next to the ones of its benchmarks,
+// had they been bought and sold with the same money on the same days
+pub fn get_comparison(db_conn: &DbConn, portfolio_id: u32, period: ReturnPeriod) -> Result<Comparison, PersistanceError> {
+    let histories = get_holding_histories(db_conn, portfolio_id)?;
+    let (start, end) = get_period_range(&histories, period);
+    let base_currency = get_base_currency(db_conn)?;
+    let risk_free_rate = get_risk_free_rate(db_conn)?;
+    let rates = get_rates_by_date(db_conn, &histories, start, end)?;
+
+    let mut benchmarks = vec![];
+    for symbol in get_benchmarks(db_conn, portfolio_id)? {
+        let prices: BTreeMap<NaiveDate, Decimal> = price_history::price_history_db::get_by_symbol(db_conn, &symbol)?
+            .into_iter()
+            .map(|bar| (bar.date, bar.close))
+            .collect();
+        benchmarks.push((symbol, prices));
+    }
+
+    let mut missing_rates = BTreeSet::new();
+    let rate = |currency: &str, date: NaiveDate| rates.get(&date).and_then(|rates| rates.get(currency).cloned().flatten());
+    let points = returns::portfolio_points(&histories, start, end, &rate, &mut missing_rates);
+    let (returns, series, benchmarks) = comparison::compare(&points, &benchmarks, risk_free_rate);
+
+    Ok(Comparison {
+        start,
+        end,
+        base_currency,
+        returns,
+        series,
+        benchmarks,
+        missing_rates: missing_rates.into_iter().collect(),
+    })
+}
+
+// Correlations between the daily returns of the held stocks over the period, flagging
+// the pairs above the threshold
+pub fn get_correlation(db_conn: &DbConn, portfolio_id: u32, period: ReturnPeriod, threshold: Decimal) -> Result<Correlation, PersistanceError> {
+    let (start, end) = get_period_range(&get_holding_histories(db_conn, portfolio_id)?, period);
+    let stocks: Vec<Stock> = stock::stock_db::get_all(db_conn, portfolio_id)?
+        .into_iter()
+        .filter(|stock| stock.quantity > Decimal::zero())
+        .collect();
+    let rates = get_rates_to_base(db_conn, get_currencies(&stocks), today())?;
+
+    let mut holdings = vec![];
+    for stock in stocks {
+        let prices = get_price_history(db_conn, &stock.symbol, false)?
+            .into_iter()
+            .filter(|bar| bar.date >= start && bar.date <= end)
+            .map(|bar| (bar.date, bar.close))
+            .collect();
+
+        holdings.push(HoldingSeries {
+            value_base: rates.get(stock.currency()).cloned().flatten().map(|rate| stock.market_value() * rate),
+            currency: stock.currency().to_owned(),
+            symbol: stock.symbol,
+            prices,
+        });
+    }
+
+    Ok(correlation::correlate(&holdings, start, end, threshold))
+}
+
+// Returns the weights of the holdings grouped by the dimension
+pub fn get_allocation(db_conn: &DbConn, portfolio_id: u32, dimension: AllocationDimension) -> Result<Allocation, PersistanceError> {
+    let stocks = stock::stock_db::get_all(db_conn, portfolio_id)?;
+    let base_currency = get_base_currency(db_conn)?;
+    let rates = get_rates_to_base(db_conn, get_currencies(&stocks), today())?;
+
+    Ok(allocation::allocate(&stocks, dimension, &base_currency, |currency| rates.get(currency).cloned().flatten()))
+}
+
+// Sets the stop, trailing stop and take profit levels of a holding. A trailing stop
+// starts from the current price.
+pub fn set_stop(db_conn: &DbConn, portfolio_id: u32, stop: &HoldingStop) -> Result<(), PersistanceError> {
+    if !stop.is_valid() {
+        return Err(PersistanceError::InvalidAmount);
+    }
+
+    let stock = stock::stock_db::get(db_conn, portfolio_id, &stop.symbol)?
+        .ok_or(PersistanceError::KeyNotFoundError)?;
+    let mut stop = stop.clone();
+    if stop.trailing_percent.is_some() && stock.price > Decimal::zero() {
+        stop.update(stock.price);
+    }
+
+    stop::stop_db::set(db_conn, portfolio_id, &stop)
+}
+
+pub fn remove_stop(db_conn: &DbConn, portfolio_id: u32, symbol: &str) -> Result<(), PersistanceError> {
+    match stop::stop_db::delete(db_conn, portfolio_id, symbol)? {
+        true => Ok(()),
+        false => Err(PersistanceError::KeyNotFoundError),
+    }
+}
+
+pub fn get_stops(db_conn: &DbConn, portfolio_id: u32) -> Result<Vec<HoldingStop>, PersistanceError> {
+    stop::stop_db::get_all(db_conn, portfolio_id)
+}
+
+// Checks the levels of the holdings of every portfolio against their stored prices, raising
+// the trailing stops. The levels crossed are removed, selling the holding if the stop says so.
+pub fn evaluate_stops(db_conn: &DbConn, date: NaiveDate) -> Result<Vec<StopEvent>, PersistanceError> {
+    let mut events = vec![];
+
+    in_transaction(db_conn, || {
+        for (portfolio_id, mut stop) in stop::stop_db::get_all_portfolios(db_conn)? {
+            let stock = match stock::stock_db::get(db_conn, portfolio_id, &stop.symbol)? {
+                Some(stock) if stock.price > Decimal::zero() => stock,
+                _ => continue,
+            };
+
+            let previous = stop.clone();
+            let (kind, level) = match stop.update(stock.price) {
+                Some(crossed) => crossed,
+                None => {
+                    if stop != previous {
+                        stop::stop_db::set(db_conn, portfolio_id, &stop)?;
+                    }
+                    continue;
+                }
+            };
+
+            stop::stop_db::delete(db_conn, portfolio_id, &stop.symbol)?;
+            let sold = if stop.sell && stock.quantity > Decimal::zero() {
+                sell_stock(db_conn, portfolio_id, &Transaction {
+                    symbol: stock.symbol.clone(),
+                    quantity: stock.quantity,
+                    price: stock.price,
+                    date,
+                    ..Transaction::default()
+                })?;
+                Some(stock.quantity)
+            } else {
+                None
+            };
+
+            events.push(StopEvent {
+                portfolio_id,
+                symbol: stock.symbol.clone(),
+                kind,
+                level,
+                price: stock.price,
+                date,
+                sold,
+            });
+        }
+        Ok(())
+    })?;
+
+    Ok(events)
+}
+
+// Stores the weight a holding, tag or sector should have, replacing the previous one
+pub fn set_target(db_conn: &DbConn, portfolio_id: u32, target: &AllocationTarget) -> Result<(), PersistanceError> {
+    if !target.is_valid() {
+        return Err(PersistanceError::InvalidAmount);
+    }
+
+    target::target_db::set(db_conn, portfolio_id, target)
+}
+
+pub fn remove_target(db_conn: &DbConn, portfolio_id: u32, dimension: AllocationDimension, key: &str) -> Result<(), PersistanceError> {
+    target::target_db::delete(db_conn, portfolio_id, dimension, key)
+}
+
+pub fn get_targets(db_conn: &DbConn, portfolio_id: u32) -> Result<Vec<AllocationTarget>, PersistanceError> {
+    target::target_db::get_all(db_conn, portfolio_id)
+}
+
+// Drift of the holdings from the targets of the dimension and the trades to get them
+// back within their bands, at the stored prices
+pub fn get_rebalance_plan(db_conn: &DbConn, portfolio_id: u32, options: &RebalanceOptions) -> Result<RebalancePlan, PersistanceError> {
+    if !options.is_valid() {
+        return Err(PersistanceError::InvalidAmount);
+    }
+
+    let stocks = stock::stock_db::get_all(db_conn, portfolio_id)?;
+    let cash = get_cash_balances(db_conn, portfolio_id)?;
+    let targets = target::target_db::get_by_dimension(db_conn, portfolio_id, options.dimension)?;
+    let base_currency = get_base_currency(db_conn)?;
+
+    let mut currencies = get_currencies(&stocks);
+    currencies.extend(cash.iter().map(|balance| balance.currency.clone()));
+    let rates = get_rates_to_base(db_conn, currencies, today())?;
+
+    Ok(rebalance::rebalance(&stocks, &cash, &targets, options, &base_currency, |currency| rates.get(currency).cloned().flatten()))
+}
+
+// Attaches a tag to a stored stock
+pub fn tag_stock(db_conn: &DbConn, portfolio_id: u32, symbol: &str, tag: &str) -> Result<(), PersistanceError> {
+    let mut stock = stock::stock_db::get(db_conn, portfolio_id, symbol)?
+        .ok_or(PersistanceError::KeyNotFoundError)?;
+
+    if !stock.tags.iter().any(|stock_tag| stock_tag == tag) {
+        stock.tags.push(tag.to_owned());
+        stock::stock_db::update(db_conn, portfolio_id, &stock)?;
+    }
+
+    Ok(())
+}
+
+// Removes a tag from a stored stock
+pub fn untag_stock(db_conn: &DbConn, portfolio_id: u32, symbol: &str, tag: &str) -> Result<(), PersistanceError> {
+    let mut stock = stock::stock_db::get(db_conn, portfolio_id, symbol)?
+        .ok_or(PersistanceError::KeyNotFoundError)?;
+
+    stock.tags.retain(|stock_tag| stock_tag != tag);
+    stock::stock_db::update(db_conn, portfolio_id, &stock)
+}
+
+// Returns the currencies the stocks are quoted in
+pub fn get_currencies(stocks: &[Stock]) -> BTreeSet<String> {
+    stocks.iter()
+        .map(|stock| stock.currency().to_owned())
+        .collect()
+}
+
+// Returns, for every currency, the rate to convert it to the base currency
+fn get_rates_to_base(db_conn: &DbConn, currencies: BTreeSet<String>, date: NaiveDate) -> Result<HashMap<String, Option<Decimal>>, PersistanceError> {
+    let base_currency = get_base_currency(db_conn)?;
+    let mut rates = HashMap::new();
+
+    for currency in currencies {
+        let rate = fx::get_rate(db_conn, &currency, &base_currency, date)?;
+        rates.insert(currency, rate);
+    }
+
+    Ok(rates)
+}
+
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+// Records money put into the account
+pub fn deposit(db_conn: &DbConn, portfolio_id: u32, movement: &CashMovement) -> Result<(), PersistanceError> {
+    if movement.amount <= Decimal::zero() {
+        return Err(PersistanceError::InvalidAmount);
+    }
+
+    cash::cash_db::add(db_conn, portfolio_id, &CashMovement {
+        kind: CashMovementKind::Deposit,
+        transaction_id: None,
+        ..movement.clone()
+    })
+}
+
+// Records money taken out of the account, as long as there is enough cash
+pub fn withdraw(db_conn: &DbConn, portfolio_id: u32, movement: &CashMovement) -> Result<(), PersistanceError> {
+    if movement.amount <= Decimal::zero() {
+        return Err(PersistanceError::InvalidAmount);
+    }
+
+    if get_cash_balance(db_conn, portfolio_id, &movement.currency)? < movement.amount {
+        return Err(PersistanceError::NotEnoughCash);
+    }
+
+    cash::cash_db::add(db_conn, portfolio_id, &CashMovement {
+        amount: -movement.amount,
+        kind: CashMovementKind::Withdrawal,
+        transaction_id: None,
+        ..movement.clone()
+    })
+}
+
+// Returns the cash of every currency
+pub fn get_cash_balances(db_conn: &DbConn, portfolio_id: u32) -> Result<Vec<CashBalance>, PersistanceError> {
+    let movements = cash::cash_db::get_all(db_conn, portfolio_id)?;
+    Ok(cash::get_balances(&movements))
+}
+
+// Returns the cash of a currency
+pub fn get_cash_balance(db_conn: &DbConn, portfolio_id: u32, currency: &str) -> Result<Decimal, PersistanceError> {
+    let movements = cash::cash_db::get_by_currency(db_conn, portfolio_id, currency)?;
+    Ok(movements.iter().map(|movement| movement.amount).sum())
+}
+
+// Returns every deposit, withdrawal and trade settlement, oldest first
+pub fn get_cash_movements(db_conn: &DbConn, portfolio_id: u32) -> Result<Vec<CashMovement>, PersistanceError> {
+    cash::cash_db::get_all(db_conn, portfolio_id)
+}
+
+// Buys shares of a stored stock paying with the cash of its currency
+pub fn buy_stock(db_conn: &DbConn, portfolio_id: u32, transaction: &Transaction) -> Result<(), PersistanceError> {
+    if transaction.quantity <= Decimal::zero() || transaction.price < Decimal::zero()
+        || transaction.quantity.checked_mul(transaction.price).is_none() {
+        return Err(PersistanceError::InvalidAmount);
+    }
+
+    let mut stock = stock::stock_db::get(db_conn, portfolio_id, &transaction.symbol)?
+        .ok_or(PersistanceError::KeyNotFoundError)?;
+    let transaction = Transaction {
+        kind: TransactionKind::Buy,
+        currency: stock.currency().to_owned(),
+        realized_gain: Decimal::zero(),
+        ..transaction.clone()
+    };
+
+    let cost = -transaction.cash_amount();
+    if get_cash_balance(db_conn, portfolio_id, &transaction.currency)? < cost {
+        return Err(PersistanceError::NotEnoughCash);
+    }
+
+    let quantity = stock.quantity + transaction.quantity;
+    stock.initial_price = (stock.cost() + cost) / quantity;
+    stock.quantity = quantity;
+    if !stock.is_valid() {
+        return Err(PersistanceError::InvalidAmount);
+    }
+
+    in_transaction(db_conn, || {
+        record_transaction(db_conn, portfolio_id, &transaction, CashMovementKind::Buy)?;
+        stock::stock_db::update(db_conn, portfolio_id, &stock)
+    })
+}
+
+// Sells shares of a stored stock, crediting the cash of its currency
+pub fn sell_stock(db_conn: &DbConn, portfolio_id: u32, transaction: &Transaction) -> Result<(), PersistanceError> {
+    if transaction.quantity <= Decimal::zero() || transaction.price < Decimal::zero()
+        || transaction.quantity.checked_mul(transaction.price).is_none() {
+        return Err(PersistanceError::InvalidAmount);
+    }
+
+    let mut stock = stock::stock_db::get(db_conn, portfolio_id, &transaction.symbol)?
+        .ok_or(PersistanceError::KeyNotFoundError)?;
+    if stock.quantity < transaction.quantity {
+        return Err(PersistanceError::NotEnoughShares);
+    }
+
+    let mut transaction = Transaction {
+        kind: TransactionKind::Sell,
+        currency: stock.currency().to_owned(),
+        ..transaction.clone()
+    };
+    transaction.realized_gain = transaction.cash_amount() - stock.initial_price * transaction.quantity;
+    stock.quantity -= transaction.quantity;
+
+    in_transaction(db_conn, || {
+        record_transaction(db_conn, portfolio_id, &transaction, CashMovementKind::Sell)?;
+        stock::stock_db::update(db_conn, portfolio_id, &stock)
+    })
+}
+
+// Stores a transaction in the ledger along with the cash it moves
+fn record_transaction(db_conn: &DbConn, portfolio_id: u32, transaction: &Transaction, kind: CashMovementKind) -> Result<(), PersistanceError> {
+    let transaction_id = transaction::transaction_db::add(db_conn, portfolio_id, transaction)?;
+
+    cash::cash_db::add(db_conn, portfolio_id, &CashMovement {
+        id: 0,
+        currency: transaction.currency.clone(),
+        amount: transaction.cash_amount(),
+        kind,
+        date: transaction.date,
+        note: format!("{} {} {}", kind.as_str(), transaction.quantity, transaction.symbol),
+        transaction_id: Some(transaction_id),
+    })
+}
+
+// Returns the transactions of the ledger, of a stock if given, oldest first
+pub fn get_transactions(db_conn: &DbConn, portfolio_id: u32, symbol: Option<&str>) -> Result<Vec<Transaction>, PersistanceError> {
+    match symbol {
+        Some(symbol) => transaction::transaction_db::get_by_symbol(db_conn, portfolio_id, symbol),
+        None => transaction::transaction_db::get_all(db_conn, portfolio_id),
+    }
+}
+
+// Returns the lots sold in a year, matched with the purchases of the ledger and
+// adjusted for wash sales
+pub fn get_tax_report(db_conn: &DbConn, portfolio_id: u32, year: i32) -> Result<TaxReport, PersistanceError> {
+    let transactions = transaction::transaction_db::get_all(db_conn, portfolio_id)?;
+    let actions = corporate_action::corporate_action_db::get_all(db_conn, portfolio_id)?;
+    Ok(tax::report(&transactions, &actions, year))
+}
+
+// Places a simulated order on a stored stock, filled by the next quotes
+pub fn place_order(db_conn: &DbConn, portfolio_id: u32, order: &Order) -> Result<(), PersistanceError> {
+    if !order.is_valid() {
+        return Err(PersistanceError::InvalidAmount);
+    }
+
+    stock::stock_db::get(db_conn, portfolio_id, &order.symbol)?
+        .ok_or(PersistanceError::KeyNotFoundError)?;
+    order::order_db::add(db_conn, portfolio_id, order)
+}
+
+pub fn cancel_order(db_conn: &DbConn, portfolio_id: u32, id: u32) -> Result<(), PersistanceError> {
+    match order::order_db::cancel(db_conn, portfolio_id, id)? {
+        true => Ok(()),
+        false => Err(PersistanceError::KeyNotFoundError),
+    }
+}
+
+// Returns the open orders, or every order if `all`, oldest first
+pub fn get_orders(db_conn: &DbConn, portfolio_id: u32, all: bool) -> Result<Vec<Order>, PersistanceError> {
+    order::order_db::get_all(db_conn, portfolio_id, all)
+}
+
+pub fn get_order_symbols(db_conn: &DbConn) -> Result<Vec<String>, PersistanceError> {
+    order::order_db::get_open_symbols(db_conn)
+}
+
+// Fills the open orders of every portfolio on a symbol against its quote, recording
+// the trades in their ledgers. Returns the orders that were closed: filled, expired,
+// or rejected for lack of cash, shares or the stock itself.
+pub fn fill_orders(db_conn: &DbConn, symbol: &str, price: Decimal, date: NaiveDate) -> Result<Vec<Order>, PersistanceError> {
+    let slippage = get_slippage(db_conn)?;
+    let commission = get_commission(db_conn)?;
+    let mut closed = vec![];
+
+    in_transaction(db_conn, || {
+        for mut order in order::order_db::get_open_by_symbol(db_conn, symbol)? {
+            let fill_price = match order.fill_price(price, slippage) {
+                _ if order.is_expired(date) => None,
+                fill_price => fill_price,
+            };
+
+            order.status = match fill_price {
+                Some(fill_price) => {
+                    let transaction = Transaction {
+                        symbol: order.symbol.clone(),
+                        quantity: order.quantity,
+                        price: fill_price,
+                        commission,
+                        date,
+                        ..Transaction::default()
+                    };
+                    let result = match order.side {
+                        TransactionKind::Buy => buy_stock(db_conn, order.portfolio_id, &transaction),
+                        TransactionKind::Sell => sell_stock(db_conn, order.portfolio_id, &transaction),
+                    };
+
+                    match result {
+                        Ok(_) => {
+                            order.fill_price = Some(fill_price);
+                            order.filled_on = Some(date);
+                            OrderStatus::Filled
+                        },
+                        Err(PersistanceError::NotEnoughCash)
+                            | Err(PersistanceError::NotEnoughShares)
+                            | Err(PersistanceError::KeyNotFoundError) => OrderStatus::Rejected,
+                        Err(e) => return Err(e),
+                    }
+                },
+                None if order.is_expired(date) || order.time_in_force == TimeInForce::Ioc => OrderStatus::Expired,
+                None => continue,
+            };
+
+            order::order_db::update_status(db_conn, &order)?;
+            closed.push(order);
+        }
+        Ok(())
+    })?;
+
+    Ok(closed)
+}
+
+// Stores a plan investing in a stock of the portfolio every month
+pub fn add_plan(db_conn: &DbConn, portfolio_id: u32, plan: &InvestmentPlan) -> Result<(), PersistanceError> {
+    if !plan.is_valid() {
+        return Err(PersistanceError::InvalidAmount);
+    }
+
+    let stock = stock::stock_db::get(db_conn, portfolio_id, &plan.symbol)?
+        .ok_or(PersistanceError::KeyNotFoundError)?;
+    plan::plan_db::add(db_conn, portfolio_id, &InvestmentPlan {
+        currency: stock.currency().to_owned(),
+        last_scheduled: None,
+        ..plan.clone()
+    })
+}
+
+// Deletes a plan and its pending contributions, keeping the ones made
+pub fn delete_plan(db_conn: &DbConn, portfolio_id: u32, id: u32) -> Result<(), PersistanceError> {
+    in_transaction(db_conn, || {
+        match plan::plan_db::delete(db_conn, portfolio_id, id)? {
+            true => Ok(()),
+            false => Err(PersistanceError::KeyNotFoundError),
+        }
+    })
+}
+
+pub fn get_plans(db_conn: &DbConn, portfolio_id: u32) -> Result<Vec<InvestmentPlan>, PersistanceError> {
+    plan::plan_db::get_all(db_conn, portfolio_id)
+}
+
+// Creates the pending contributions of the plans due by `date`, on the first day
+// their market trades, then buys the pending ones at the first price stored since.
+// Returns the contributions that were executed or failed.
+pub fn run_plans(db_conn: &DbConn, portfolio_id: u32, date: NaiveDate) -> Result<Vec<Contribution>, PersistanceError> {
+    let mut closed = vec![];
+
+    in_transaction(db_conn, || {
+        let plans = plan::plan_db::get_all(db_conn, portfolio_id)?;
+        for plan in &plans {
+            let stock = match stock::stock_db::get(db_conn, portfolio_id, &plan.symbol)? {
+                Some(stock) => stock,
+                None => continue,
+            };
+            let holidays = get_holiday_dates(db_conn, stock.market.id)?;
+
+            let due = plan.due(date);
+            for scheduled in &due {
+                plan::plan_db::add_contribution(db_conn, portfolio_id, &Contribution {
+                    plan_id: plan.id,
+                    symbol: plan.symbol.clone(),
+                    amount: plan.amount,
+                    currency: plan.currency.clone(),
+                    scheduled: *scheduled,
+                    date: market::next_trading_day(*scheduled, &holidays),
+                    ..Contribution::default()
+                })?;
+            }
+            if let Some(last) = due.last() {
+                plan::plan_db::set_last_scheduled(db_conn, plan.id, *last)?;
+            }
+        }
+
+        for mut contribution in plan::plan_db::get_contributions(db_conn, portfolio_id, Some(ContributionStatus::Pending))? {
+            let bar = price_history::price_history_db::get_by_symbol(db_conn, &contribution.symbol)?
+                .into_iter()
+                .find(|bar| bar.date >= contribution.date && bar.date <= date && bar.close > Decimal::zero());
+            let bar = match bar {
+                Some(bar) => bar,
+                None => continue,
+            };
+
+            // The deposit is only made for a buy that can go through, which then costs
+            // no more than it
+            let quantity = contribution.amount.div_trunc(bar.close, 4);
+            let stock = stock::stock_db::get(db_conn, portfolio_id, &contribution.symbol)?;
+            let deposit_first = plans.iter().any(|plan| plan.id == contribution.plan_id && plan.deposit);
+            let result = match (stock, quantity > Decimal::zero()) {
+                (None, _) => Err(PersistanceError::KeyNotFoundError),
+                (Some(_), false) => Err(PersistanceError::InvalidAmount),
+                (Some(_), true) if deposit_first => deposit(db_conn, portfolio_id, &CashMovement {
+                    currency: contribution.currency.clone(),
+                    amount: contribution.amount,
+                    date: bar.date,
+                    note: format!("plan {} {}", contribution.plan_id, contribution.symbol),
+                    ..CashMovement::default()
+                }),
+                (Some(_), true) => Ok(()),
+            };
+            let result = result.and_then(|_| buy_stock(db_conn, portfolio_id, &Transaction {
+                symbol: contribution.symbol.clone(),
+                quantity,
+                price: bar.close,
+                date: bar.date,
+                ..Transaction::default()
+            }));
+
+            contribution.status = match result {
+                Ok(_) => {
+                    contribution.quantity = Some(quantity);
+                    contribution.price = Some(bar.close);
+                    contribution.executed_on = Some(bar.date);
+                    ContributionStatus::Executed
+                },
+                Err(PersistanceError::NotEnoughCash)
+                    | Err(PersistanceError::InvalidAmount)
+                    | Err(PersistanceError::KeyNotFoundError) => ContributionStatus::Failed,
+                Err(e) => return Err(e),
+            };
+
+            plan::plan_db::update_contribution(db_conn, &contribution)?;
+            closed.push(contribution);
+        }
+        Ok(())
+    })?;
+
+    Ok(closed)
+}
+
+// Next contributions of every plan after `date` along with the ones made so far
+pub fn get_plan_report(db_conn: &DbConn, portfolio_id: u32, date: NaiveDate) -> Result<PlanReport, PersistanceError> {
+    let plans = plan::plan_db::get_all(db_conn, portfolio_id)?;
+    let contributions = plan::plan_db::get_contributions(db_conn, portfolio_id, None)?;
+
+    let mut upcoming = vec![];
+    for plan in &plans {
+        let holidays = match stock::stock_db::get(db_conn, portfolio_id, &plan.symbol)? {
+            Some(stock) => get_holiday_dates(db_conn, stock.market.id)?,
+            None => continue,
+        };
+
+        upcoming.extend(plan.upcoming(date, plan::UPCOMING_CONTRIBUTIONS).into_iter()
+            .map(|scheduled| Contribution {
+                plan_id: plan.id,
+                symbol: plan.symbol.clone(),
+                amount: plan.amount,
+                currency: plan.currency.clone(),
+                scheduled,
+                date: market::next_trading_day(scheduled, &holidays),
+                ..Contribution::default()
+            }));
+    }
+    upcoming.sort_by_key(|contribution| (contribution.date, contribution.plan_id));
+
+    Ok(PlanReport {
+        upcoming,
+        totals: plan::totals(&plans, &contributions),
+        contributions,
+    })
+}
+
+// Stores a dividend of a stored stock, paid in the currency of the stock unless told otherwise
+pub fn add_dividend(db_conn: &DbConn, portfolio_id: u32, dividend: &Dividend) -> Result<(), PersistanceError> {
+    let stock = stock::stock_db::get(db_conn, portfolio_id, &dividend.symbol)?
+        .ok_or(PersistanceError::KeyNotFoundError)?;
+    let currency = if dividend.currency.is_empty() {
+        stock.currency().to_owned()
+    } else {
+        dividend.currency.clone()
+    };
+
+    dividend::dividend_db::add(db_conn, portfolio_id, &Dividend {
+        currency,
+        quantity: None,
+        paid: false,
+        ..dividend.clone()
+    })
+}
+
+// Returns the stored dividends, of a stock if given
+pub fn get_dividends(db_conn: &DbConn, portfolio_id: u32, symbol: Option<&str>) -> Result<Vec<Dividend>, PersistanceError> {
+    match symbol {
+        Some(symbol) => dividend::dividend_db::get_by_symbol(db_conn, portfolio_id, symbol),
+        None => dividend::dividend_db::get_all(db_conn, portfolio_id),
+    }
+}
+
+// Returns the income of the paid dividends by month and by stock
+pub fn get_dividend_report(db_conn: &DbConn, portfolio_id: u32) -> Result<DividendReport, PersistanceError> {
+    let dividends = dividend::dividend_db::get_all(db_conn, portfolio_id)?;
+    Ok(dividend::get_report(&dividends))
+}
+
+// Works out the shares entitled to the dividends whose ex-date has been reached
+// and pays the ones whose pay-date has been reached. Returns the dividends paid.
+pub fn process_dividends(db_conn: &DbConn, portfolio_id: u32, date: NaiveDate) -> Result<Vec<Dividend>, PersistanceError> {
+    let mut paid = vec![];
+    let pending = dividend::dividend_db::get_all(db_conn, portfolio_id)?
+        .into_iter()
+        .filter(|dividend| !dividend.paid && dividend.ex_date <= date);
+
+    for mut dividend in pending {
+        if dividend.quantity.is_none() {
+            dividend.quantity = Some(get_quantity_held_before(db_conn, portfolio_id, &dividend.symbol, dividend.ex_date)?);
+            dividend::dividend_db::update(db_conn, &dividend)?;
+        }
+
+        if dividend.pay_date <= date {
+            dividend.paid = true;
+            in_transaction(db_conn, || pay_dividend(db_conn, portfolio_id, &dividend))?;
+            paid.push(dividend);
+        }
+    }
+
+    Ok(paid)
+}
+
+// Shares of a stock held at the end of the day before `date`
+fn get_quantity_held_before(db_conn: &DbConn, portfolio_id: u32, symbol: &str, date: NaiveDate) -> Result<Decimal, PersistanceError> {
+    let transactions = transaction::transaction_db::get_by_symbol(db_conn, portfolio_id, symbol)?;
+
+    if transactions.is_empty() {
+        // Without trades in the ledger only the current position is known
+        let stock = stock::stock_db::get(db_conn, portfolio_id, symbol)?;
+        return Ok(stock.map(|stock| stock.quantity).unwrap_or_default());
+    }
+
+    let actions = corporate_action::corporate_action_db::get_by_symbol(db_conn, portfolio_id, symbol)?;
+    Ok(transaction::quantity_held_before(&transactions, &actions, date))
+}
+
+// Credits the dividend to the cash of its currency, reinvesting it if the stock has DRIP enabled
+fn pay_dividend(db_conn: &DbConn, portfolio_id: u32, dividend: &Dividend) -> Result<(), PersistanceError> {
+    dividend::dividend_db::update(db_conn, dividend)?;
+
+    let gross = dividend.gross().unwrap_or_default();
+    if gross <= Decimal::zero() {
+        return Ok(());
+    }
+
+    cash::cash_db::add(db_conn, portfolio_id, &CashMovement {
+        id: 0,
+        currency: dividend.currency.clone(),
+        amount: gross,
+        kind: CashMovementKind::Dividend,
+        date: dividend.pay_date,
+        note: format!("dividend {}", dividend.symbol),
+        transaction_id: None,
+    })?;
+
+    let stock = match stock::stock_db::get(db_conn, portfolio_id, &dividend.symbol)? {
+        Some(stock) => stock,
+        None => return Ok(()),
+    };
+
+    if !stock.drip || stock.price <= Decimal::zero() || stock.currency() != dividend.currency {
+        return Ok(());
+    }
+
+    let quantity = gross.div_trunc(stock.price, Decimal::SCALE);
+    if quantity <= Decimal::zero() {
+        return Ok(());
+    }
+
+    buy_stock(db_conn, portfolio_id, &Transaction {
+        symbol: stock.symbol.clone(),
+        quantity,
+        price: stock.price,
+        date: dividend.pay_date,
+        ..Transaction::default()
+    })
+}
+
+// Gets the dividends a stock has paid or announced
+pub async fn get_dividend_calendar(client: &HttpClient, symbol: &str) -> Result<Vec<Dividend>, Box<dyn std::error::Error+Sync+Send>> {
+    let history = dividend::dividend_api::get_dividend_history(client, symbol).await?;
+    let mut dividends = vec![];
+
+    for element in history {
+        let ex_date: NaiveDate = element.date.parse()?;
+        let pay_date = match element.payment_date.as_deref() {
+            Some(date) if !date.is_empty() => date.parse()?,
+            _ => ex_date,
+        };
+
+        dividends.push(Dividend {
+            symbol: symbol.to_owned(),
+            amount: element.dividend,
+            ex_date,
+            pay_date,
+            ..Dividend::default()
+        });
+    }
+
+    Ok(dividends)
+}
+
+// Applies a corporate action to a stored stock, recording the position it had before
+pub fn add_corporate_action(db_conn: &DbConn, portfolio_id: u32, action: &CorporateAction) -> Result<(), PersistanceError> {
+    let mut stock = stock::stock_db::get(db_conn, portfolio_id, &action.symbol)?
+        .ok_or(PersistanceError::KeyNotFoundError)?;
+
+    let valid = match action.kind {
+        CorporateActionKind::Split | CorporateActionKind::ReverseSplit => action.ratio > Decimal::zero(),
+        CorporateActionKind::SymbolChange => !action.new_symbol.is_empty() && action.new_symbol != action.symbol,
+        CorporateActionKind::Merger => action.ratio >= Decimal::zero() && !action.new_symbol.is_empty(),
+        CorporateActionKind::Delisting => true,
+    };
+    if !valid || action.cash_per_share < Decimal::zero() {
+        return Err(PersistanceError::InvalidCorporateAction);
+    }
+
+    let action = CorporateAction {
+        id: 0,
+        quantity_before: stock.quantity,
+        initial_price_before: stock.initial_price,
+        ..action.clone()
+    };
+
+    // The price is shared by the portfolios holding the stock. It's adjusted once, unless
+    // it was quoted since the split took effect.
+    let adjust_price = action.is_split()
+        && !get_splits(db_conn, &action.symbol)?.iter().any(|split| split.date == action.date && split.kind == action.kind && split.ratio == action.ratio)
+        && price_history::price_history_db::get_latest(db_conn, &action.symbol)?
+            .map(|bar| bar.date < action.date)
+            .unwrap_or(true);
+
+    in_transaction(db_conn, || {
+        match action.kind {
+            CorporateActionKind::Split | CorporateActionKind::ReverseSplit => {
+                let factor = action.share_factor();
+                stock.quantity = stock.quantity * factor;
+                stock.initial_price = stock.initial_price / factor;
+                stock::stock_db::update(db_conn, portfolio_id, &stock)?;
+                if adjust_price {
+                    stock::stock_db::update_price(db_conn, &stock.symbol, stock.price / factor)?;
+                }
+            },
+            CorporateActionKind::SymbolChange => rename_stock(db_conn, portfolio_id, &action.symbol, &action.new_symbol)?,
+            CorporateActionKind::Merger => {
+                let mut acquirer = stock::stock_db::get(db_conn, portfolio_id, &action.new_symbol)?
+                    .ok_or(PersistanceError::KeyNotFoundError)?;
+                let cash = credit_corporate_action(db_conn, portfolio_id, &action, &stock)?;
+
+                // The cost of the position moves to the shares received, less the cash paid back
+                let quantity = acquirer.quantity + stock.quantity * action.ratio;
+                if quantity > Decimal::zero() {
+                    let cost = (stock.cost() - cash).max(Decimal::zero());
+                    acquirer.initial_price = (acquirer.cost() + cost) / quantity;
+                }
+                acquirer.quantity = quantity;
+                stock.quantity = Decimal::zero();
+
+                stock::stock_db::update(db_conn, portfolio_id, &acquirer)?;
+                stock::stock_db::update(db_conn, portfolio_id, &stock)?;
+            },
+            CorporateActionKind::Delisting => {
+                credit_corporate_action(db_conn, portfolio_id, &action, &stock)?;
+                stock.quantity = Decimal::zero();
+                stock::stock_db::update(db_conn, portfolio_id, &stock)?;
+            },
+        }
+
+        corporate_action::corporate_action_db::add(db_conn, portfolio_id, &action)
+    })
+}
+
+// Credits the cash paid for the shares of a stock by a corporate action. Returns the amount.
+fn credit_corporate_action(db_conn: &DbConn, portfolio_id: u32, action: &CorporateAction, stock: &Stock) -> Result<Decimal, PersistanceError> {
+    let amount = stock.quantity * action.cash_per_share;
+    if amount <= Decimal::zero() {
+        return Ok(Decimal::zero());
+    }
+
+    cash::cash_db::add(db_conn, portfolio_id, &CashMovement {
+        id: 0,
+        currency: stock.currency().to_owned(),
+        amount,
+        kind: CashMovementKind::CorporateAction,
+        date: action.date,
+        note: format!("{} {}", action.kind.as_str(), action.symbol),
+        transaction_id: None,
+    })?;
+
+    Ok(amount)
+}
+
+// Changes the symbol of a stock everywhere it's referenced in the portfolio. The provider
+// no longer quotes the old symbol, so the watchlists, alerts and benchmarks shared with
+// the other portfolios follow it too, and its cached profile is fetched again.
+fn rename_stock(db_conn: &DbConn, portfolio_id: u32, symbol: &str, new_symbol: &str) -> Result<(), PersistanceError> {
+    if stock::stock_db::get(db_conn, portfolio_id, new_symbol)?.is_some() {
+        return Err(PersistanceError::InvalidCorporateAction);
+    }
+
+    stock::stock_db::rename_symbol(db_conn, portfolio_id, symbol, new_symbol)?;
+    transaction::transaction_db::rename_symbol(db_conn, portfolio_id, symbol, new_symbol)?;
+    dividend::dividend_db::rename_symbol(db_conn, portfolio_id, symbol, new_symbol)?;
+    price_history::price_history_db::copy_symbol(db_conn, symbol, new_symbol)?;
+    stop::stop_db::rename_symbol(db_conn, portfolio_id, symbol, new_symbol)?;
+    order::order_db::rename_symbol(db_conn, portfolio_id, symbol, new_symbol)?;
+    plan::plan_db::rename_symbol(db_conn, portfolio_id, symbol, new_symbol)?;
+    target::target_db::rename_symbol(db_conn, portfolio_id, symbol, new_symbol)?;
+    watchlist::watchlist_db::rename_symbol(db_conn, symbol, new_symbol)?;
+    alert::alert_db::rename_symbol(db_conn, symbol, new_symbol)?;
+    benchmark::benchmark_db::rename_symbol(db_conn, symbol, new_symbol)?;
+    profile::profile_db::delete(db_conn, symbol)?;
+    corporate_action::corporate_action_db::rename_symbol(db_conn, portfolio_id, symbol, new_symbol)
+}
+
+// Returns the stored corporate actions, of a stock if given, oldest first
+pub fn get_corporate_actions(db_conn: &DbConn, portfolio_id: u32, symbol: Option<&str>) -> Result<Vec<CorporateAction>, PersistanceError> {
+    match symbol {
+        Some(symbol) => corporate_action::corporate_action_db::get_by_symbol(db_conn, portfolio_id, symbol),
+        None => corporate_action::corporate_action_db::get_all(db_conn, portfolio_id),
+    }
+}
+
+// Returns the stored prices of a stock, oldest first. Unless `raw` is set they're adjusted
+// for the splits recorded since, so they're comparable with today's price.
+pub fn get_price_history(db_conn: &DbConn, symbol: &str, raw: bool) -> Result<Vec<PriceBar>, PersistanceError> {
+    let bars = price_history::price_history_db::get_by_symbol(db_conn, symbol)?;
+    if raw {
+        return Ok(bars);
+    }
+
+    Ok(price_history::adjust_for_splits(bars, &get_splits(db_conn, symbol)?))
+}
+
+// The prices are shared by the portfolios, so are the splits any of them recorded. A split
+// recorded by several portfolios is returned once.
+fn get_splits(db_conn: &DbConn, symbol: &str) -> Result<Vec<CorporateAction>, PersistanceError> {
+    let mut splits = corporate_action::corporate_action_db::get_splits(db_conn, symbol)?;
+    splits.dedup_by(|split, other| split.date == other.date && split.kind == other.kind && split.ratio == other.ratio);
+    Ok(splits)
+}
+
+// Calculates an indicator over the daily prices of a stock, adjusted for its splits
+pub fn get_indicator(db_conn: &DbConn, symbol: &str, indicator: Indicator) -> Result<Vec<IndicatorValue>, PersistanceError> {
+    let bars = get_price_history(db_conn, symbol, false)?;
+    Ok(indicator.calculate(&bars))
+}
+
+// Runs a strategy over the stored prices of its symbols, adjusted for the splits
+// recorded. Nothing is read from the network or stored.
+pub fn run_backtest(db_conn: &DbConn, config: &BacktestConfig) -> Result<BacktestResult, PersistanceError> {
+    let mut strategy = config.strategy.build();
+    let mut bars = BTreeMap::new();
+    for symbol in strategy.symbols() {
+        let history = get_price_history(db_conn, &symbol, false)?;
+        bars.insert(symbol, history);
+    }
+
+    Ok(backtest::run(strategy.as_mut(), &bars, config, get_risk_free_rate(db_conn)?))
+}
+
+// Stores the prices of a stock, replacing the ones of the same days
+pub fn add_price_history(db_conn: &DbConn, bars: &[PriceBar]) -> Result<(), PersistanceError> {
+    in_transaction(db_conn, || {
+        for bar in bars {
+            price_history::price_history_db::add(db_conn, bar)?;
+        }
+        Ok(())
+    })
+}
+
+// Gets the daily prices of a stock
+pub async fn get_historical_prices(client: &HttpClient, symbol: &str) -> Result<Vec<PriceBar>, Box<dyn std::error::Error+Sync+Send>> {
+    let history = stock::stock_api::get_historical_prices(client, symbol).await?;
+    let mut bars = vec![];
+
+    for element in history {
+        bars.push(PriceBar {
+            symbol: symbol.to_owned(),
+            date: element.date.parse()?,
+            open: element.open,
+            high: element.high,
+            low: element.low,
+            close: element.close,
+            volume: element.volume,
+        });
+    }
+
+    Ok(bars)
+}
+
+// Returns the currency used to value the portfolio
+pub fn get_base_currency(db_conn: &DbConn) -> Result<String, PersistanceError> {
+    let currency = setting::setting_db::get(db_conn, setting::BASE_CURRENCY)?;
+    Ok(currency.unwrap_or_else(|| setting::DEFAULT_BASE_CURRENCY.to_owned()))
+}
+
+// Sets the currency used to value the portfolio
+pub fn set_base_currency(db_conn: &DbConn, currency: &str) -> Result<(), PersistanceError> {
+    setting::setting_db::set(db_conn, setting::BASE_CURRENCY, &currency.to_uppercase())
+}
+
+// Returns the annual percent the risk ratios are measured against, zero until set
+pub fn get_risk_free_rate(db_conn: &DbConn) -> Result<Decimal, PersistanceError> {
+    let rate = setting::setting_db::get(db_conn, setting::RISK_FREE_RATE)?;
+    Ok(rate.and_then(|rate| rate.parse().ok()).unwrap_or_default())
+}
+
+pub fn set_risk_free_rate(db_conn: &DbConn, rate: Decimal) -> Result<(), PersistanceError> {
+    setting::setting_db::set(db_conn, setting::RISK_FREE_RATE, &rate.to_string())
+}
+
+// Returns the percent the paper orders fill worse than the quote, zero until set
+pub fn get_slippage(db_conn: &DbConn) -> Result<Decimal, PersistanceError> {
+    let slippage = setting::setting_db::get(db_conn, setting::SLIPPAGE)?;
+    Ok(slippage.and_then(|slippage| slippage.parse().ok()).unwrap_or_default())
+}
+
+pub fn set_slippage(db_conn: &DbConn, slippage: Decimal) -> Result<(), PersistanceError> {
+    if slippage < Decimal::zero() || slippage >= Decimal::from(100) {
+        return Err(PersistanceError::InvalidAmount);
+    }
+
+    setting::setting_db::set(db_conn, setting::SLIPPAGE, &slippage.to_string())
+}
+
+// Returns the commission of a paper fill, zero until set
+pub fn get_commission(db_conn: &DbConn) -> Result<Decimal, PersistanceError> {
+    let commission = setting::setting_db::get(db_conn, setting::COMMISSION)?;
+    Ok(commission.and_then(|commission| commission.parse().ok()).unwrap_or_default())
+}
+
+pub fn set_commission(db_conn: &DbConn, commission: Decimal) -> Result<(), PersistanceError> {
+    if commission < Decimal::zero() {
+        return Err(PersistanceError::InvalidAmount);
+    }
+
+    setting::setting_db::set(db_conn, setting::COMMISSION, &commission.to_string())
+}
+
+// Stores an exchange rate, keeping the previous days
+pub fn add_fx_rate(db_conn: &DbConn, fx_rate: &FxRate) -> Result<(), PersistanceError> {
+    fx::fx_db::add(db_conn, fx_rate)
+}
+
+// Returns the stored rates of a currency pair, oldest first
+pub fn get_fx_history(db_conn: &DbConn, base: &str, quote: &str) -> Result<Vec<FxRate>, PersistanceError> {
+    fx::fx_db::get_history(db_conn, base, quote)
+}
+
+// Get the current exchange rate of a currency pair
+pub async fn get_current_fx_rate(client: &HttpClient, base: &str, quote: &str) -> Result<FxRate, Box<dyn std::error::Error+Sync+Send>> {
+    let fx_quote = fx::fx_api::get_fx_rate(client, base, quote).await?;
+
+    Ok(FxRate {
+        base: base.to_owned(),
+        quote: quote.to_owned(),
+        rate: fx_quote.price,
+        date: today(),
+    })
+}
+
+// Updates the price of the stocks with the symbol, recording it in the price history of the day
+pub fn update_price(db_conn: &DbConn, symbol: &str, price: Decimal) -> Result<(), PersistanceError> {
+    let date = today();
+    let bar = price_history::price_history_db::get(db_conn, symbol, date)?;
+
+    in_transaction(db_conn, || {
+        stock::stock_db::update_price(db_conn, symbol, price)?;
+        price_history::price_history_db::add(db_conn, &PriceBar::with_quote(bar, symbol, date, price))
+    })
+}
+
+// Get the current price of a stock
+pub async fn get_current_price(client: &HttpClient, symbol: &str) ->Result<Decimal, Box<dyn std::error::Error+Sync+Send>> {
+    match stock::stock_api::get_stock_price(client, symbol).await {
+        Ok(stock_price) => Ok(stock_price.price),
+        Err(e) => Err(e)
+    }
+}
+
+// Fills the sector, industry and country of a stock from its company profile.
+// Returns false if the API has no profile for it.
+pub async fn fill_stock_profile(client: &HttpClient, stock: &mut Stock) -> Result<bool, Box<dyn std::error::Error+Sync+Send>> {
+    let profile = match stock::stock_api::get_profile(client, &stock.symbol).await? {
+        Some(profile) => profile,
+        None => return Ok(false),
+    };
+
+    stock.sector = profile.sector.unwrap_or_default();
+    stock.industry = profile.industry.unwrap_or_default();
+    stock.country = profile.country.unwrap_or_default();
+    Ok(true)
+}
+
+// Returns the cached profile of a company, however old it is
+pub fn get_cached_profile(db_conn: &DbConn, symbol: &str) -> Result<Option<CompanyProfile>, PersistanceError> {
+    profile::profile_db::get(db_conn, symbol)
+}
+
+// Caches the profile of a company
+pub fn cache_profile(db_conn: &DbConn, profile: &CompanyProfile) -> Result<(), PersistanceError> {
+    profile::profile_db::set(db_conn, profile)
+}
+
+// Gets the profile and the key metrics of a company. Returns None if the API doesn't know it.
+pub async fn get_company_profile(client: &HttpClient, symbol: &str) -> Result<Option<CompanyProfile>, Box<dyn std::error::Error+Sync+Send>> {
+    let profile = match stock::stock_api::get_profile(client, symbol).await? {
+        Some(profile) => profile,
+        None => return Ok(None),
+    };
+    let metrics = stock::stock_api::get_key_metrics(client, symbol).await?;
+
+    Ok(Some(CompanyProfile::new(symbol, profile, metrics, Local::now().naive_local())))
+}
+
+// Returns a list of all the available stocks in the API
+pub async fn get_available_stocks(client: &HttpClient, ) -> Result<Vec<Stock>, Box<dyn std::error::Error+Sync+Send>>{
+    match stock::stock_api::get_stock_list(client).await {
+        Ok(stocks) => {
+            Ok(stocks
+                .iter()
+                .map(Stock::from)
+                .collect::<Vec<Stock>>())
+        },
+        Err(e) => Err(e)
+    }
+}
+
+// Creates an empty portfolio
+pub fn add_portfolio(db_conn: &DbConn, name: &str) -> Result<(), PersistanceError> {
+    portfolio::portfolio_db::add(db_conn, name)
+}
+
+// Changes the name of a portfolio
+pub fn rename_portfolio(db_conn: &DbConn, name: &str, new_name: &str) -> Result<(), PersistanceError> {
+    let portfolio = portfolio::portfolio_db::get_by_name(db_conn, name)?
+        .ok_or(PersistanceError::KeyNotFoundError)?;
+    portfolio::portfolio_db::rename(db_conn, portfolio.id, new_name)
+}
+
+// Deletes a portfolio without holdings, cash or ledger. The default one can't be deleted.
+pub fn delete_portfolio(db_conn: &DbConn, name: &str) -> Result<(), PersistanceError> {
+    let portfolio = portfolio::portfolio_db::get_by_name(db_conn, name)?
+        .ok_or(PersistanceError::KeyNotFoundError)?;
+
+    if portfolio.id == DEFAULT_PORTFOLIO_ID
+            || !stock::stock_db::get_all(db_conn, portfolio.id)?.is_empty()
+            || !cash::cash_db::get_all(db_conn, portfolio.id)?.is_empty() {
+        return Err(PersistanceError::EntryHasDependencies);
+    }
+
+    in_transaction(db_conn, || {
+        benchmark::benchmark_db::delete_all(db_conn, portfolio.id)?;
+        target::target_db::delete_all(db_conn, portfolio.id)?;
+        stop::stop_db::delete_all(db_conn, portfolio.id)?;
+        order::order_db::delete_all(db_conn, portfolio.id)?;
+        plan::plan_db::delete_all(db_conn, portfolio.id)?;
+        portfolio::portfolio_db::delete(db_conn, portfolio.id)
+    })
+}
+
+// Returns every portfolio
+pub fn get_portfolios(db_conn: &DbConn) -> Result<Vec<Portfolio>, PersistanceError> {
+    portfolio::portfolio_db::get_all(db_conn)
+}
+
+// Returns the portfolio with the given name, or the default one
+pub fn get_portfolio(db_conn: &DbConn, name: Option<&str>) -> Result<Option<Portfolio>, PersistanceError> {
+    match name {
+        Some(name) => portfolio::portfolio_db::get_by_name(db_conn, name),
+        None => portfolio::portfolio_db::get(db_conn, DEFAULT_PORTFOLIO_ID),
+    }
+}
+
+// Creates an empty watchlist
+pub fn add_watchlist(db_conn: &DbConn, name: &str) -> Result<(), PersistanceError> {
+    watchlist::watchlist_db::add(db_conn, name)
+}
+
+// Deletes a watchlist along with its items
+pub fn delete_watchlist(db_conn: &DbConn, name: &str) -> Result<(), PersistanceError> {
+    let watchlist = get_stored_watchlist(db_conn, name)?;
+    in_transaction(db_conn, || watchlist::watchlist_db::delete(db_conn, watchlist.id))
+}
+
+// Returns every watchlist
+pub fn get_watchlists(db_conn: &DbConn) -> Result<Vec<Watchlist>, PersistanceError> {
+    watchlist::watchlist_db::get_all(db_conn)
+}
+
+// Adds a symbol to a watchlist
+pub fn add_to_watchlist(db_conn: &DbConn, name: &str, item: &WatchlistItem) -> Result<(), PersistanceError> {
+    let watchlist = get_stored_watchlist(db_conn, name)?;
+    watchlist::watchlist_db::add_item(db_conn, watchlist.id, item)
+}
+
+// Changes the target price and the note of a symbol of a watchlist
+pub fn update_watchlist_item(db_conn: &DbConn, name: &str, item: &WatchlistItem) -> Result<(), PersistanceError> {
+    let watchlist = get_stored_watchlist(db_conn, name)?;
+
+    match watchlist::watchlist_db::update_item(db_conn, watchlist.id, item)? {
+        true => Ok(()),
+        false => Err(PersistanceError::KeyNotFoundError)
+    }
+}
+
+// Removes a symbol from a watchlist
+pub fn remove_from_watchlist(db_conn: &DbConn, name: &str, symbol: &str) -> Result<(), PersistanceError> {
+    let watchlist = get_stored_watchlist(db_conn, name)?;
+    watchlist::watchlist_db::delete_item(db_conn, watchlist.id, symbol)
+}
+
+// Returns the symbols of a watchlist with their last price and the distance to their target
+pub fn get_watchlist(db_conn: &DbConn, name: &str) -> Result<Vec<WatchlistEntry>, PersistanceError> {
+    let watchlist = get_stored_watchlist(db_conn, name)?;
+    let mut entries = vec![];
+
+    for item in watchlist::watchlist_db::get_items(db_conn, watchlist.id)? {
+        let last_bar = price_history::price_history_db::get_latest(db_conn, &item.symbol)?;
+        entries.push(WatchlistEntry::new(item, last_bar));
+    }
+
+    Ok(entries)
+}
+
+// Adds a symbol the portfolio is compared against
+pub fn add_benchmark(db_conn: &DbConn, portfolio_id: u32, symbol: &str) -> Result<(), PersistanceError> {
+    benchmark::benchmark_db::add(db_conn, portfolio_id, &symbol.to_uppercase())
+}
+
+pub fn remove_benchmark(db_conn: &DbConn, portfolio_id: u32, symbol: &str) -> Result<(), PersistanceError> {
+    benchmark::benchmark_db::delete(db_conn, portfolio_id, &symbol.to_uppercase())
+}
+
+// Returns the benchmarks of the portfolio, in the order they were added
+pub fn get_benchmarks(db_conn: &DbConn, portfolio_id: u32) -> Result<Vec<String>, PersistanceError> {
+    benchmark::benchmark_db::get_all(db_conn, portfolio_id)
+}
+
+// Returns the symbols followed by any watchlist
+pub fn get_watched_symbols(db_conn: &DbConn) -> Result<Vec<String>, PersistanceError> {
+    watchlist::watchlist_db::get_symbols(db_conn)
+}
+
+fn get_stored_watchlist(db_conn: &DbConn, name: &str) -> Result<Watchlist, PersistanceError> {
+    watchlist::watchlist_db::get_by_name(db_conn, name)?
+        .ok_or(PersistanceError::KeyNotFoundError)
+}
+
+// Stores a rule on the price of a symbol
+pub fn add_alert(db_conn: &DbConn, alert: &Alert) -> Result<(), PersistanceError> {
+    if !alert.condition.is_valid() {
+        return Err(PersistanceError::InvalidAmount);
+    }
+
+    alert::alert_db::add(db_conn, &Alert { symbol: alert.symbol.to_uppercase(), ..alert.clone() })
+}
+
+// Deletes an alert along with its webhooks, the record of their deliveries is kept
+pub fn delete_alert(db_conn: &DbConn, id: u32) -> Result<(), PersistanceError> {
+    in_transaction(db_conn, || {
+        webhook::webhook_db::delete_by_alert(db_conn, id)?;
+
+        match alert::alert_db::delete(db_conn, id)? {
+            true => Ok(()),
+            false => Err(PersistanceError::KeyNotFoundError),
+        }
+    })
+}
+
+pub fn get_alerts(db_conn: &DbConn) -> Result<Vec<Alert>, PersistanceError> {
+    alert::alert_db::get_all(db_conn)
+}
+
+// Returns the symbols with an alert
+pub fn get_alert_symbols(db_conn: &DbConn) -> Result<Vec<String>, PersistanceError> {
+    alert::alert_db::get_symbols(db_conn)
+}
+
+// Checks the alerts against the stored daily prices, returning the ones that fire.
+// Only the symbols with a price of `date` are checked, and every alert fires at most
+// once a day.
+pub fn evaluate_alerts(db_conn: &DbConn, date: NaiveDate) -> Result<Vec<AlertEvent>, PersistanceError> {
+    let mut bars_by_symbol: HashMap<String, Vec<PriceBar>> = HashMap::new();
+    let mut events = vec![];
+
+    for alert in alert::alert_db::get_all(db_conn)? {
+        if alert.last_triggered == Some(date) {
+            continue;
+        }
+
+        if !bars_by_symbol.contains_key(&alert.symbol) {
+            let bars = price_history::price_history_db::get_by_symbol(db_conn, &alert.symbol)?;
+            bars_by_symbol.insert(alert.symbol.clone(), bars);
+        }
+        let bars = &bars_by_symbol[&alert.symbol];
+
+        let last = match bars.last() {
+            Some(last) if last.date == date => last,
+            _ => continue,
+        };
+
+        if alert.condition.is_met(bars) {
+            alert::alert_db::set_triggered(db_conn, alert.id, date)?;
+            events.push(AlertEvent {
+                alert_id: alert.id,
+                symbol: alert.symbol.clone(),
+                condition: alert.condition.clone(),
+                date,
+                price: last.close,
+            });
+        }
+    }
+
+    Ok(events)
+}
+
+// Registers a URL the events of an alert are posted to
+pub fn add_webhook(db_conn: &DbConn, webhook: &Webhook) -> Result<(), PersistanceError> {
+    if !webhook.is_valid() {
+        return Err(PersistanceError::InvalidWebhook);
+    }
+    if !alert::alert_db::get_all(db_conn)?.iter().any(|alert| alert.id == webhook.alert_id) {
+        return Err(PersistanceError::KeyNotFoundError);
+    }
 
-pub type DbConn = PooledConnection<SqliteConnectionManager>;
-pub type HttpClient = hyper::Client<HttpsConnector<hyper::client::HttpConnector>, hyper::Body>;
+    webhook::webhook_db::add(db_conn, webhook)
+}
 
-// Stores a stock in the local storage
-pub fn add_stock(db_conn: &DbConn, stock: &Stock) -> Result<(), PersistanceError> {
-    stock::stock_db::add(db_conn, stock)
+pub fn delete_webhook(db_conn: &DbConn, id: u32) -> Result<(), PersistanceError> {
+    match webhook::webhook_db::delete(db_conn, id)? {
+        true => Ok(()),
+        false => Err(PersistanceError::KeyNotFoundError),
+    }
 }
 
-// Deletes a stock from the local storage
-pub fn delete_stock(db_conn: &DbConn, symbol: &str) -> Result<(), PersistanceError> {
-    stock::stock_db::delete(db_conn, symbol)
+// Returns every webhook, without its secret
+pub fn get_webhooks(db_conn: &DbConn) -> Result<Vec<Webhook>, PersistanceError> {
+    let webhooks = webhook::webhook_db::get_all(db_conn)?;
+    Ok(webhooks.into_iter().map(|webhook| Webhook { secret: String::new(), ..webhook }).collect())
 }
 
-// Returns a vector with all the stored stocks
-pub fn get_stored_stocks(db_conn: &DbConn)  -> Result<Vec<Stock>, PersistanceError> {
-    stock::stock_db::get_all(db_conn)
+pub fn get_alert_webhooks(db_conn: &DbConn, alert_id: u32) -> Result<Vec<Webhook>, PersistanceError> {
+    webhook::webhook_db::get_by_alert(db_conn, alert_id)
 }
 
-// Updates the price of a stored stock.
-pub fn update_price(db_conn: &DbConn, symbol: &str, price: f32) -> Result<(), PersistanceError> {
-    stock::stock_db::update_price(db_conn, symbol, price)
+pub fn add_deliveries(db_conn: &DbConn, deliveries: &[Delivery]) -> Result<(), PersistanceError> {
+    in_transaction(db_conn, || {
+        for delivery in deliveries {
+            webhook::webhook_db::add_delivery(db_conn, delivery)?;
+        }
+        Ok(())
+    })
 }
 
-// Get the current price of a stock
-pub async fn get_current_price(client: &HttpClient, symbol: &str) ->Result<f32, Box<dyn std::error::Error+Sync+Send>> {
-    match stock::stock_api::get_stock_price(client, symbol).await {
-        Ok(stock_price) => Ok(stock_price.price),
-        Err(e) => Err(e)
+// Returns the attempts at posting to the webhooks, of an alert if given
+pub fn get_deliveries(db_conn: &DbConn, alert_id: Option<u32>) -> Result<Vec<Delivery>, PersistanceError> {
+    webhook::webhook_db::get_deliveries(db_conn, alert_id)
+}
+
+// Posts the payload to the webhook until it's accepted, waiting `backoff` before the
+// first retry and twice as long before every other one. An attempt without a response
+// within `timeout` failed. Returns every attempt.
+pub async fn deliver_webhook<C>(client: &hyper::Client<C>, webhook: &Webhook, payload: &[u8], backoff: Duration, timeout: Duration) -> Vec<Delivery>
+        where C: hyper::client::connect::Connect + Clone + Send + Sync + 'static {
+    let mut deliveries: Vec<Delivery> = vec![];
+    let mut wait = backoff;
+
+    for attempt in 1..=webhook::MAX_ATTEMPTS {
+        if attempt > 1 {
+            tokio::time::delay_for(wait).await;
+            wait *= 2;
+        }
+
+        let post = webhook::webhook_api::post(client, &webhook.url, &webhook.secret, payload.to_vec());
+        let result = tokio::time::timeout(timeout, post).await
+            .unwrap_or_else(|elapsed| Err(elapsed.into()));
+        let delivery = Delivery {
+            webhook_id: webhook.id,
+            alert_id: webhook.alert_id,
+            attempt,
+            timestamp: Local::now().naive_local(),
+            status: result.as_ref().ok().cloned(),
+            error: result.as_ref().err().map(|e| e.to_string()).unwrap_or_default(),
+            delivered: matches!(result, Ok(status) if (200..300).contains(&status)),
+            ..Delivery::default()
+        };
+
+        let retriable = delivery.is_retriable();
+        deliveries.push(delivery);
+        if !retriable {
+            break;
+        }
     }
+
+    deliveries
 }
 
-// Returns a list of all the available stocks in the API
-pub async fn get_available_stocks(client: &HttpClient, ) -> Result<Vec<Stock>, Box<dyn std::error::Error+Sync+Send>>{
-    match stock::stock_api::get_stock_list(client).await {
-        Ok(stocks) => {
-            Ok(stocks
-                .iter()
-                .map(Stock::from)
-                .collect::<Vec<Stock>>())
-        },
-        Err(e) => Err(e)
+// Stores a market in the local storage
+pub fn add_market(db_conn: &DbConn, market: &Market) -> Result<(), PersistanceError> {
+    market::market_db::add(db_conn, market)
+}
+
+// Updates the stored data of a market
+pub fn update_market(db_conn: &DbConn, market: &Market) -> Result<(), PersistanceError> {
+    match market::market_db::get(db_conn, market.id)? {
+        Some(_) => market::market_db::update(db_conn, market),
+        None => Err(PersistanceError::KeyNotFoundError)
+    }
+}
+
+// Deletes a market from the local storage, as long as no stock belongs to it
+pub fn delete_market(db_conn: &DbConn, id: u16) -> Result<(), PersistanceError> {
+    if market::market_db::get(db_conn, id)?.is_none() {
+        return Err(PersistanceError::KeyNotFoundError);
+    }
+
+    if !stock::stock_db::get_by_market(db_conn, id)?.is_empty() {
+        return Err(PersistanceError::EntryHasDependencies);
+    }
+
+    in_transaction(db_conn, || {
+        market::market_db::delete_holidays(db_conn, id)?;
+        market::market_db::delete(db_conn, id)
+    })
+}
+
+// Marks a day the market is closed
+pub fn add_holiday(db_conn: &DbConn, holiday: &MarketHoliday) -> Result<(), PersistanceError> {
+    if market::market_db::get(db_conn, holiday.market_id)?.is_none() {
+        return Err(PersistanceError::KeyNotFoundError);
+    }
+
+    market::market_db::add_holiday(db_conn, holiday)
+}
+
+pub fn remove_holiday(db_conn: &DbConn, market_id: u16, date: NaiveDate) -> Result<(), PersistanceError> {
+    match market::market_db::delete_holiday(db_conn, market_id, date)? {
+        true => Ok(()),
+        false => Err(PersistanceError::KeyNotFoundError),
+    }
+}
+
+pub fn get_holidays(db_conn: &DbConn, market_id: u16) -> Result<Vec<MarketHoliday>, PersistanceError> {
+    market::market_db::get_holidays(db_conn, market_id)
+}
+
+fn get_holiday_dates(db_conn: &DbConn, market_id: u16) -> Result<Vec<NaiveDate>, PersistanceError> {
+    Ok(market::market_db::get_holidays(db_conn, market_id)?
+        .into_iter()
+        .map(|holiday| holiday.date)
+        .collect())
+}
+
+// Returns a list of all the stored markets
+pub fn get_stored_markets(db_conn: &DbConn) -> Result<Vec<Market>, PersistanceError> {
+    market::market_db::get_all(db_conn)
+}
+
+#[cfg(test)]
+pub fn get_test_connection() -> DbConn {
+    let manager = SqliteConnectionManager::memory();
+    let pool = r2d2::Pool::builder().max_size(1).build(manager).unwrap();
+    let connection = pool.get().unwrap();
+    init_storage(&connection).unwrap();
+    connection
+}
+
+#[test]
+fn test_get_portfolio_by_market() {
+    let db_conn = get_test_connection();
+    add_market(&db_conn, &Market { id: 0, symbol: "NYSE".into(), currency: "USD".into() }).unwrap();
+    let market = get_stored_markets(&db_conn).unwrap().remove(0);
+
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock {
+        symbol: "KO".into(),
+        name: "Coca-Cola".into(),
+        price: Decimal::new(6000, 2),
+        initial_price: Decimal::new(5000, 2),
+        quantity: Decimal::from(2),
+        market: market.clone(),
+        ..Stock::default()
+    }).unwrap();
+
+    let by_market = stock::stock_db::get_by_market(&db_conn, market.id).unwrap();
+    assert_eq!(1, by_market.len());
+    assert_eq!(market, by_market[0].market);
+
+    let portfolio = get_portfolio_by_market(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap();
+    assert_eq!(1, portfolio.len());
+    assert_eq!("USD", portfolio[0].currency);
+    assert_eq!(Decimal::new(120, 0), portfolio[0].total_value);
+    assert_eq!(Some(Decimal::new(120, 0)), portfolio[0].total_value_base);
+}
+
+#[test]
+fn test_get_rate_uses_latest_and_inverse() {
+    let db_conn = get_test_connection();
+    let date = |day| NaiveDate::from_ymd_opt(2020, 5, day).unwrap();
+    let eur_usd = |rate, day| FxRate { base: "EUR".into(), quote: "USD".into(), rate, date: date(day) };
+
+    add_fx_rate(&db_conn, &eur_usd(Decimal::new(125, 2), 4)).unwrap();
+    add_fx_rate(&db_conn, &eur_usd(Decimal::new(8, 1), 6)).unwrap();
+
+    assert_eq!(None, fx::get_rate(&db_conn, "EUR", "USD", date(3)).unwrap());
+    assert_eq!(Some(Decimal::new(125, 2)), fx::get_rate(&db_conn, "EUR", "USD", date(5)).unwrap());
+    assert_eq!(Some(Decimal::new(125, 2)), fx::get_rate(&db_conn, "USD", "EUR", date(7)).unwrap());
+    assert_eq!(2, get_fx_history(&db_conn, "EUR", "USD").unwrap().len());
+
+    set_base_currency(&db_conn, "eur").unwrap();
+    assert_eq!("EUR", get_base_currency(&db_conn).unwrap());
+}
+
+#[test]
+fn test_delete_market_with_stocks() {
+    let db_conn = get_test_connection();
+    add_market(&db_conn, &Market { id: 0, symbol: "NYSE".into(), currency: "USD".into() }).unwrap();
+    let market = get_stored_markets(&db_conn).unwrap().remove(0);
+
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock {
+        symbol: "KO".into(),
+        name: "Coca-Cola".into(),
+        price: Decimal::new(6000, 2),
+        initial_price: Decimal::new(5000, 2),
+        quantity: Decimal::from(2),
+        market: market.clone(),
+        ..Stock::default()
+    }).unwrap();
+
+    assert!(matches!(delete_market(&db_conn, market.id), Err(PersistanceError::EntryHasDependencies)));
+    assert!(matches!(delete_market(&db_conn, 42), Err(PersistanceError::KeyNotFoundError)));
+
+    delete_stock(&db_conn, DEFAULT_PORTFOLIO_ID, "KO").unwrap();
+    assert!(delete_market(&db_conn, market.id).is_ok());
+}
+
+#[test]
+fn test_buy_and_sell_move_cash() {
+    let db_conn = get_test_connection();
+    let date = NaiveDate::from_ymd_opt(2020, 5, 4).unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "KO".into(), currency: "USD".into(), ..Stock::default() }).unwrap();
+    let trade = |quantity, price| Transaction {
+        id: 0,
+        symbol: "KO".into(),
+        kind: TransactionKind::Buy,
+        quantity: Decimal::from(quantity),
+        price: Decimal::from(price),
+        commission: Decimal::from(1),
+        currency: String::new(),
+        date,
+        realized_gain: Decimal::zero(),
+    };
+    let usd = |amount| CashMovement {
+        id: 0,
+        currency: "USD".into(),
+        amount: Decimal::from(amount),
+        kind: CashMovementKind::Deposit,
+        date,
+        note: String::new(),
+        transaction_id: None,
+    };
+
+    assert!(matches!(buy_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &trade(10, 40)), Err(PersistanceError::NotEnoughCash)));
+
+    deposit(&db_conn, DEFAULT_PORTFOLIO_ID, &usd(1000)).unwrap();
+    buy_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &trade(10, 40)).unwrap();
+    buy_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &trade(10, 50)).unwrap();
+    assert_eq!(Decimal::from(98), get_cash_balance(&db_conn, DEFAULT_PORTFOLIO_ID, "USD").unwrap());
+
+    let stock = stock::stock_db::get(&db_conn, DEFAULT_PORTFOLIO_ID, "KO").unwrap().unwrap();
+    assert_eq!(Decimal::from(20), stock.quantity);
+    assert_eq!(Decimal::new(451, 1), stock.initial_price);
+
+    assert!(matches!(sell_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &trade(30, 60)), Err(PersistanceError::NotEnoughShares)));
+    sell_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &trade(5, 60)).unwrap();
+    assert_eq!(Decimal::from(397), get_cash_balance(&db_conn, DEFAULT_PORTFOLIO_ID, "USD").unwrap());
+    assert_eq!(Decimal::new(735, 1), get_transactions(&db_conn, DEFAULT_PORTFOLIO_ID, Some("KO")).unwrap()[2].realized_gain);
+
+    assert!(matches!(withdraw(&db_conn, DEFAULT_PORTFOLIO_ID, &usd(400)), Err(PersistanceError::NotEnoughCash)));
+    withdraw(&db_conn, DEFAULT_PORTFOLIO_ID, &usd(397)).unwrap();
+    assert_eq!(vec![CashBalance { currency: "USD".into(), balance: Decimal::zero() }], get_cash_balances(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap());
+    assert_eq!(5, get_cash_movements(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap().len());
+}
+
+#[test]
+fn test_amounts_that_overflow_are_rejected() {
+    let db_conn = get_test_connection();
+    let huge = Decimal::from(i64::MAX);
+    let stock = Stock { symbol: "KO".into(), currency: "USD".into(), price: huge, quantity: huge, ..Stock::default() };
+    assert!(matches!(add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &stock), Err(PersistanceError::InvalidAmount)));
+
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { price: Decimal::from(50), quantity: Decimal::zero(), ..stock.clone() }).unwrap();
+    assert!(matches!(update_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &stock), Err(PersistanceError::InvalidAmount)));
+
+    let trade = Transaction {
+        id: 0,
+        symbol: "KO".into(),
+        kind: TransactionKind::Buy,
+        quantity: huge,
+        price: huge,
+        commission: Decimal::zero(),
+        currency: String::new(),
+        date: NaiveDate::from_ymd_opt(2020, 5, 4).unwrap(),
+        realized_gain: Decimal::zero(),
+    };
+    assert!(matches!(buy_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &trade), Err(PersistanceError::InvalidAmount)));
+    assert!(matches!(sell_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &trade), Err(PersistanceError::InvalidAmount)));
+}
+
+#[test]
+fn test_process_dividends() {
+    let db_conn = get_test_connection();
+    let date = |month, day| NaiveDate::from_ymd_opt(2020, month, day).unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "KO".into(), currency: "USD".into(), ..Stock::default() }).unwrap();
+    deposit(&db_conn, DEFAULT_PORTFOLIO_ID, &CashMovement { currency: "USD".into(), amount: Decimal::from(1000), date: date(1, 1), ..CashMovement::default() }).unwrap();
+
+    let trade = |quantity, day| Transaction {
+        symbol: "KO".into(),
+        quantity: Decimal::from(quantity),
+        price: Decimal::from(40),
+        date: date(3, day),
+        ..Transaction::default()
+    };
+    buy_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &trade(10, 1)).unwrap();
+    buy_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &trade(5, 13)).unwrap();
+
+    add_dividend(&db_conn, DEFAULT_PORTFOLIO_ID, &Dividend {
+        symbol: "KO".into(),
+        amount: Decimal::new(41, 2),
+        ex_date: date(3, 13),
+        pay_date: date(4, 1),
+        ..Dividend::default()
+    }).unwrap();
+
+    assert!(process_dividends(&db_conn, DEFAULT_PORTFOLIO_ID, date(3, 12)).unwrap().is_empty());
+    assert!(process_dividends(&db_conn, DEFAULT_PORTFOLIO_ID, date(3, 20)).unwrap().is_empty());
+    assert_eq!(Some(Decimal::from(10)), get_dividends(&db_conn, DEFAULT_PORTFOLIO_ID, Some("KO")).unwrap()[0].quantity);
+    assert_eq!(Decimal::from(400), get_cash_balance(&db_conn, DEFAULT_PORTFOLIO_ID, "USD").unwrap());
+
+    let paid = process_dividends(&db_conn, DEFAULT_PORTFOLIO_ID, date(4, 1)).unwrap();
+    assert_eq!(Some(Decimal::new(41, 1)), paid[0].gross());
+    assert_eq!(Decimal::new(4041, 1), get_cash_balance(&db_conn, DEFAULT_PORTFOLIO_ID, "USD").unwrap());
+    assert!(process_dividends(&db_conn, DEFAULT_PORTFOLIO_ID, date(4, 2)).unwrap().is_empty());
+
+    let report = get_dividend_report(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap();
+    assert_eq!("2020-04", report.by_month[0].month);
+    assert_eq!(Decimal::new(41, 1), report.by_symbol[0].amount);
+}
+
+#[test]
+fn test_split_adjusts_position_and_history() {
+    let db_conn = get_test_connection();
+    let date = |month, day| NaiveDate::from_ymd_opt(2020, month, day).unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "AAPL".into(), currency: "USD".into(), ..Stock::default() }).unwrap();
+    deposit(&db_conn, DEFAULT_PORTFOLIO_ID, &CashMovement { currency: "USD".into(), amount: Decimal::from(4000), date: date(1, 1), ..CashMovement::default() }).unwrap();
+    buy_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Transaction {
+        symbol: "AAPL".into(),
+        quantity: Decimal::from(10),
+        price: Decimal::from(400),
+        date: date(8, 3),
+        ..Transaction::default()
+    }).unwrap();
+    add_price_history(&db_conn, &[
+        PriceBar { symbol: "AAPL".into(), date: date(8, 28), close: Decimal::from(500), ..PriceBar::default() },
+        PriceBar { symbol: "AAPL".into(), date: date(8, 31), close: Decimal::from(130), ..PriceBar::default() },
+    ]).unwrap();
+
+    add_corporate_action(&db_conn, DEFAULT_PORTFOLIO_ID, &CorporateAction {
+        symbol: "AAPL".into(),
+        kind: CorporateActionKind::Split,
+        date: date(8, 31),
+        ratio: Decimal::from(4),
+        ..CorporateAction::default()
+    }).unwrap();
+
+    let stock = stock::stock_db::get(&db_conn, DEFAULT_PORTFOLIO_ID, "AAPL").unwrap().unwrap();
+    assert_eq!(Decimal::from(40), stock.quantity);
+    assert_eq!(Decimal::from(100), stock.initial_price);
+    assert_eq!(Decimal::from(10), get_corporate_actions(&db_conn, DEFAULT_PORTFOLIO_ID, Some("AAPL")).unwrap()[0].quantity_before);
+
+    let history = get_price_history(&db_conn, "AAPL", false).unwrap();
+    assert_eq!(Decimal::from(125), history[0].close);
+    assert_eq!(Decimal::from(130), history[1].close);
+    assert_eq!(Decimal::from(500), get_price_history(&db_conn, "AAPL", true).unwrap()[0].close);
+
+    assert_eq!(Decimal::from(10), get_quantity_held_before(&db_conn, DEFAULT_PORTFOLIO_ID, "AAPL", date(8, 31)).unwrap());
+    assert_eq!(Decimal::from(40), get_quantity_held_before(&db_conn, DEFAULT_PORTFOLIO_ID, "AAPL", date(9, 1)).unwrap());
+}
+
+#[test]
+fn test_splits_of_every_portfolio_adjust_history_once() {
+    let db_conn = get_test_connection();
+    let date = |month, day| NaiveDate::from_ymd_opt(2020, month, day).unwrap();
+    add_portfolio(&db_conn, "ira").unwrap();
+    add_price_history(&db_conn, &[PriceBar { symbol: "AAPL".into(), date: date(8, 28), close: Decimal::from(500), ..PriceBar::default() }]).unwrap();
+    let split = CorporateAction { symbol: "AAPL".into(), kind: CorporateActionKind::Split, date: date(8, 31), ratio: Decimal::from(4), ..CorporateAction::default() };
+
+    // The split recorded by the IRA alone adjusts the prices the other portfolio sees
+    for portfolio_id in &[DEFAULT_PORTFOLIO_ID, 2] {
+        add_stock(&db_conn, *portfolio_id, &Stock { symbol: "AAPL".into(), currency: "USD".into(), ..Stock::default() }).unwrap();
+    }
+    add_corporate_action(&db_conn, 2, &split).unwrap();
+    assert_eq!(Decimal::from(125), get_price_history(&db_conn, "AAPL", false).unwrap()[0].close);
+
+    add_corporate_action(&db_conn, DEFAULT_PORTFOLIO_ID, &split).unwrap();
+    assert_eq!(Decimal::from(125), get_price_history(&db_conn, "AAPL", false).unwrap()[0].close);
+}
+
+#[test]
+fn test_split_adjusts_price_once() {
+    let db_conn = get_test_connection();
+    let date = |month, day| NaiveDate::from_ymd_opt(2020, month, day).unwrap();
+    add_portfolio(&db_conn, "ira").unwrap();
+    for portfolio_id in &[DEFAULT_PORTFOLIO_ID, 2] {
+        add_stock(&db_conn, *portfolio_id, &Stock { symbol: "AAPL".into(), currency: "USD".into(), price: Decimal::from(500), ..Stock::default() }).unwrap();
+    }
+    add_price_history(&db_conn, &[PriceBar { symbol: "AAPL".into(), date: date(8, 28), close: Decimal::from(500), ..PriceBar::default() }]).unwrap();
+    let split = CorporateAction { symbol: "AAPL".into(), kind: CorporateActionKind::Split, date: date(8, 31), ratio: Decimal::from(4), ..CorporateAction::default() };
+
+    // The price quoted before the split is adjusted in every portfolio, but only the first time it's recorded
+    add_corporate_action(&db_conn, DEFAULT_PORTFOLIO_ID, &split).unwrap();
+    add_corporate_action(&db_conn, 2, &split).unwrap();
+    for portfolio_id in &[DEFAULT_PORTFOLIO_ID, 2] {
+        assert_eq!(Decimal::from(125), stock::stock_db::get(&db_conn, *portfolio_id, "AAPL").unwrap().unwrap().price);
+    }
+
+    // A price quoted since the split already is in shares after it
+    add_price_history(&db_conn, &[PriceBar { symbol: "AAPL".into(), date: date(9, 30), close: Decimal::from(115), ..PriceBar::default() }]).unwrap();
+    stock::stock_db::update_price(&db_conn, "AAPL", Decimal::from(115)).unwrap();
+    add_corporate_action(&db_conn, DEFAULT_PORTFOLIO_ID, &CorporateAction { kind: CorporateActionKind::ReverseSplit, date: date(9, 1), ratio: Decimal::from(2), ..split.clone() }).unwrap();
+    assert_eq!(Decimal::from(115), stock::stock_db::get(&db_conn, 2, "AAPL").unwrap().unwrap().price);
+}
+
+#[test]
+fn test_symbol_change_renames_stock() {
+    let db_conn = get_test_connection();
+    let date = NaiveDate::from_ymd_opt(2020, 6, 1).unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "FB".into(), currency: "USD".into(), ..Stock::default() }).unwrap();
+    deposit(&db_conn, DEFAULT_PORTFOLIO_ID, &CashMovement { currency: "USD".into(), amount: Decimal::from(1000), date, ..CashMovement::default() }).unwrap();
+    buy_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Transaction { symbol: "FB".into(), quantity: Decimal::from(2), price: Decimal::from(200), date, ..Transaction::default() }).unwrap();
+    update_price(&db_conn, "FB", Decimal::from(210)).unwrap();
+
+    add_corporate_action(&db_conn, DEFAULT_PORTFOLIO_ID, &CorporateAction {
+        symbol: "FB".into(),
+        kind: CorporateActionKind::SymbolChange,
+        new_symbol: "META".into(),
+        ..CorporateAction::default()
+    }).unwrap();
+
+    assert!(stock::stock_db::get(&db_conn, DEFAULT_PORTFOLIO_ID, "FB").unwrap().is_none());
+    assert_eq!(Decimal::from(2), stock::stock_db::get(&db_conn, DEFAULT_PORTFOLIO_ID, "META").unwrap().unwrap().quantity);
+    assert_eq!(1, get_transactions(&db_conn, DEFAULT_PORTFOLIO_ID, Some("META")).unwrap().len());
+    assert_eq!(Decimal::from(210), get_price_history(&db_conn, "META", false).unwrap()[0].close);
+    assert_eq!(1, get_corporate_actions(&db_conn, DEFAULT_PORTFOLIO_ID, Some("META")).unwrap().len());
+}
+
+#[test]
+fn test_symbol_change_renames_stops() {
+    let db_conn = get_test_connection();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "FB".into(), currency: "USD".into(), ..Stock::default() }).unwrap();
+    set_stop(&db_conn, DEFAULT_PORTFOLIO_ID, &HoldingStop { symbol: "FB".into(), stop_price: Some(Decimal::from(150)), ..HoldingStop::default() }).unwrap();
+
+    add_corporate_action(&db_conn, DEFAULT_PORTFOLIO_ID, &CorporateAction {
+        symbol: "FB".into(),
+        kind: CorporateActionKind::SymbolChange,
+        new_symbol: "META".into(),
+        ..CorporateAction::default()
+    }).unwrap();
+
+    assert_eq!(vec!["META".to_owned()], get_stops(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap().into_iter().map(|stop| stop.symbol).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_symbol_change_renames_orders() {
+    let db_conn = get_test_connection();
+    let date = NaiveDate::from_ymd_opt(2020, 6, 1).unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "FB".into(), currency: "USD".into(), ..Stock::default() }).unwrap();
+    deposit(&db_conn, DEFAULT_PORTFOLIO_ID, &CashMovement { currency: "USD".into(), amount: Decimal::from(1000), date, ..CashMovement::default() }).unwrap();
+    place_order(&db_conn, DEFAULT_PORTFOLIO_ID, &Order { symbol: "FB".into(), side: TransactionKind::Buy, quantity: Decimal::from(2), date, ..Order::default() }).unwrap();
+
+    add_corporate_action(&db_conn, DEFAULT_PORTFOLIO_ID, &CorporateAction {
+        symbol: "FB".into(),
+        kind: CorporateActionKind::SymbolChange,
+        date,
+        new_symbol: "META".into(),
+        ..CorporateAction::default()
+    }).unwrap();
+
+    // The order fills against the quotes of the new symbol
+    assert_eq!(vec!["META".to_owned()], get_order_symbols(&db_conn).unwrap());
+    assert_eq!(OrderStatus::Filled, fill_orders(&db_conn, "META", Decimal::from(200), date).unwrap()[0].status);
+}
+
+#[test]
+fn test_symbol_change_renames_plans() {
+    let db_conn = get_test_connection();
+    let date = |day| NaiveDate::from_ymd_opt(2020, 6, day).unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "FB".into(), currency: "USD".into(), ..Stock::default() }).unwrap();
+    add_plan(&db_conn, DEFAULT_PORTFOLIO_ID, &InvestmentPlan { symbol: "FB".into(), amount: Decimal::from(100), day: 1, start: date(1), deposit: true, ..InvestmentPlan::default() }).unwrap();
+    run_plans(&db_conn, DEFAULT_PORTFOLIO_ID, date(1)).unwrap();
+
+    add_corporate_action(&db_conn, DEFAULT_PORTFOLIO_ID, &CorporateAction {
+        symbol: "FB".into(),
+        kind: CorporateActionKind::SymbolChange,
+        date: date(2),
+        new_symbol: "META".into(),
+        ..CorporateAction::default()
+    }).unwrap();
+
+    // The pending contribution buys the stock under its new symbol
+    add_price_history(&db_conn, &[PriceBar { symbol: "META".into(), date: date(3), close: Decimal::from(50), ..PriceBar::default() }]).unwrap();
+    let closed = run_plans(&db_conn, DEFAULT_PORTFOLIO_ID, date(3)).unwrap();
+    assert_eq!(vec![("META".to_owned(), ContributionStatus::Executed)],
+        closed.into_iter().map(|contribution| (contribution.symbol, contribution.status)).collect::<Vec<_>>());
+    assert_eq!("META", get_plans(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap()[0].symbol);
+}
+
+#[test]
+fn test_symbol_change_renames_targets() {
+    let db_conn = get_test_connection();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "FB".into(), currency: "USD".into(), ..Stock::default() }).unwrap();
+    let target = AllocationTarget { dimension: AllocationDimension::Holding, key: "FB".into(), weight: Decimal::from(20), tolerance: Decimal::from(5) };
+    set_target(&db_conn, DEFAULT_PORTFOLIO_ID, &target).unwrap();
+    set_target(&db_conn, DEFAULT_PORTFOLIO_ID, &AllocationTarget { dimension: AllocationDimension::Tag, ..target.clone() }).unwrap();
+
+    add_corporate_action(&db_conn, DEFAULT_PORTFOLIO_ID, &CorporateAction {
+        symbol: "FB".into(),
+        kind: CorporateActionKind::SymbolChange,
+        new_symbol: "META".into(),
+        ..CorporateAction::default()
+    }).unwrap();
+
+    // Only the holding target is keyed by the symbol
+    assert_eq!(vec![(AllocationDimension::Holding, "META".to_owned()), (AllocationDimension::Tag, "FB".to_owned())],
+        get_targets(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap().into_iter().map(|target| (target.dimension, target.key)).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_symbol_change_renames_shared_references() {
+    let db_conn = get_test_connection();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "FB".into(), currency: "USD".into(), ..Stock::default() }).unwrap();
+    add_watchlist(&db_conn, "social").unwrap();
+    add_watchlist(&db_conn, "tech").unwrap();
+    add_to_watchlist(&db_conn, "social", &WatchlistItem { symbol: "FB".into(), ..WatchlistItem::default() }).unwrap();
+    add_to_watchlist(&db_conn, "tech", &WatchlistItem { symbol: "FB".into(), ..WatchlistItem::default() }).unwrap();
+    add_to_watchlist(&db_conn, "tech", &WatchlistItem { symbol: "META".into(), ..WatchlistItem::default() }).unwrap();
+    add_alert(&db_conn, &Alert::from(r#"{"symbol": "FB", "condition": {"above": "300"}}"#)).unwrap();
+    add_portfolio(&db_conn, "ira").unwrap();
+    add_benchmark(&db_conn, 2, "FB").unwrap();
+    cache_profile(&db_conn, &CompanyProfile { symbol: "FB".into(), fetched_at: Local::now().naive_local(), ..CompanyProfile::default() }).unwrap();
+
+    add_corporate_action(&db_conn, DEFAULT_PORTFOLIO_ID, &CorporateAction {
+        symbol: "FB".into(),
+        kind: CorporateActionKind::SymbolChange,
+        new_symbol: "META".into(),
+        ..CorporateAction::default()
+    }).unwrap();
+
+    // Shared by every portfolio, they follow the symbol the provider quotes
+    assert_eq!(vec!["META".to_owned()], get_watched_symbols(&db_conn).unwrap());
+    assert_eq!(1, get_watchlist(&db_conn, "tech").unwrap().len());
+    assert_eq!(vec!["META".to_owned()], get_alert_symbols(&db_conn).unwrap());
+    assert_eq!(vec!["META".to_owned()], get_benchmarks(&db_conn, 2).unwrap());
+    assert_eq!(None, get_cached_profile(&db_conn, "FB").unwrap());
+}
+
+#[test]
+fn test_delisting_pays_out_position() {
+    let db_conn = get_test_connection();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock {
+        symbol: "XYZ".into(),
+        currency: "USD".into(),
+        quantity: Decimal::from(10),
+        initial_price: Decimal::from(5),
+        ..Stock::default()
+    }).unwrap();
+
+    add_corporate_action(&db_conn, DEFAULT_PORTFOLIO_ID, &CorporateAction {
+        symbol: "XYZ".into(),
+        kind: CorporateActionKind::Delisting,
+        cash_per_share: Decimal::new(15, 1),
+        ..CorporateAction::default()
+    }).unwrap();
+
+    assert_eq!(Decimal::zero(), stock::stock_db::get(&db_conn, DEFAULT_PORTFOLIO_ID, "XYZ").unwrap().unwrap().quantity);
+    assert_eq!(Decimal::from(15), get_cash_balance(&db_conn, DEFAULT_PORTFOLIO_ID, "USD").unwrap());
+    assert!(add_corporate_action(&db_conn, DEFAULT_PORTFOLIO_ID, &CorporateAction {
+        symbol: "XYZ".into(),
+        kind: CorporateActionKind::Split,
+        ..CorporateAction::default()
+    }).is_err());
+}
+
+#[test]
+fn test_process_dividends_reinvests_with_drip() {
+    let db_conn = get_test_connection();
+    let date = |month, day| NaiveDate::from_ymd_opt(2020, month, day).unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock {
+        symbol: "KO".into(),
+        currency: "USD".into(),
+        price: Decimal::from(41),
+        initial_price: Decimal::from(40),
+        quantity: Decimal::from(100),
+        drip: true,
+        ..Stock::default()
+    }).unwrap();
+
+    add_dividend(&db_conn, DEFAULT_PORTFOLIO_ID, &Dividend {
+        symbol: "KO".into(),
+        amount: Decimal::new(41, 2),
+        ex_date: date(3, 13),
+        pay_date: date(4, 1),
+        ..Dividend::default()
+    }).unwrap();
+    process_dividends(&db_conn, DEFAULT_PORTFOLIO_ID, date(4, 1)).unwrap();
+
+    let stock = stock::stock_db::get(&db_conn, DEFAULT_PORTFOLIO_ID, "KO").unwrap().unwrap();
+    assert_eq!(Decimal::from(101), stock.quantity);
+    assert_eq!(Decimal::zero(), get_cash_balance(&db_conn, DEFAULT_PORTFOLIO_ID, "USD").unwrap());
+    assert_eq!(3, get_cash_movements(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap().len() + get_transactions(&db_conn, DEFAULT_PORTFOLIO_ID, None).unwrap().len());
+}
+
+#[cfg(test)]
+proptest::proptest! {
+    #[test]
+    fn test_prices_round_trip_through_storage(
+            price in crate::decimal::any_decimal(),
+            initial_price in crate::decimal::any_decimal()) {
+        let db_conn = get_test_connection();
+        let stock = Stock {
+            symbol: "KO".into(),
+            name: "Coca-Cola".into(),
+            price,
+            initial_price,
+            ..Stock::default()
+        };
+
+        add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &stock).unwrap();
+        proptest::prop_assert_eq!(vec![stock], get_stored_stocks(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap());
+
+        update_price(&db_conn, "KO", initial_price).unwrap();
+        proptest::prop_assert_eq!(initial_price, get_stored_stocks(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap()[0].price);
     }
 }
 
-// Returns a list of all the markets available in the API
-pub fn get_available_markets() -> Result<Vec<Market>, PersistanceError> {
-    unimplemented!();
+#[test]
+fn test_portfolios_keep_holdings_apart() {
+    let db_conn = get_test_connection();
+    add_portfolio(&db_conn, "ira").unwrap();
+    let ira = get_portfolio(&db_conn, Some("ira")).unwrap().unwrap();
+
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "KO".into(), ..Stock::default() }).unwrap();
+    add_stock(&db_conn, ira.id, &Stock { symbol: "KO".into(), quantity: Decimal::from(3), ..Stock::default() }).unwrap();
+    add_stock(&db_conn, ira.id, &Stock { symbol: "PEP".into(), ..Stock::default() }).unwrap();
+    update_price(&db_conn, "KO", Decimal::from(50)).unwrap();
+
+    assert_eq!(1, get_stored_stocks(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap().len());
+    let ira_stocks = get_stored_stocks(&db_conn, ira.id).unwrap();
+    assert_eq!(2, ira_stocks.len());
+    assert_eq!(Decimal::from(50), ira_stocks[0].price);
+    assert_eq!(Decimal::from(3), ira_stocks[0].quantity);
+
+    assert!(matches!(delete_portfolio(&db_conn, "ira"), Err(PersistanceError::EntryHasDependencies)));
+    assert!(matches!(delete_portfolio(&db_conn, "default"), Err(PersistanceError::EntryHasDependencies)));
+
+    rename_portfolio(&db_conn, "ira", "roth").unwrap();
+    assert!(get_portfolio(&db_conn, Some("ira")).unwrap().is_none());
+    delete_stock(&db_conn, ira.id, "KO").unwrap();
+    delete_stock(&db_conn, ira.id, "PEP").unwrap();
+    delete_portfolio(&db_conn, "roth").unwrap();
+    assert_eq!(vec!["default"], get_portfolios(&db_conn).unwrap().iter().map(|p| p.name.as_str()).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_get_watchlist_uses_last_price() {
+    let db_conn = get_test_connection();
+    add_watchlist(&db_conn, "chips").unwrap();
+    add_to_watchlist(&db_conn, "chips", &WatchlistItem { symbol: "AMD".into(), target_price: Some(Decimal::from(80)), note: "buy the dip".into() }).unwrap();
+    add_to_watchlist(&db_conn, "chips", &WatchlistItem { symbol: "INTC".into(), ..WatchlistItem::default() }).unwrap();
+    assert!(add_to_watchlist(&db_conn, "banks", &WatchlistItem { symbol: "JPM".into(), ..WatchlistItem::default() }).is_err());
+
+    update_price(&db_conn, "AMD", Decimal::from(100)).unwrap();
+    assert_eq!(vec!["AMD".to_owned(), "INTC".to_owned()], get_watched_symbols(&db_conn).unwrap());
+    assert!(get_stored_stocks(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap().is_empty());
+
+    let entries = get_watchlist(&db_conn, "chips").unwrap();
+    assert_eq!(Some(Decimal::from(100)), entries[0].price);
+    assert_eq!(Some(Decimal::from(-20)), entries[0].distance_to_target_pct);
+    assert_eq!(None, entries[1].price);
+
+    update_watchlist_item(&db_conn, "chips", &WatchlistItem { symbol: "AMD".into(), target_price: None, note: String::new() }).unwrap();
+    assert_eq!(None, get_watchlist(&db_conn, "chips").unwrap()[0].distance_to_target);
+    remove_from_watchlist(&db_conn, "chips", "INTC").unwrap();
+    assert_eq!(1, get_watchlist(&db_conn, "chips").unwrap().len());
+
+    delete_watchlist(&db_conn, "chips").unwrap();
+    assert!(get_watchlists(&db_conn).unwrap().is_empty());
+    assert!(get_watched_symbols(&db_conn).unwrap().is_empty());
+}
+
+#[test]
+fn test_tags_are_stored_with_the_stock() {
+    let db_conn = get_test_connection();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "KO".into(), tags: vec!["dividend".into()], ..Stock::default() }).unwrap();
+
+    tag_stock(&db_conn, DEFAULT_PORTFOLIO_ID, "KO", "core").unwrap();
+    tag_stock(&db_conn, DEFAULT_PORTFOLIO_ID, "KO", "core").unwrap();
+    assert_eq!(vec!["dividend".to_owned(), "core".to_owned()], get_stored_stocks(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap()[0].tags);
+
+    untag_stock(&db_conn, DEFAULT_PORTFOLIO_ID, "KO", "dividend").unwrap();
+    assert_eq!(vec!["core".to_owned()], get_stored_stocks(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap()[0].tags);
+    assert!(tag_stock(&db_conn, DEFAULT_PORTFOLIO_ID, "PEP", "core").is_err());
 }
 
+#[test]
+fn test_cache_profile() {
+    let db_conn = get_test_connection();
+    let profile = CompanyProfile {
+        symbol: "KO".into(),
+        sector: "Consumer Defensive".into(),
+        pe_ratio: Some(Decimal::new(235, 1)),
+        fetched_at: Local::now().naive_local(),
+        ..CompanyProfile::default()
+    };
+
+    assert_eq!(None, get_cached_profile(&db_conn, "KO").unwrap());
+    cache_profile(&db_conn, &profile).unwrap();
+    cache_profile(&db_conn, &profile).unwrap();
+    assert_eq!(Some(profile), get_cached_profile(&db_conn, "KO").unwrap());
+}
 
 /*
 #[test]
@@ -103,6 +2319,428 @@ fn test_delete() {
 
     assert!(persistance.delete(&mock_asset.symbol).is_ok());
 }*/
+#[test]
+fn test_compare_with_benchmark() {
+    let db_conn = get_test_connection();
+    let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+    let bar = |symbol: &str, day, close| PriceBar { symbol: symbol.into(), date: date(day), close: Decimal::from(close), ..PriceBar::default() };
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "AAPL".into(), currency: "USD".into(), ..Stock::default() }).unwrap();
+    deposit(&db_conn, DEFAULT_PORTFOLIO_ID, &CashMovement { currency: "USD".into(), amount: Decimal::from(5000), date: date(1), ..CashMovement::default() }).unwrap();
+    buy_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Transaction { symbol: "AAPL".into(), quantity: Decimal::from(10), price: Decimal::from(100), date: date(2), ..Transaction::default() }).unwrap();
+    add_price_history(&db_conn, &[bar("AAPL", 2, 100), bar("AAPL", 3, 110), bar("AAPL", 6, 121)]).unwrap();
+    add_price_history(&db_conn, &[bar("SPY", 1, 300), bar("SPY", 2, 300), bar("SPY", 3, 330), bar("SPY", 6, 330)]).unwrap();
+
+    add_benchmark(&db_conn, DEFAULT_PORTFOLIO_ID, "spy").unwrap();
+    assert_eq!(vec!["SPY".to_owned()], get_benchmarks(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap());
+
+    let comparison = get_comparison(&db_conn, DEFAULT_PORTFOLIO_ID, ReturnPeriod::Custom(date(1), date(6))).unwrap();
+    assert_eq!(Decimal::from(1210), comparison.returns.end_value);
+    assert_eq!(Some(Decimal::from(21)), comparison.returns.time_weighted);
+    assert_eq!("SPY", comparison.benchmarks[0].symbol);
+    assert_eq!(Some(Decimal::from(10)), comparison.benchmarks[0].returns.time_weighted);
+    assert_eq!(Decimal::from(1100), comparison.benchmarks[0].returns.end_value.round_dp(2));
+
+    remove_benchmark(&db_conn, DEFAULT_PORTFOLIO_ID, "SPY").unwrap();
+    assert!(get_benchmarks(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap().is_empty());
+}
+
+#[test]
+fn test_rebalance_to_targets() {
+    let db_conn = get_test_connection();
+    let date = NaiveDate::from_ymd_opt(2020, 1, 2).unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "AAPL".into(), currency: "USD".into(), price: Decimal::from(100), ..Stock::default() }).unwrap();
+    deposit(&db_conn, DEFAULT_PORTFOLIO_ID, &CashMovement { currency: "USD".into(), amount: Decimal::from(1000), date, ..CashMovement::default() }).unwrap();
+    buy_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Transaction { symbol: "AAPL".into(), quantity: Decimal::from(5), price: Decimal::from(100), date, ..Transaction::default() }).unwrap();
+
+    let target = AllocationTarget { dimension: AllocationDimension::Holding, key: "AAPL".into(), weight: Decimal::from(80), tolerance: Decimal::from(5) };
+    set_target(&db_conn, DEFAULT_PORTFOLIO_ID, &target).unwrap();
+    assert_eq!(vec![target.clone()], get_targets(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap());
+    assert!(set_target(&db_conn, DEFAULT_PORTFOLIO_ID, &AllocationTarget { weight: Decimal::from(101), ..target.clone() }).is_err());
+
+    // Half of the 1000 is in AAPL, 300 more gets it to 80%
+    let plan = get_rebalance_plan(&db_conn, DEFAULT_PORTFOLIO_ID, &RebalanceOptions::default()).unwrap();
+    assert_eq!(Decimal::from(50), plan.drifts[0].weight);
+    assert_eq!(TransactionKind::Buy, plan.trades[0].kind);
+    assert_eq!(Decimal::from(3), plan.trades[0].quantity);
+
+    let options = RebalanceOptions::from(r#"{"lot_size": "0"}"#);
+    assert!(matches!(get_rebalance_plan(&db_conn, DEFAULT_PORTFOLIO_ID, &options), Err(PersistanceError::InvalidAmount)));
+    let options = RebalanceOptions::from(r#"{"min_trade": "-1"}"#);
+    assert!(matches!(get_rebalance_plan(&db_conn, DEFAULT_PORTFOLIO_ID, &options), Err(PersistanceError::InvalidAmount)));
+
+    remove_target(&db_conn, DEFAULT_PORTFOLIO_ID, AllocationDimension::Holding, "AAPL").unwrap();
+    assert!(get_rebalance_plan(&db_conn, DEFAULT_PORTFOLIO_ID, &RebalanceOptions::default()).unwrap().trades.is_empty());
+}
+
+#[test]
+fn test_evaluate_alerts() {
+    let db_conn = get_test_connection();
+    let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+    let bar = |day, close| PriceBar { symbol: "AAPL".into(), date: date(day), close: Decimal::from(close), ..PriceBar::default() };
+    add_price_history(&db_conn, &[bar(1, 100), bar(2, 110)]).unwrap();
+
+    add_alert(&db_conn, &Alert::from(r#"{"symbol": "aapl", "condition": {"above": "105"}}"#)).unwrap();
+    add_alert(&db_conn, &Alert::from(r#"{"symbol": "AAPL", "condition": {"daily_move": "20"}}"#)).unwrap();
+    assert!(add_alert(&db_conn, &Alert::from(r#"{"symbol": "AAPL", "condition": {"below": "0"}}"#)).is_err());
+    assert!(add_alert(&db_conn, &Alert::from(r#"{"symbol": "AAPL", "condition": {"cross_above": {"rsi": 0}}}"#)).is_err());
+    assert_eq!(vec!["AAPL".to_owned()], get_alert_symbols(&db_conn).unwrap());
+
+    let events = evaluate_alerts(&db_conn, date(2)).unwrap();
+    assert_eq!(1, events.len());
+    assert_eq!((1, Decimal::from(110)), (events[0].alert_id, events[0].price));
+    assert_eq!(Some(date(2)), get_alerts(&db_conn).unwrap()[0].last_triggered);
+
+    // Once a day, and only with a price of the day
+    assert!(evaluate_alerts(&db_conn, date(2)).unwrap().is_empty());
+    assert!(evaluate_alerts(&db_conn, date(3)).unwrap().is_empty());
+
+    delete_alert(&db_conn, 1).unwrap();
+    assert!(delete_alert(&db_conn, 1).is_err());
+    assert_eq!(1, get_alerts(&db_conn).unwrap().len());
+}
+
+#[tokio::test]
+async fn test_deliver_webhook() {
+    use std::sync::{Arc, Mutex};
+    use hyper::{service::{make_service_fn, service_fn}, Body, Request, Response, Server};
+
+    // Stand-in receiver that fails the first post and keeps the signatures it gets
+    let received = Arc::new(Mutex::new(vec![]));
+    let server_received = received.clone();
+    let make_service = make_service_fn(move |_| {
+        let received = server_received.clone();
+        async move {
+            Ok::<_, hyper::Error>(service_fn(move |request: Request<Body>| {
+                let received = received.clone();
+                async move {
+                    let signature = request.headers()[webhook::webhook_api::SIGNATURE_HEADER].to_str().unwrap().to_owned();
+                    let body = hyper::body::to_bytes(request.into_body()).await?;
+                    let mut received = received.lock().unwrap();
+                    received.push((signature, body.to_vec()));
+
+                    let status = if received.len() == 1 { 500 } else { 200 };
+                    Ok::<_, hyper::Error>(Response::builder().status(status).body(Body::empty()).unwrap())
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let address = server.local_addr();
+    tokio::spawn(server);
+
+    // Connects with a standard socket, as the one of the HTTP connector goes through
+    // net2, which can't build the addresses of newer toolchains
+    #[derive(Clone)]
+    struct LoopbackConnector;
+
+    impl hyper::service::Service<hyper::Uri> for LoopbackConnector {
+        type Response = tokio::net::TcpStream;
+        type Error = std::io::Error;
+        type Future = std::pin::Pin<Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+        fn poll_ready(&mut self, _: &mut std::task::Context) -> std::task::Poll<Result<(), Self::Error>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, uri: hyper::Uri) -> Self::Future {
+            Box::pin(async move {
+                let stream = std::net::TcpStream::connect(uri.authority().unwrap().as_str())?;
+                tokio::net::TcpStream::from_std(stream)
+            })
+        }
+    }
+
+    let db_conn = get_test_connection();
+    add_alert(&db_conn, &Alert::from(r#"{"symbol": "AAPL", "condition": {"above": "100"}}"#)).unwrap();
+    let webhook = Webhook { alert_id: 1, url: format!("http://{}/hook", address), secret: "s3cret".into(), ..Webhook::default() };
+    assert!(add_webhook(&db_conn, &Webhook { secret: String::new(), ..webhook.clone() }).is_err());
+    assert!(add_webhook(&db_conn, &Webhook { url: "ftp://example.com".into(), ..webhook.clone() }).is_err());
+    assert!(add_webhook(&db_conn, &Webhook { alert_id: 2, ..webhook.clone() }).is_err());
+    add_webhook(&db_conn, &webhook).unwrap();
+    assert_eq!("", get_webhooks(&db_conn).unwrap()[0].secret);
+    let webhook = get_alert_webhooks(&db_conn, 1).unwrap().remove(0);
+
+    let client = hyper::Client::builder().build::<_, hyper::Body>(LoopbackConnector);
+    let payload = br#"{"event":"alert"}"#;
+    let deliveries = deliver_webhook(&client, &webhook, payload, Duration::from_millis(10), webhook::TIMEOUT).await;
+
+    // Retried once the receiver failed, with the body signed with the secret
+    assert_eq!(vec![Some(500), Some(200)], deliveries.iter().map(|delivery| delivery.status).collect::<Vec<_>>());
+    assert_eq!(vec![false, true], deliveries.iter().map(|delivery| delivery.delivered).collect::<Vec<_>>());
+    let signature = webhook::webhook_api::sign("s3cret", payload).unwrap();
+    assert_eq!((signature, payload.to_vec()), received.lock().unwrap()[1]);
+
+    add_deliveries(&db_conn, &deliveries).unwrap();
+    assert_eq!(deliveries.iter().map(|delivery| Delivery { id: 0, ..delivery.clone() }).collect::<Vec<_>>(),
+        get_deliveries(&db_conn, Some(1)).unwrap().into_iter().map(|delivery| Delivery { id: 0, ..delivery }).collect::<Vec<_>>());
+    assert!(get_deliveries(&db_conn, Some(2)).unwrap().is_empty());
+
+    // A receiver that never answers times out every attempt
+    let stalled = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let stalled_webhook = Webhook { url: format!("http://{}/hook", stalled.local_addr().unwrap()), ..webhook.clone() };
+    let deliveries = deliver_webhook(&client, &stalled_webhook, payload, Duration::from_millis(10), Duration::from_millis(50)).await;
+    assert_eq!(webhook::MAX_ATTEMPTS as usize, deliveries.len());
+    assert!(deliveries.iter().all(|delivery| delivery.status.is_none() && !delivery.error.is_empty() && delivery.is_retriable()));
+
+    // The webhooks go along with their alert
+    delete_alert(&db_conn, 1).unwrap();
+    assert!(get_webhooks(&db_conn).unwrap().is_empty());
+
+    // Known value of HMAC-SHA256
+    assert_eq!("sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8",
+        webhook::webhook_api::sign("key", b"The quick brown fox jumps over the lazy dog").unwrap());
+}
+
+#[test]
+fn test_evaluate_stops() {
+    let db_conn = get_test_connection();
+    let date = NaiveDate::from_ymd_opt(2020, 1, 2).unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "AAPL".into(), currency: "USD".into(), price: Decimal::from(100), ..Stock::default() }).unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "MSFT".into(), currency: "USD".into(), price: Decimal::from(200), ..Stock::default() }).unwrap();
+    deposit(&db_conn, DEFAULT_PORTFOLIO_ID, &CashMovement { currency: "USD".into(), amount: Decimal::from(1000), date, ..CashMovement::default() }).unwrap();
+    buy_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Transaction { symbol: "AAPL".into(), quantity: Decimal::from(5), price: Decimal::from(100), date, ..Transaction::default() }).unwrap();
+
+    let trailing = HoldingStop { symbol: "AAPL".into(), trailing_percent: Some(Decimal::from(10)), sell: true, ..HoldingStop::default() };
+    set_stop(&db_conn, DEFAULT_PORTFOLIO_ID, &trailing).unwrap();
+    set_stop(&db_conn, DEFAULT_PORTFOLIO_ID, &HoldingStop { symbol: "MSFT".into(), take_profit: Some(Decimal::from(250)), ..HoldingStop::default() }).unwrap();
+    assert!(set_stop(&db_conn, DEFAULT_PORTFOLIO_ID, &HoldingStop { symbol: "KO".into(), ..trailing.clone() }).is_err());
+    assert_eq!(Some(Decimal::from(90)), get_stops(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap()[0].stop_price);
+
+    // The trailing stop follows AAPL up to 108
+    update_price(&db_conn, "AAPL", Decimal::from(120)).unwrap();
+    assert!(evaluate_stops(&db_conn, date).unwrap().is_empty());
+    assert_eq!(Some(Decimal::from(108)), get_stops(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap()[0].stop_price);
+
+    // Then the drop sells the whole holding
+    update_price(&db_conn, "AAPL", Decimal::from(105)).unwrap();
+    update_price(&db_conn, "MSFT", Decimal::from(250)).unwrap();
+    let events = evaluate_stops(&db_conn, date).unwrap();
+    assert_eq!(vec![(stop::StopKind::TrailingStop, Some(Decimal::from(5))), (stop::StopKind::TakeProfit, None)],
+        events.iter().map(|event| (event.kind, event.sold)).collect::<Vec<_>>());
+    assert_eq!(Decimal::zero(), get_stored_stocks(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap()[0].quantity);
+    assert_eq!(Decimal::from(1025), get_cash_balance(&db_conn, DEFAULT_PORTFOLIO_ID, "USD").unwrap());
+    assert!(get_stops(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap().is_empty());
+}
+
+#[test]
+fn test_evaluate_stops_of_every_portfolio() {
+    let db_conn = get_test_connection();
+    let date = NaiveDate::from_ymd_opt(2020, 1, 2).unwrap();
+    add_portfolio(&db_conn, "ira").unwrap();
+    for portfolio_id in &[DEFAULT_PORTFOLIO_ID, 2] {
+        add_stock(&db_conn, *portfolio_id, &Stock { symbol: "AAPL".into(), currency: "USD".into(), price: Decimal::from(100), ..Stock::default() }).unwrap();
+    }
+    set_stop(&db_conn, 2, &HoldingStop { symbol: "AAPL".into(), trailing_percent: Some(Decimal::from(10)), take_profit: Some(Decimal::from(150)), ..HoldingStop::default() }).unwrap();
+
+    // The stops of the portfolio that didn't update the prices follow them too
+    update_price(&db_conn, "AAPL", Decimal::from(120)).unwrap();
+    assert!(evaluate_stops(&db_conn, date).unwrap().is_empty());
+    assert_eq!(Some(Decimal::from(108)), get_stops(&db_conn, 2).unwrap()[0].stop_price);
+
+    update_price(&db_conn, "AAPL", Decimal::from(150)).unwrap();
+    let events = evaluate_stops(&db_conn, date).unwrap();
+    assert_eq!(vec![(2, stop::StopKind::TakeProfit)], events.iter().map(|event| (event.portfolio_id, event.kind)).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_fill_orders() {
+    let db_conn = get_test_connection();
+    let date = NaiveDate::from_ymd_opt(2020, 1, 2).unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "AAPL".into(), currency: "USD".into(), ..Stock::default() }).unwrap();
+    deposit(&db_conn, DEFAULT_PORTFOLIO_ID, &CashMovement { currency: "USD".into(), amount: Decimal::from(1000), date, ..CashMovement::default() }).unwrap();
+    set_slippage(&db_conn, Decimal::from(1)).unwrap();
+    set_commission(&db_conn, Decimal::from(2)).unwrap();
+
+    let buy = Order { symbol: "AAPL".into(), side: TransactionKind::Buy, quantity: Decimal::from(5), date, ..Order::default() };
+    place_order(&db_conn, DEFAULT_PORTFOLIO_ID, &buy).unwrap();
+    place_order(&db_conn, DEFAULT_PORTFOLIO_ID, &Order { quantity: Decimal::from(50), time_in_force: TimeInForce::Ioc, ..buy.clone() }).unwrap();
+    place_order(&db_conn, DEFAULT_PORTFOLIO_ID, &Order {
+        side: TransactionKind::Sell,
+        order_type: order::OrderType::Limit,
+        limit_price: Some(Decimal::from(110)),
+        time_in_force: TimeInForce::Gtc,
+        ..buy.clone()
+    }).unwrap();
+    place_order(&db_conn, DEFAULT_PORTFOLIO_ID, &Order { quantity: Decimal::from(1), ..buy.clone() }).unwrap();
+    cancel_order(&db_conn, DEFAULT_PORTFOLIO_ID, 4).unwrap();
+    assert!(place_order(&db_conn, DEFAULT_PORTFOLIO_ID, &Order { symbol: "MSFT".into(), ..buy.clone() }).is_err());
+
+    // The market order fills with slippage, the big one lacks cash and the limit waits
+    let closed = fill_orders(&db_conn, "AAPL", Decimal::from(100), date).unwrap();
+    assert_eq!(vec![(1, OrderStatus::Filled, Some(Decimal::from(101))), (2, OrderStatus::Rejected, None)],
+        closed.iter().map(|order| (order.id, order.status, order.fill_price)).collect::<Vec<_>>());
+    assert_eq!(Decimal::from(493), get_cash_balance(&db_conn, DEFAULT_PORTFOLIO_ID, "USD").unwrap());
+
+    // Limits fill at their price or better the days after
+    let next_day = date.succ_opt().unwrap();
+    assert!(fill_orders(&db_conn, "AAPL", Decimal::from(105), next_day).unwrap().is_empty());
+    let closed = fill_orders(&db_conn, "AAPL", Decimal::from(112), next_day).unwrap();
+    assert_eq!(Some(Decimal::new(11088, 2)), closed[0].fill_price);
+    assert_eq!(Decimal::zero(), get_stored_stocks(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap()[0].quantity);
+    assert_eq!(2, get_transactions(&db_conn, DEFAULT_PORTFOLIO_ID, Some("AAPL")).unwrap().len());
+    assert!(get_orders(&db_conn, DEFAULT_PORTFOLIO_ID, false).unwrap().is_empty());
+    assert_eq!(OrderStatus::Canceled, get_orders(&db_conn, DEFAULT_PORTFOLIO_ID, true).unwrap()[3].status);
+}
+
+#[test]
+fn test_orders_of_deleted_stocks_are_not_filled() {
+    let db_conn = get_test_connection();
+    let date = NaiveDate::from_ymd_opt(2020, 1, 2).unwrap();
+    add_portfolio(&db_conn, "paper").unwrap();
+    for portfolio_id in &[DEFAULT_PORTFOLIO_ID, 2] {
+        add_stock(&db_conn, *portfolio_id, &Stock { symbol: "AAPL".into(), currency: "USD".into(), ..Stock::default() }).unwrap();
+        deposit(&db_conn, *portfolio_id, &CashMovement { currency: "USD".into(), amount: Decimal::from(1000), date, ..CashMovement::default() }).unwrap();
+    }
+
+    let buy = Order { symbol: "AAPL".into(), side: TransactionKind::Buy, quantity: Decimal::from(5), date, ..Order::default() };
+    place_order(&db_conn, DEFAULT_PORTFOLIO_ID, &buy).unwrap();
+    place_order(&db_conn, 2, &buy).unwrap();
+    delete_stock(&db_conn, DEFAULT_PORTFOLIO_ID, "AAPL").unwrap();
+    assert_eq!(OrderStatus::Canceled, get_orders(&db_conn, DEFAULT_PORTFOLIO_ID, true).unwrap()[0].status);
+
+    // An order left without its stock is rejected without holding back the others
+    order::order_db::add(&db_conn, DEFAULT_PORTFOLIO_ID, &buy).unwrap();
+    let closed = fill_orders(&db_conn, "AAPL", Decimal::from(100), date).unwrap();
+    assert_eq!(vec![(2, OrderStatus::Filled), (3, OrderStatus::Rejected)],
+        closed.iter().map(|order| (order.id, order.status)).collect::<Vec<_>>());
+    assert_eq!(Decimal::from(5), get_stored_stocks(&db_conn, 2).unwrap()[0].quantity);
+}
+
+#[test]
+fn test_run_backtest_on_stored_prices() {
+    let db_conn = get_test_connection();
+    let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+    let bars: Vec<PriceBar> = [100, 100, 50, 60]
+        .iter()
+        .enumerate()
+        .map(|(day, close)| PriceBar { symbol: "AAPL".into(), date: date(day as u32 + 1), close: Decimal::from(*close as i64), ..PriceBar::default() })
+        .collect();
+    add_price_history(&db_conn, &bars).unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "AAPL".into(), currency: "USD".into(), ..Stock::default() }).unwrap();
+    // Halves the prices before the split, so the backtest buys twice the shares
+    add_corporate_action(&db_conn, DEFAULT_PORTFOLIO_ID, &CorporateAction {
+        symbol: "AAPL".into(),
+        kind: CorporateActionKind::Split,
+        ratio: Decimal::from(2),
+        date: date(3),
+        ..CorporateAction::default()
+    }).unwrap();
+
+    let config = BacktestConfig::from(r#"{"strategy": {"rebalance": {"weights": {"AAPL": "100"}}}, "start": "2020-01-01", "end": "2020-01-31", "initial_cash": "1000"}"#);
+    let result = run_backtest(&db_conn, &config).unwrap();
+
+    assert_eq!(1, result.trades.len());
+    assert_eq!(4, result.equity.len());
+    assert_eq!(Some(Decimal::from(20)), result.stats.returns.time_weighted);
+}
+
+#[test]
+fn test_run_plans() {
+    let db_conn = get_test_connection();
+    let date = |month, day| NaiveDate::from_ymd_opt(2020, month, day).unwrap();
+    add_market(&db_conn, &Market { id: 0, symbol: "XETRA".into(), currency: "EUR".into() }).unwrap();
+    let market = get_stored_markets(&db_conn).unwrap().pop().unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "VWCE".into(), market: market.clone(), ..Stock::default() }).unwrap();
+    add_holiday(&db_conn, &MarketHoliday { market_id: market.id, date: date(6, 1), name: "Whit Monday".into() }).unwrap();
+    add_plan(&db_conn, DEFAULT_PORTFOLIO_ID, &InvestmentPlan {
+        symbol: "VWCE".into(),
+        amount: Decimal::from(200),
+        day: 1,
+        start: date(5, 1),
+        deposit: true,
+        ..InvestmentPlan::default()
+    }).unwrap();
+    assert!(add_plan(&db_conn, DEFAULT_PORTFOLIO_ID, &InvestmentPlan { symbol: "AAPL".into(), amount: Decimal::from(1), ..InvestmentPlan::default() }).is_err());
+
+    // The 1st of June is a holiday, so the contribution waits for the price of the 2nd
+    let bars: Vec<PriceBar> = [(date(5, 1), 80), (date(5, 29), 90), (date(6, 2), 100)]
+        .iter()
+        .map(|(date, close)| PriceBar { symbol: "VWCE".into(), date: *date, close: Decimal::from(*close), ..PriceBar::default() })
+        .collect();
+    add_price_history(&db_conn, &bars[..2]).unwrap();
+    let closed = run_plans(&db_conn, DEFAULT_PORTFOLIO_ID, date(6, 1)).unwrap();
+    assert_eq!(vec![(date(5, 1), Some(Decimal::new(25, 1)))], closed.iter().map(|contribution| (contribution.date, contribution.quantity)).collect::<Vec<_>>());
+
+    add_price_history(&db_conn, &bars[2..]).unwrap();
+    let closed = run_plans(&db_conn, DEFAULT_PORTFOLIO_ID, date(6, 2)).unwrap();
+    assert_eq!(vec![(date(6, 2), Some(Decimal::from(2)))], closed.iter().map(|contribution| (contribution.date, contribution.quantity)).collect::<Vec<_>>());
+
+    let report = get_plan_report(&db_conn, DEFAULT_PORTFOLIO_ID, date(6, 2)).unwrap();
+    assert_eq!(vec![date(7, 1), date(8, 3), date(9, 1)], report.upcoming.iter().map(|contribution| contribution.date).collect::<Vec<_>>());
+    assert_eq!(Decimal::new(45, 1), report.totals[0].quantity);
+    assert_eq!(Decimal::from(400), report.totals[0].invested);
+    assert_eq!(Decimal::new(45, 1), get_stored_stocks(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap()[0].quantity);
+    assert_eq!(Decimal::zero(), get_cash_balance(&db_conn, DEFAULT_PORTFOLIO_ID, "EUR").unwrap());
+}
+
+#[test]
+fn test_failed_contributions_deposit_nothing() {
+    let db_conn = get_test_connection();
+    let date = |month, day| NaiveDate::from_ymd_opt(2020, month, day).unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "VWCE".into(), currency: "EUR".into(), ..Stock::default() }).unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "IWDA".into(), currency: "EUR".into(), ..Stock::default() }).unwrap();
+    let plan = InvestmentPlan { symbol: "VWCE".into(), amount: Decimal::new(1, 3), day: 1, start: date(5, 1), deposit: true, ..InvestmentPlan::default() };
+    add_plan(&db_conn, DEFAULT_PORTFOLIO_ID, &plan).unwrap();
+    add_plan(&db_conn, DEFAULT_PORTFOLIO_ID, &InvestmentPlan { symbol: "IWDA".into(), amount: Decimal::from(100), ..plan.clone() }).unwrap();
+    add_price_history(&db_conn, &["VWCE", "IWDA"].iter()
+        .map(|symbol| PriceBar { symbol: symbol.to_string(), date: date(5, 4), close: Decimal::from(100), ..PriceBar::default() })
+        .collect::<Vec<_>>()).unwrap();
+
+    // The amount buys nothing, and the stock of the other plan is gone with it
+    plan::plan_db::add_contribution(&db_conn, DEFAULT_PORTFOLIO_ID, &Contribution {
+        plan_id: 2,
+        symbol: "IWDA".into(),
+        amount: Decimal::from(100),
+        currency: "EUR".into(),
+        scheduled: date(5, 1),
+        date: date(5, 1),
+        ..Contribution::default()
+    }).unwrap();
+    stock::stock_db::delete(&db_conn, DEFAULT_PORTFOLIO_ID, "IWDA").unwrap();
+    let closed = run_plans(&db_conn, DEFAULT_PORTFOLIO_ID, date(5, 4)).unwrap();
+    assert_eq!(vec![ContributionStatus::Failed, ContributionStatus::Failed],
+        closed.iter().map(|contribution| contribution.status).collect::<Vec<_>>());
+    assert!(get_cash_movements(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap().is_empty());
+
+    delete_stock(&db_conn, DEFAULT_PORTFOLIO_ID, "VWCE").unwrap();
+    assert_eq!(vec!["IWDA".to_owned()], get_plans(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap().into_iter().map(|plan| plan.symbol).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_get_tax_report() {
+    let db_conn = get_test_connection();
+    let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    add_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &Stock { symbol: "KO".into(), currency: "USD".into(), ..Stock::default() }).unwrap();
+    deposit(&db_conn, DEFAULT_PORTFOLIO_ID, &CashMovement { currency: "USD".into(), amount: Decimal::from(2000), ..CashMovement::default() }).unwrap();
+
+    let trade = |kind, quantity, price, date| Transaction {
+        symbol: "KO".into(),
+        quantity: Decimal::from(quantity),
+        price: Decimal::from(price),
+        commission: Decimal::from(1),
+        date,
+        kind,
+        ..Transaction::default()
+    };
+    buy_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &trade(TransactionKind::Buy, 20, 50, date(2020, 12, 1))).unwrap();
+    sell_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &trade(TransactionKind::Sell, 10, 40, date(2020, 12, 20))).unwrap();
+    buy_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &trade(TransactionKind::Buy, 10, 42, date(2021, 1, 5))).unwrap();
+    sell_stock(&db_conn, DEFAULT_PORTFOLIO_ID, &trade(TransactionKind::Sell, 20, 60, date(2021, 6, 1))).unwrap();
+
+    // The December loss is washed by the January purchase, which is sold after the rest of the first lot
+    let report = get_tax_report(&db_conn, DEFAULT_PORTFOLIO_ID, 2020).unwrap();
+    assert_eq!(Decimal::new(1015, 1), report.sales[0].wash_sale);
+    assert_eq!(Decimal::zero(), report.sales[0].adjusted_gain);
+
+    let report = get_tax_report(&db_conn, DEFAULT_PORTFOLIO_ID, 2021).unwrap();
+    assert_eq!(2, report.sales.len());
+    assert_eq!(date(2020, 12, 1), report.sales[0].acquired);
+    assert_eq!(Decimal::new(5995, 1), report.sales[0].proceeds);
+    assert_eq!(Decimal::new(5005, 1), report.sales[0].cost);
+    assert_eq!(Decimal::new(5225, 1), report.sales[1].cost);
+    assert_eq!(Decimal::from(176), report.totals[0].adjusted_gain);
+}
+
 /*
 
 fn get_asset_mock() -> Stock {
@@ -0,0 +1,142 @@
+pub mod alert_db;
+
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use crate::indicator::Indicator;
+use super::price_history::PriceBar;
+
+// What makes an alert fire. The crossings compare the last two closes with the
+// line of the indicator on those days, e.g. `{"cross_above": {"sma": 50}}`, and
+// the indicator levels its last value, e.g. `{"indicator_above": [{"rsi": 14}, "70"]}`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+    Above(Decimal),
+    Below(Decimal),
+    // Change from the close of the day before, either way, in percent
+    DailyMove(Decimal),
+    CrossAbove(Indicator),
+    CrossBelow(Indicator),
+    IndicatorAbove(Indicator, Decimal),
+    IndicatorBelow(Indicator, Decimal),
+}
+
+// Rule on the price of a symbol. It fires at most once a day.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Alert {
+    #[serde(default)]
+    pub id: u32,
+    pub symbol: String,
+    pub condition: AlertCondition,
+    #[serde(default)]
+    pub last_triggered: Option<NaiveDate>,
+}
+
+// Alert that fired along with the close that made it fire
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct AlertEvent {
+    pub alert_id: u32,
+    pub symbol: String,
+    pub condition: AlertCondition,
+    pub date: NaiveDate,
+    pub price: Decimal,
+}
+
+impl From<&str> for Alert {
+    fn from(json: &str) -> Self {
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+}
+
+impl AlertCondition {
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Self::Above(price) | Self::Below(price) => *price > Decimal::zero(),
+            Self::DailyMove(percent) => *percent > Decimal::zero(),
+            Self::CrossAbove(indicator) | Self::CrossBelow(indicator) => indicator.is_valid(),
+            Self::IndicatorAbove(indicator, _) | Self::IndicatorBelow(indicator, _) => indicator.is_valid(),
+        }
+    }
+
+    // Whether the condition holds on the last of the bars, oldest first
+    pub fn is_met(&self, bars: &[PriceBar]) -> bool {
+        let (previous, last) = match bars {
+            [.., previous, last] => (Some(previous), last),
+            [last] => (None, last),
+            [] => return false,
+        };
+
+        match self {
+            Self::Above(price) => last.close >= *price,
+            Self::Below(price) => last.close <= *price,
+            Self::DailyMove(percent) => previous
+                .and_then(|previous| ((last.close - previous.close) * Decimal::from(100)).checked_div(previous.close))
+                .map(|change| change.max(-change) >= *percent)
+                .unwrap_or(false),
+            Self::CrossAbove(indicator) => match (previous, last_two_values(indicator, bars)) {
+                (Some(previous), Some((previous_value, last_value))) => previous.close <= previous_value && last.close > last_value,
+                _ => false,
+            },
+            Self::CrossBelow(indicator) => match (previous, last_two_values(indicator, bars)) {
+                (Some(previous), Some((previous_value, last_value))) => previous.close >= previous_value && last.close < last_value,
+                _ => false,
+            },
+            Self::IndicatorAbove(indicator, level) => last_value(indicator, bars)
+                .map(|value| value > *level)
+                .unwrap_or(false),
+            Self::IndicatorBelow(indicator, level) => last_value(indicator, bars)
+                .map(|value| value < *level)
+                .unwrap_or(false),
+        }
+    }
+}
+
+// Value of the indicator on the day of the last bar
+fn last_value(indicator: &Indicator, bars: &[PriceBar]) -> Option<Decimal> {
+    let last = bars.last()?;
+    indicator.calculate(bars).last()
+        .filter(|value| value.date == last.date)
+        .map(|value| value.value)
+}
+
+// Values of the indicator on the days of the last two bars
+fn last_two_values(indicator: &Indicator, bars: &[PriceBar]) -> Option<(Decimal, Decimal)> {
+    let values = indicator.calculate(bars);
+    match values.as_slice() {
+        [.., previous, last] if last.date == bars.last()?.date && previous.date == bars[bars.len() - 2].date => {
+            Some((previous.value, last.value))
+        },
+        _ => None,
+    }
+}
+
+#[test]
+fn test_alert_condition_is_met() {
+    let bars: Vec<PriceBar> = [10, 10, 10, 9, 12]
+        .iter()
+        .enumerate()
+        .map(|(day, close)| PriceBar {
+            symbol: "AAPL".into(),
+            date: NaiveDate::from_ymd_opt(2020, 1, day as u32 + 1).unwrap(),
+            close: Decimal::from(*close),
+            ..PriceBar::default()
+        })
+        .collect();
+
+    assert!(AlertCondition::Above(Decimal::from(12)).is_met(&bars));
+    assert!(!AlertCondition::Below(Decimal::from(11)).is_met(&bars));
+    // From 9 to 12 is a third up
+    assert!(AlertCondition::DailyMove(Decimal::from(30)).is_met(&bars));
+    assert!(!AlertCondition::DailyMove(Decimal::from(40)).is_met(&bars[..4]));
+    // Below the 3 day average of 9.67 the day before and above the one of 10.33
+    assert!(AlertCondition::CrossAbove(Indicator::Sma(3)).is_met(&bars));
+    assert!(!AlertCondition::CrossBelow(Indicator::Sma(3)).is_met(&bars));
+    assert!(AlertCondition::CrossBelow(Indicator::Sma(3)).is_met(&bars[..4]));
+    assert!(AlertCondition::IndicatorAbove(Indicator::Sma(3), Decimal::from(10)).is_met(&bars));
+    assert!(!AlertCondition::IndicatorAbove(Indicator::Sma(10), Decimal::zero()).is_met(&bars));
+
+    assert!(!AlertCondition::CrossAbove(Indicator::Rsi(0)).is_valid());
+    assert!(!AlertCondition::IndicatorAbove(Indicator::Atr(0), Decimal::from(1)).is_valid());
+    assert!(!AlertCondition::IndicatorAbove(Indicator::Atr(0), Decimal::from(1)).is_met(&bars));
+}
@@ -0,0 +1,111 @@
+use chrono::NaiveDate;
+use crate::repository::{
+    error::PersistanceError
+};
+use super::Alert;
+use r2d2_sqlite::rusqlite::{
+    params,
+    Row,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+impl From<&Row<'_>> for Alert {
+    fn from(row: &Row) -> Self {
+        Alert {
+            id: row.get_unwrap(0),
+            symbol: row.get_unwrap(1),
+            condition: serde_json::from_str(&row.get_unwrap::<_, String>(2)).unwrap(),
+            last_triggered: row.get_unwrap::<_, Option<String>>(3).map(|date| date.parse().unwrap()),
+        }
+    }
+}
+
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS alert (
+            id INTEGER PRIMARY KEY,
+            symbol VARCHAR(4),
+            condition TEXT,
+            last_triggered TEXT
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+pub fn add(db: &DbConn, alert: &Alert) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT INTO
+            alert (symbol, condition, last_triggered)
+            values (?1, ?2, ?3);",
+        params![
+            alert.symbol,
+            serde_json::to_string(&alert.condition).unwrap(),
+            alert.last_triggered.map(|date| date.to_string())]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+// Returns whether there was an alert with the id
+pub fn delete(db: &DbConn, id: u32) -> Result<bool, PersistanceError> {
+    let result = db.execute(
+        "DELETE FROM alert WHERE id = ?1",
+        params![id]);
+
+    match result {
+        Ok(deleted) => Ok(deleted > 0),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+pub fn get_all(db: &DbConn) -> Result<Vec<Alert>, PersistanceError> {
+    let mut query = db.prepare("SELECT id, symbol, condition, last_triggered FROM alert ORDER BY id").unwrap();
+
+    let items = query.query_map(
+        NO_PARAMS,
+        |row| Ok(Alert::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+// Returns the symbols with an alert, without repetitions
+pub fn get_symbols(db: &DbConn) -> Result<Vec<String>, PersistanceError> {
+    let mut query = db.prepare("SELECT DISTINCT symbol FROM alert ORDER BY symbol").unwrap();
+
+    let items = query.query_map(
+        NO_PARAMS,
+        |row| row.get(0))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+pub fn rename_symbol(db: &DbConn, symbol: &str, new_symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "UPDATE alert SET symbol = ?1 WHERE symbol = ?2",
+        params![new_symbol, symbol]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+pub fn set_triggered(db: &DbConn, id: u32, date: NaiveDate) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "UPDATE alert SET last_triggered = ?1 WHERE id = ?2",
+        params![date.to_string(), id]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
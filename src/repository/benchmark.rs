@@ -0,0 +1 @@
+pub mod benchmark_db;
@@ -0,0 +1,84 @@
+use crate::repository::{
+    error::PersistanceError
+};
+use r2d2_sqlite::rusqlite::{
+    params,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS benchmark (
+            portfolio_id INTEGER REFERENCES portfolio(id),
+            symbol VARCHAR(4),
+            PRIMARY KEY (portfolio_id, symbol)
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+pub fn add(db: &DbConn, portfolio_id: u32, symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT INTO 
+            benchmark (portfolio_id, symbol) 
+            values (?1, ?2);",
+        params![portfolio_id, symbol]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+pub fn delete(db: &DbConn, portfolio_id: u32, symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"DELETE FROM benchmark 
+            WHERE portfolio_id = ?1 AND symbol = ?2;", 
+        params![portfolio_id, symbol]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+// Renames the benchmarks of every portfolio, dropping the ones of portfolios that
+// already compare against the new symbol
+pub fn rename_symbol(db: &DbConn, symbol: &str, new_symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "UPDATE OR IGNORE benchmark SET symbol = ?1 WHERE symbol = ?2",
+        params![new_symbol, symbol])
+        .and_then(|_| db.execute("DELETE FROM benchmark WHERE symbol = ?1", params![symbol]));
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+pub fn delete_all(db: &DbConn, portfolio_id: u32) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"DELETE FROM benchmark 
+            WHERE portfolio_id = ?1;", 
+        params![portfolio_id]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+// Returns the symbols of the benchmarks of the portfolio, in the order they were added
+pub fn get_all(db: &DbConn, portfolio_id: u32) -> Result<Vec<String>, PersistanceError> {
+    let mut query = db.prepare("SELECT symbol FROM benchmark WHERE portfolio_id = ?1 ORDER BY rowid").unwrap();
+
+    let items = query.query_map(
+        params![portfolio_id], 
+        |row| row.get(0))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
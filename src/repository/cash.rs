@@ -0,0 +1,111 @@
+pub mod cash_db;
+
+use std::collections::BTreeMap;
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CashMovementKind {
+    #[default]
+    Deposit,
+    Withdrawal,
+    Buy,
+    Sell,
+    Dividend,
+    CorporateAction,
+}
+
+// Change of the cash balance of a currency. Money leaving the account is negative.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct CashMovement {
+    #[serde(default)]
+    pub id: u32,
+    pub currency: String,
+    pub amount: Decimal,
+    #[serde(default)]
+    pub kind: CashMovementKind,
+    #[serde(default = "crate::repository::today")]
+    pub date: NaiveDate,
+    #[serde(default)]
+    pub note: String,
+    // Transaction of the ledger that moved the cash, if any
+    #[serde(default)]
+    pub transaction_id: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct CashBalance {
+    pub currency: String,
+    pub balance: Decimal,
+}
+
+impl CashMovementKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Deposit => "deposit",
+            Self::Withdrawal => "withdrawal",
+            Self::Buy => "buy",
+            Self::Sell => "sell",
+            Self::Dividend => "dividend",
+            Self::CorporateAction => "corporate_action",
+        }
+    }
+}
+
+impl From<&str> for CashMovementKind {
+    fn from(kind: &str) -> Self {
+        match kind {
+            "withdrawal" => Self::Withdrawal,
+            "buy" => Self::Buy,
+            "sell" => Self::Sell,
+            "dividend" => Self::Dividend,
+            "corporate_action" => Self::CorporateAction,
+            _ => Self::Deposit,
+        }
+    }
+}
+
+impl From<&str> for CashMovement {
+    fn from(json: &str) -> Self {
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+}
+
+// Adds up the movements of every currency
+pub fn get_balances(movements: &[CashMovement]) -> Vec<CashBalance> {
+    let mut balances: BTreeMap<&str, Decimal> = BTreeMap::new();
+
+    for movement in movements {
+        *balances.entry(&movement.currency).or_default() += movement.amount;
+    }
+
+    balances.into_iter()
+        .map(|(currency, balance)| CashBalance { currency: currency.to_owned(), balance })
+        .collect()
+}
+
+#[test]
+fn test_get_balances() {
+    let movement = |currency: &str, amount| CashMovement {
+        id: 0,
+        currency: currency.into(),
+        amount,
+        kind: CashMovementKind::Deposit,
+        date: NaiveDate::from_ymd_opt(2020, 5, 1).unwrap(),
+        note: String::new(),
+        transaction_id: None,
+    };
+
+    let balances = get_balances(&[
+        movement("USD", Decimal::new(10050, 2)),
+        movement("EUR", Decimal::from(20)),
+        movement("USD", Decimal::new(-2525, 2)),
+    ]);
+
+    assert_eq!(vec![
+        CashBalance { currency: "EUR".into(), balance: Decimal::from(20) },
+        CashBalance { currency: "USD".into(), balance: Decimal::new(7525, 2) },
+    ], balances);
+}
@@ -0,0 +1,90 @@
+use crate::repository::{
+    error::PersistanceError
+};
+use super::{CashMovement, CashMovementKind};
+use r2d2_sqlite::rusqlite::{
+    params,
+    Row,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+const SELECT_MOVEMENT: &str = r"
+    SELECT id, currency, amount, kind, date, note, transaction_id
+        FROM cash_movement";
+
+impl From<&Row<'_>> for CashMovement {
+    fn from(row: &Row) -> Self {
+        CashMovement {
+            id: row.get_unwrap(0),
+            currency: row.get_unwrap(1),
+            amount: row.get_unwrap(2),
+            kind: CashMovementKind::from(row.get_unwrap::<_, String>(3).as_str()),
+            date: row.get_unwrap::<_, String>(4).parse().unwrap(),
+            note: row.get_unwrap(5),
+            transaction_id: row.get_unwrap(6),
+        }
+    }
+}
+
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS cash_movement (
+            id INTEGER PRIMARY KEY,
+            portfolio_id INTEGER REFERENCES portfolio(id),
+            currency VARCHAR(3),
+            amount TEXT,
+            kind VARCHAR(16),
+            date TEXT,
+            note TEXT,
+            transaction_id INTEGER REFERENCES stock_transaction(id)
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+pub fn add(db: &DbConn, portfolio_id: u32, movement: &CashMovement) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT INTO 
+            cash_movement (portfolio_id, currency, amount, kind, date, note, transaction_id) 
+            values (?1, ?2, ?3, ?4, ?5, ?6, ?7);",
+        params![
+            portfolio_id,
+            movement.currency,
+            movement.amount,
+            movement.kind.as_str(),
+            movement.date.to_string(),
+            movement.note,
+            movement.transaction_id]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+pub fn get_all(db: &DbConn, portfolio_id: u32) -> Result<Vec<CashMovement>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE portfolio_id = ?1 ORDER BY date, id", SELECT_MOVEMENT)).unwrap();
+
+    let items = query.query_map(
+        params![portfolio_id], 
+        |row| Ok(CashMovement::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+pub fn get_by_currency(db: &DbConn, portfolio_id: u32, currency: &str) -> Result<Vec<CashMovement>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE portfolio_id = ?1 AND currency = ?2 ORDER BY date, id", SELECT_MOVEMENT)).unwrap();
+
+    let items = query.query_map(
+        params![portfolio_id, currency], 
+        |row| Ok(CashMovement::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
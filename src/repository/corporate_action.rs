@@ -0,0 +1,111 @@
+pub mod corporate_action_db;
+
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CorporateActionKind {
+    #[default]
+    Split,
+    ReverseSplit,
+    SymbolChange,
+    Merger,
+    Delisting,
+}
+
+// Event changing the shares of a stock. The position before the action is kept
+// so the original records can be reconstructed.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct CorporateAction {
+    #[serde(default)]
+    pub id: u32,
+    pub symbol: String,
+    pub kind: CorporateActionKind,
+    #[serde(default = "crate::repository::today")]
+    pub date: NaiveDate,
+    // Shares after a split or received in a merger for every share, shares
+    // merged into one by a reverse split
+    #[serde(default)]
+    pub ratio: Decimal,
+    // Symbol after a symbol change, or the acquiring stock of a merger
+    #[serde(default)]
+    pub new_symbol: String,
+    // Cash paid for every share by a merger or a delisting
+    #[serde(default)]
+    pub cash_per_share: Decimal,
+    #[serde(default)]
+    pub quantity_before: Decimal,
+    #[serde(default)]
+    pub initial_price_before: Decimal,
+}
+
+impl CorporateActionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Split => "split",
+            Self::ReverseSplit => "reverse_split",
+            Self::SymbolChange => "symbol_change",
+            Self::Merger => "merger",
+            Self::Delisting => "delisting",
+        }
+    }
+}
+
+impl From<&str> for CorporateActionKind {
+    fn from(kind: &str) -> Self {
+        match kind {
+            "reverse_split" => Self::ReverseSplit,
+            "symbol_change" => Self::SymbolChange,
+            "merger" => Self::Merger,
+            "delisting" => Self::Delisting,
+            _ => Self::Split,
+        }
+    }
+}
+
+impl From<&str> for CorporateAction {
+    fn from(json: &str) -> Self {
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+}
+
+impl CorporateAction {
+    // Shares held after the action for every share held before
+    pub fn share_factor(&self) -> Decimal {
+        match self.kind {
+            CorporateActionKind::Split | CorporateActionKind::Merger => self.ratio,
+            CorporateActionKind::ReverseSplit => Decimal::from(1) / self.ratio,
+            CorporateActionKind::SymbolChange => Decimal::from(1),
+            CorporateActionKind::Delisting => Decimal::zero(),
+        }
+    }
+
+    pub fn is_split(&self) -> bool {
+        matches!(self.kind, CorporateActionKind::Split | CorporateActionKind::ReverseSplit)
+    }
+}
+
+// Shares a share held at the end of `from` became at the end of `to`, given the
+// splits and reverse splits of the stock
+pub fn split_factor(actions: &[CorporateAction], from: NaiveDate, to: NaiveDate) -> Decimal {
+    actions.iter()
+        .filter(|action| action.is_split() && action.date > from && action.date <= to)
+        .fold(Decimal::from(1), |factor, action| factor * action.share_factor())
+}
+
+#[test]
+fn test_split_factor() {
+    let date = |month| NaiveDate::from_ymd_opt(2020, month, 1).unwrap();
+    let actions = vec![
+        CorporateAction { symbol: "AAPL".into(), kind: CorporateActionKind::Split, date: date(3), ratio: Decimal::from(4), ..CorporateAction::default() },
+        CorporateAction { symbol: "AAPL".into(), kind: CorporateActionKind::ReverseSplit, date: date(6), ratio: Decimal::from(2), ..CorporateAction::default() },
+        CorporateAction { symbol: "AAPL".into(), kind: CorporateActionKind::SymbolChange, date: date(7), new_symbol: "APL".into(), ..CorporateAction::default() },
+    ];
+
+    assert_eq!(Decimal::from(2), split_factor(&actions, date(1), date(12)));
+    assert_eq!(Decimal::from(4), split_factor(&actions, date(1), date(5)));
+    assert_eq!(Decimal::new(5, 1), split_factor(&actions, date(3), date(12)));
+    assert_eq!(Decimal::from(1), split_factor(&actions, date(6), date(12)));
+}
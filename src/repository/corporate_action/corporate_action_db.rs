@@ -0,0 +1,124 @@
+use crate::repository::{
+    error::PersistanceError
+};
+use super::{CorporateAction, CorporateActionKind};
+use r2d2_sqlite::rusqlite::{
+    params,
+    Row,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+const SELECT_ACTION: &str = r"
+    SELECT id, symbol, kind, date, ratio, new_symbol, cash_per_share, quantity_before, initial_price_before
+        FROM corporate_action";
+
+impl From<&Row<'_>> for CorporateAction {
+    fn from(row: &Row) -> Self {
+        CorporateAction {
+            id: row.get_unwrap(0),
+            symbol: row.get_unwrap(1),
+            kind: CorporateActionKind::from(row.get_unwrap::<_, String>(2).as_str()),
+            date: row.get_unwrap::<_, String>(3).parse().unwrap(),
+            ratio: row.get_unwrap(4),
+            new_symbol: row.get_unwrap(5),
+            cash_per_share: row.get_unwrap(6),
+            quantity_before: row.get_unwrap(7),
+            initial_price_before: row.get_unwrap(8),
+        }
+    }
+}
+
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS corporate_action (
+            id INTEGER PRIMARY KEY,
+            portfolio_id INTEGER REFERENCES portfolio(id),
+            symbol VARCHAR(4),
+            kind VARCHAR(16),
+            date TEXT,
+            ratio TEXT,
+            new_symbol VARCHAR(4),
+            cash_per_share TEXT,
+            quantity_before TEXT,
+            initial_price_before TEXT
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+pub fn add(db: &DbConn, portfolio_id: u32, action: &CorporateAction) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT INTO 
+            corporate_action (portfolio_id, symbol, kind, date, ratio, new_symbol, cash_per_share, quantity_before, initial_price_before) 
+            values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);",
+        params![
+            portfolio_id,
+            action.symbol,
+            action.kind.as_str(),
+            action.date.to_string(),
+            action.ratio,
+            action.new_symbol,
+            action.cash_per_share,
+            action.quantity_before,
+            action.initial_price_before]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+pub fn get_all(db: &DbConn, portfolio_id: u32) -> Result<Vec<CorporateAction>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE portfolio_id = ?1 ORDER BY date, id", SELECT_ACTION)).unwrap();
+
+    let items = query.query_map(
+        params![portfolio_id], 
+        |row| Ok(CorporateAction::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+// Returns the actions of a stock, including the symbol change or merger that made it its symbol
+pub fn get_by_symbol(db: &DbConn, portfolio_id: u32, symbol: &str) -> Result<Vec<CorporateAction>, PersistanceError> {
+    let mut query = db.prepare(&format!(
+        "{} WHERE portfolio_id = ?1 AND (symbol = ?2 OR new_symbol = ?2) ORDER BY date, id", SELECT_ACTION)).unwrap();
+
+    let items = query.query_map(
+        params![portfolio_id, symbol], 
+        |row| Ok(CorporateAction::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+// Returns the splits and reverse splits of a stock recorded by every portfolio, by date
+pub fn get_splits(db: &DbConn, symbol: &str) -> Result<Vec<CorporateAction>, PersistanceError> {
+    let mut query = db.prepare(&format!(
+        "{} WHERE symbol = ?1 AND kind IN (?2, ?3) ORDER BY date, kind, ratio, id", SELECT_ACTION)).unwrap();
+
+    let items = query.query_map(
+        params![symbol, CorporateActionKind::Split.as_str(), CorporateActionKind::ReverseSplit.as_str()],
+        |row| Ok(CorporateAction::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+pub fn rename_symbol(db: &DbConn, portfolio_id: u32, symbol: &str, new_symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "UPDATE corporate_action SET symbol = ?1 WHERE portfolio_id = ?2 AND symbol = ?3",
+        params![new_symbol, portfolio_id, symbol]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
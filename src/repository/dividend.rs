@@ -0,0 +1,113 @@
+pub mod dividend_db;
+pub mod dividend_api;
+
+use std::collections::BTreeMap;
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+
+// Dividend paid by a stock
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Dividend {
+    #[serde(default)]
+    pub id: u32,
+    pub symbol: String,
+    // Paid per share
+    pub amount: Decimal,
+    pub ex_date: NaiveDate,
+    pub pay_date: NaiveDate,
+    // Filled with the currency of the stock when empty
+    #[serde(default)]
+    pub currency: String,
+    // Shares entitled to the dividend, known once the ex-date is reached
+    #[serde(default)]
+    pub quantity: Option<Decimal>,
+    #[serde(default)]
+    pub paid: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct MonthlyIncome {
+    pub month: String,
+    pub currency: String,
+    pub amount: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct SymbolIncome {
+    pub symbol: String,
+    pub currency: String,
+    pub amount: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct DividendReport {
+    pub by_month: Vec<MonthlyIncome>,
+    pub by_symbol: Vec<SymbolIncome>,
+}
+
+impl From<&str> for Dividend {
+    fn from(json: &str) -> Self {
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+}
+
+impl Dividend {
+    // Amount received for the entitled shares
+    pub fn gross(&self) -> Option<Decimal> {
+        self.quantity.map(|quantity| quantity * self.amount)
+    }
+}
+
+// Adds up the paid dividends by month of payment and by stock
+pub fn get_report(dividends: &[Dividend]) -> DividendReport {
+    let mut by_month: BTreeMap<(String, String), Decimal> = BTreeMap::new();
+    let mut by_symbol: BTreeMap<(String, String), Decimal> = BTreeMap::new();
+
+    for dividend in dividends.iter().filter(|dividend| dividend.paid) {
+        let gross = dividend.gross().unwrap_or_default();
+        let month = dividend.pay_date.format("%Y-%m").to_string();
+
+        *by_month.entry((month, dividend.currency.clone())).or_default() += gross;
+        *by_symbol.entry((dividend.symbol.clone(), dividend.currency.clone())).or_default() += gross;
+    }
+
+    DividendReport {
+        by_month: by_month.into_iter()
+            .map(|((month, currency), amount)| MonthlyIncome { month, currency, amount })
+            .collect(),
+        by_symbol: by_symbol.into_iter()
+            .map(|((symbol, currency), amount)| SymbolIncome { symbol, currency, amount })
+            .collect(),
+    }
+}
+
+#[test]
+fn test_get_report() {
+    let dividend = |symbol: &str, month, quantity, paid| Dividend {
+        symbol: symbol.into(),
+        amount: Decimal::new(5, 1),
+        ex_date: NaiveDate::from_ymd_opt(2020, month, 1).unwrap(),
+        pay_date: NaiveDate::from_ymd_opt(2020, month, 15).unwrap(),
+        currency: "USD".into(),
+        quantity: Some(Decimal::from(quantity)),
+        paid,
+        ..Dividend::default()
+    };
+
+    let report = get_report(&[
+        dividend("KO", 3, 10, true),
+        dividend("PG", 3, 4, true),
+        dividend("KO", 6, 10, true),
+        dividend("KO", 9, 10, false),
+    ]);
+
+    assert_eq!(vec![
+        MonthlyIncome { month: "2020-03".into(), currency: "USD".into(), amount: Decimal::from(7) },
+        MonthlyIncome { month: "2020-06".into(), currency: "USD".into(), amount: Decimal::from(5) },
+    ], report.by_month);
+    assert_eq!(vec![
+        SymbolIncome { symbol: "KO".into(), currency: "USD".into(), amount: Decimal::from(10) },
+        SymbolIncome { symbol: "PG".into(), currency: "USD".into(), amount: Decimal::from(2) },
+    ], report.by_symbol);
+}
@@ -0,0 +1,59 @@
+use hyper::{
+    body::to_bytes,
+    Uri,
+};
+use serde_json;
+use serde::Deserialize;
+use crate::repository::HttpClient;
+use crate::decimal::Decimal;
+
+enum Endpoint {
+    DividendHistory(String),
+}
+
+impl Endpoint {
+    const BASE_URL: &'static str = "https://financialmodelingprep.com/";
+
+    pub fn to_uri(&self) -> Uri {
+        let route = match self {
+            Self::DividendHistory(symbol) => "api/v3/historical-price-full/stock_dividend/".to_owned() + symbol,
+        };
+
+        let base = String::from(Self::BASE_URL);
+
+        format!("{}{}", base, route).parse().unwrap()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DividendElement {
+    // Ex-date
+    pub date: String,
+    pub dividend: Decimal,
+    #[serde(default, alias = "paymentDate")]
+    pub payment_date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DividendHistoryResponse {
+    #[serde(default)]
+    pub historical: Vec<DividendElement>,
+}
+
+pub async fn get_dividend_history(client: &HttpClient, symbol: &str) -> Result<Vec<DividendElement>, Box<dyn std::error::Error + Send + Sync>> {
+    let uri = Endpoint::DividendHistory(symbol.into()).to_uri();
+    let response = client.get(uri).await;
+
+    match response {
+        Ok(mut resp) => {
+            match to_bytes(resp.body_mut()).await {
+                Ok(body) => {
+                    let history: DividendHistoryResponse = serde_json::from_slice(&body)?;
+                    Ok(history.historical)
+                },
+                Err(e) => Err(e.into())
+            }
+        },
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,125 @@
+use crate::repository::{
+    error::PersistanceError
+};
+use super::Dividend;
+use r2d2_sqlite::rusqlite::{
+    params,
+    Row,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+const SELECT_DIVIDEND: &str = r"
+    SELECT id, symbol, amount, ex_date, pay_date, currency, quantity, paid
+        FROM dividend";
+
+impl From<&Row<'_>> for Dividend {
+    fn from(row: &Row) -> Self {
+        Dividend {
+            id: row.get_unwrap(0),
+            symbol: row.get_unwrap(1),
+            amount: row.get_unwrap(2),
+            ex_date: row.get_unwrap::<_, String>(3).parse().unwrap(),
+            pay_date: row.get_unwrap::<_, String>(4).parse().unwrap(),
+            currency: row.get_unwrap(5),
+            quantity: row.get_unwrap(6),
+            paid: row.get_unwrap(7),
+        }
+    }
+}
+
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS dividend (
+            id INTEGER PRIMARY KEY,
+            portfolio_id INTEGER REFERENCES portfolio(id),
+            symbol VARCHAR(4),
+            amount TEXT,
+            ex_date TEXT,
+            pay_date TEXT,
+            currency VARCHAR(3),
+            quantity TEXT,
+            paid INTEGER,
+            UNIQUE (portfolio_id, symbol, ex_date)
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+// Stores a dividend unless the one of its ex-date is already stored
+pub fn add(db: &DbConn, portfolio_id: u32, dividend: &Dividend) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT OR IGNORE INTO 
+            dividend (portfolio_id, symbol, amount, ex_date, pay_date, currency, quantity, paid) 
+            values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8);",
+        params![
+            portfolio_id,
+            dividend.symbol,
+            dividend.amount,
+            dividend.ex_date.to_string(),
+            dividend.pay_date.to_string(),
+            dividend.currency,
+            dividend.quantity,
+            dividend.paid]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+pub fn update(db: &DbConn, dividend: &Dividend) -> Result<(), PersistanceError> {
+    let result = db.execute(r"
+        UPDATE dividend 
+            SET amount = ?1, pay_date = ?2, currency = ?3, quantity = ?4, paid = ?5
+            WHERE id = ?6",
+        params![
+            dividend.amount,
+            dividend.pay_date.to_string(),
+            dividend.currency,
+            dividend.quantity,
+            dividend.paid,
+            dividend.id]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+pub fn get_all(db: &DbConn, portfolio_id: u32) -> Result<Vec<Dividend>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE portfolio_id = ?1 ORDER BY ex_date, id", SELECT_DIVIDEND)).unwrap();
+
+    let items = query.query_map(
+        params![portfolio_id], 
+        |row| Ok(Dividend::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+pub fn get_by_symbol(db: &DbConn, portfolio_id: u32, symbol: &str) -> Result<Vec<Dividend>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE portfolio_id = ?1 AND symbol = ?2 ORDER BY ex_date, id", SELECT_DIVIDEND)).unwrap();
+
+    let items = query.query_map(
+        params![portfolio_id, symbol], 
+        |row| Ok(Dividend::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+pub fn rename_symbol(db: &DbConn, portfolio_id: u32, symbol: &str, new_symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "UPDATE dividend SET symbol = ?1 WHERE portfolio_id = ?2 AND symbol = ?3",
+        params![new_symbol, portfolio_id, symbol]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
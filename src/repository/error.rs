@@ -8,17 +8,29 @@ use r2d2_sqlite::rusqlite;
 pub enum PersistanceError {
     KeyNotFoundError,
     InitializationError(rusqlite::Error),
+    UnsupportedSchemaVersion(u32),
     CouldNotInsert(rusqlite::Error),
     CouldNotDelete(rusqlite::Error),
     CouldNotUpdate(rusqlite::Error),
     EntryHasDependencies,
+    InvalidAmount,
+    NotEnoughCash,
+    NotEnoughShares,
+    InvalidCorporateAction,
+    InvalidWebhook,
 }
 
 impl Error for PersistanceError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             PersistanceError::KeyNotFoundError => None,
+            PersistanceError::UnsupportedSchemaVersion(_) => None,
             PersistanceError::EntryHasDependencies => None,
+            PersistanceError::InvalidAmount => None,
+            PersistanceError::NotEnoughCash => None,
+            PersistanceError::NotEnoughShares => None,
+            PersistanceError::InvalidCorporateAction => None,
+            PersistanceError::InvalidWebhook => None,
             PersistanceError::CouldNotInsert(e) |
             PersistanceError::CouldNotUpdate(e) |
             PersistanceError::CouldNotDelete(e) |
@@ -31,11 +43,17 @@ impl fmt::Display for PersistanceError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             PersistanceError::KeyNotFoundError => write!(f, "Key not found!"),
+            PersistanceError::UnsupportedSchemaVersion(version) => write!(f, "The storage has schema version {}, which is newer than this version supports!", version),
             PersistanceError::EntryHasDependencies => write!(f, "Some items depend on this item!"),
+            PersistanceError::InvalidAmount => write!(f, "The amount must be positive!"),
+            PersistanceError::NotEnoughCash => write!(f, "Not enough cash!"),
+            PersistanceError::NotEnoughShares => write!(f, "Not enough shares!"),
+            PersistanceError::InvalidCorporateAction => write!(f, "The corporate action is missing its ratio or symbol!"),
+            PersistanceError::InvalidWebhook => write!(f, "The webhook needs an http or https URL and a secret!"),
             PersistanceError::CouldNotInsert(e) |
             PersistanceError::CouldNotUpdate(e) |
             PersistanceError::CouldNotDelete(e) |
-            PersistanceError::InitializationError(e) => write!(f, "{}", e.to_string())
+            PersistanceError::InitializationError(e) => write!(f, "{}", e)
         }
     }
 }
@@ -0,0 +1,33 @@
+pub mod fx_db;
+pub mod fx_api;
+
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use super::{DbConn, error::PersistanceError};
+
+// Exchange rate of a day: 1 `base` equals `rate` `quote`
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct FxRate {
+    pub base: String,
+    pub quote: String,
+    pub rate: Decimal,
+    pub date: NaiveDate,
+}
+
+// Returns the latest rate known on `date` to convert from one currency to another,
+// using the inverse pair when only that one is stored
+pub fn get_rate(db: &DbConn, from: &str, to: &str, date: NaiveDate) -> Result<Option<Decimal>, PersistanceError> {
+    if from == to {
+        return Ok(Some(Decimal::from(1)));
+    }
+
+    if let Some(fx_rate) = fx_db::get_latest(db, from, to, date)? {
+        return Ok(Some(fx_rate.rate));
+    }
+
+    let inverse = fx_db::get_latest(db, to, from, date)?
+        .and_then(|fx_rate| Decimal::from(1).checked_div(fx_rate.rate));
+
+    Ok(inverse)
+}
@@ -0,0 +1,58 @@
+use hyper::{
+    body::to_bytes,
+    Uri,
+};
+use serde_json;
+use serde::Deserialize;
+use log::debug;
+use crate::repository::HttpClient;
+use crate::decimal::Decimal;
+
+enum Endpoint {
+    Quote(String),
+}
+
+impl Endpoint {
+    const BASE_URL: &'static str = "https://financialmodelingprep.com/";
+
+    pub fn to_uri(&self) -> Uri {
+        let route = match self {
+            Self::Quote(pair) => "api/v3/quote/".to_owned() + pair,
+        };
+
+        let base = String::from(Self::BASE_URL);
+
+        format!("{}{}", base, route).parse().unwrap()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FxQuoteResponse {
+    pub symbol: String,
+    pub price: Decimal,
+}
+
+// Gets how many `quote` units a `base` unit is worth
+pub async fn get_fx_rate(client: &HttpClient, base: &str, quote: &str) -> Result<FxQuoteResponse, Box<dyn std::error::Error + Send + Sync>> {
+    let uri = Endpoint::Quote(format!("{}{}", base, quote)).to_uri();
+    let response = client.get(uri).await;
+
+    match response {
+        Ok(mut resp) => {
+            match to_bytes(resp.body_mut()).await {
+                Ok(body) => {
+                    let mut quotes: Vec<FxQuoteResponse> = serde_json::from_slice(&body)?;
+                    match quotes.pop() {
+                        Some(fx_quote) => {
+                            debug!(target: "fx_api", "Rate of {}: {}", fx_quote.symbol, fx_quote.price);
+                            Ok(fx_quote)
+                        },
+                        None => Err(format!("No quote for {}{}", base, quote).into())
+                    }
+                },
+                Err(e) => Err(e.into())
+            }
+        },
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,81 @@
+use crate::repository::{
+    error::PersistanceError
+};
+use super::FxRate;
+use chrono::NaiveDate;
+use r2d2_sqlite::rusqlite::{
+    params,
+    OptionalExtension,
+    Row,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+impl From<&Row<'_>> for FxRate {
+    fn from(row: &Row) -> Self {
+        FxRate {
+            base: row.get_unwrap(0),
+            quote: row.get_unwrap(1),
+            rate: row.get_unwrap(2),
+            date: row.get_unwrap::<_, String>(3).parse().unwrap(),
+        }
+    }
+}
+
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS fx_rate (
+            base VARCHAR(3),
+            quote VARCHAR(3),
+            rate TEXT,
+            date TEXT,
+            PRIMARY KEY (base, quote, date)
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+// Stores the rate of a day, replacing the one already stored for that day
+pub fn add(db: &DbConn, fx_rate: &FxRate) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT OR REPLACE INTO 
+            fx_rate (base, quote, rate, date) 
+            values (?1, ?2, ?3, ?4);",
+        params![fx_rate.base, fx_rate.quote, fx_rate.rate, fx_rate.date.to_string()]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+pub fn get_latest(db: &DbConn, base: &str, quote: &str, date: NaiveDate) -> Result<Option<FxRate>, PersistanceError> {
+    let result = db.query_row(
+        r"SELECT base, quote, rate, date 
+            FROM fx_rate 
+            WHERE base = ?1 AND quote = ?2 AND date <= ?3
+            ORDER BY date DESC
+            LIMIT 1", 
+        params![base, quote, date.to_string()], 
+        |row| Ok(FxRate::from(row)))
+        .optional();
+
+    result.map_err(PersistanceError::CouldNotInsert)
+}
+
+pub fn get_history(db: &DbConn, base: &str, quote: &str) -> Result<Vec<FxRate>, PersistanceError> {
+    let mut query = db.prepare(r"
+    SELECT base, quote, rate, date
+        FROM fx_rate
+        WHERE base = ?1 AND quote = ?2
+        ORDER BY date").unwrap();
+
+    let items = query.query_map(
+        params![base, quote], 
+        |row| Ok(FxRate::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
@@ -1,7 +1,131 @@
 pub mod market_db;
 
+use std::collections::BTreeMap;
+use chrono::{Datelike, NaiveDate, Weekday};
+use log::debug;
+use serde::{Serialize, Deserialize};
+use super::stock::Stock;
+use crate::decimal::Decimal;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
 pub struct Market {
-    id: u16,
-    symbol: String,
+    pub id: u16,
+    #[serde(default)]
+    pub symbol: String,
+    #[serde(default)]
+    pub currency: String,
+}
+
+// Day a market is closed besides the weekends
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct MarketHoliday {
+    pub market_id: u16,
+    pub date: NaiveDate,
+    #[serde(default)]
+    pub name: String,
+}
+
+// Stocks of a market sharing the same currency, with their subtotals
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct MarketPortfolio {
+    pub market: Market,
+    pub currency: String,
+    pub stocks: Vec<Stock>,
+    pub total_value: Decimal,
+    pub total_cost: Decimal,
+    // Subtotals converted to the base currency, if its exchange rate is known
+    pub total_value_base: Option<Decimal>,
+    pub total_cost_base: Option<Decimal>,
+}
+
+impl From<&str> for Market {
+    fn from(json: &str) -> Self {
+        debug!(target: "market", "Deserializing {}", json);
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+}
+
+impl From<&str> for MarketHoliday {
+    fn from(json: &str) -> Self {
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+}
+
+pub fn is_trading_day(date: NaiveDate, holidays: &[NaiveDate]) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !holidays.contains(&date)
+}
+
+// The date itself if the market trades that day, the next day it does otherwise
+pub fn next_trading_day(date: NaiveDate, holidays: &[NaiveDate]) -> NaiveDate {
+    let mut date = date;
+    while !is_trading_day(date, holidays) {
+        date = date.succ_opt().unwrap();
+    }
+    date
+}
+
+// Groups the stocks by market and currency, computing the subtotals of each group
+pub fn group_by_market(stocks: Vec<Stock>) -> Vec<MarketPortfolio> {
+    let mut groups: BTreeMap<(u16, String), MarketPortfolio> = BTreeMap::new();
+
+    for stock in stocks {
+        let currency = stock.currency().to_owned();
+        let group = groups
+            .entry((stock.market.id, currency.clone()))
+            .or_insert_with(|| MarketPortfolio {
+                market: stock.market.clone(),
+                currency,
+                stocks: vec![],
+                total_value: Decimal::zero(),
+                total_cost: Decimal::zero(),
+                total_value_base: None,
+                total_cost_base: None,
+            });
+
+        group.total_value += stock.market_value();
+        group.total_cost += stock.cost();
+        group.stocks.push(stock);
+    }
+
+    groups.into_values().collect()
 }
 
+#[test]
+fn test_group_by_market() {
+    let nasdaq = Market { id: 1, symbol: "NASDAQ".into(), currency: "USD".into() };
+    let xetra = Market { id: 2, symbol: "XETRA".into(), currency: "EUR".into() };
+    let stock = |symbol: &str, price: i64, market: &Market| Stock {
+        symbol: symbol.into(),
+        name: symbol.into(),
+        price: Decimal::from(price),
+        initial_price: Decimal::from(price - 1),
+        quantity: Decimal::from(2),
+        market: market.clone(),
+        ..Stock::default()
+    };
+
+    let groups = group_by_market(vec![
+        stock("AAPL", 10, &nasdaq),
+        stock("SAP", 5, &xetra),
+        stock("MSFT", 20, &nasdaq),
+    ]);
+
+    assert_eq!(2, groups.len());
+    assert_eq!(nasdaq, groups[0].market);
+    assert_eq!(2, groups[0].stocks.len());
+    assert_eq!(Decimal::from(60), groups[0].total_value);
+    assert_eq!(Decimal::from(56), groups[0].total_cost);
+    assert_eq!("EUR", groups[1].currency);
+    assert_eq!(Decimal::from(10), groups[1].total_value);
+}
+
+#[test]
+fn test_next_trading_day() {
+    let date = |month, day| NaiveDate::from_ymd_opt(2020, month, day).unwrap();
+    let holidays = [date(12, 25), date(12, 28)];
+
+    assert_eq!(date(12, 24), next_trading_day(date(12, 24), &holidays));
+    // Christmas, then the weekend and a closed Monday
+    assert_eq!(date(12, 29), next_trading_day(date(12, 25), &holidays));
+    assert!(!is_trading_day(date(12, 27), &[]));
+}
@@ -1,10 +1,12 @@
+use chrono::NaiveDate;
 use crate::repository::{
     error::PersistanceError
 };
-use super::Market;
+use super::{Market, MarketHoliday};
 use r2d2_sqlite::rusqlite::{
     Row,
     params,
+    OptionalExtension,
     NO_PARAMS
 };
 use crate::repository::DbConn;
@@ -14,6 +16,7 @@ impl From<&Row<'_>> for Market {
         Market {
             id: row.get_unwrap(0),
             symbol: row.get_unwrap(1),
+            currency: row.get_unwrap(2),
         }
     }
 }
@@ -22,18 +25,28 @@ pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
     db.execute(
         r"CREATE TABLE IF NOT EXISTS market (
             id INTEGER PRIMARY KEY,
-            symbol VARCHAR(4)
+            symbol VARCHAR(4),
+            currency VARCHAR(3)
         )", NO_PARAMS)
-        .map(|x| ())
-        .map_err(|e| PersistanceError::InitializationError(e))
+        .map_err(PersistanceError::InitializationError)?;
+
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS market_holiday (
+            market_id INTEGER REFERENCES market(id),
+            date TEXT,
+            name TEXT,
+            PRIMARY KEY (market_id, date)
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
 }
 
 pub fn add(db: &DbConn, market: &Market) -> Result<(), PersistanceError> {
     let result = db.execute(
         r"INSERT INTO 
-            market (symbol) 
-            values (?1);",
-        params![market.symbol]);
+            market (symbol, currency) 
+            values (?1, ?2);",
+        params![market.symbol, market.currency]);
 
     match result {
         Ok(_) => Ok(()),
@@ -56,9 +69,9 @@ pub fn delete(db: &DbConn, id: u16) -> Result<(), PersistanceError> {
 pub fn update(db: &DbConn, market: &Market) -> Result<(), PersistanceError> {
     let result = db.execute(r"
         UPDATE market 
-            SET symbol = ?1
-            WHERE id = ?2",
-        params![market.symbol, market.id.to_string() ]);
+            SET symbol = ?1, currency = ?2
+            WHERE id = ?3",
+        params![market.symbol, market.currency, market.id]);
 
     match result {
         Ok(_) => Ok(()),
@@ -69,21 +82,17 @@ pub fn update(db: &DbConn, market: &Market) -> Result<(), PersistanceError> {
 
 pub fn get(db: &DbConn, id: u16) -> Result<Option<Market>, PersistanceError> {
     let result = db.query_row(
-        "SELECT id, symbol FROM market WHERE id = ?1", 
+        "SELECT id, symbol, currency FROM market WHERE id = ?1", 
         params![id], 
-        |row| {
-            Ok(Some(Market {
-                id: row.get(0).unwrap(),
-                symbol: row.get(1).unwrap(),
-            }))
-        });
-
-    result.map_err(|error| PersistanceError::CouldNotInsert(error))
+        |row| Ok(Market::from(row)))
+        .optional();
+
+    result.map_err(PersistanceError::CouldNotInsert)
 }
 
 pub fn get_all(db: &DbConn) -> std::result::Result<Vec<Market>, PersistanceError> { 
     let mut query = db.prepare(r"
-    SELECT id, symbol
+    SELECT id, symbol, currency
         FROM market").unwrap();
 
     let items = query.query_map(
@@ -95,3 +104,57 @@ pub fn get_all(db: &DbConn) -> std::result::Result<Vec<Market>, PersistanceError
 
     Ok(items)
 }
+
+pub fn add_holiday(db: &DbConn, holiday: &MarketHoliday) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT OR REPLACE INTO
+            market_holiday (market_id, date, name)
+            values (?1, ?2, ?3);",
+        params![holiday.market_id, holiday.date.to_string(), holiday.name]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+// Returns whether the market had the holiday
+pub fn delete_holiday(db: &DbConn, market_id: u16, date: NaiveDate) -> Result<bool, PersistanceError> {
+    let result = db.execute(
+        "DELETE FROM market_holiday WHERE market_id = ?1 AND date = ?2",
+        params![market_id, date.to_string()]);
+
+    match result {
+        Ok(deleted) => Ok(deleted > 0),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+pub fn delete_holidays(db: &DbConn, market_id: u16) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "DELETE FROM market_holiday WHERE market_id = ?1",
+        params![market_id]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+// Returns the holidays of the market, oldest first
+pub fn get_holidays(db: &DbConn, market_id: u16) -> Result<Vec<MarketHoliday>, PersistanceError> {
+    let mut query = db.prepare("SELECT market_id, date, name FROM market_holiday WHERE market_id = ?1 ORDER BY date").unwrap();
+
+    let items = query.query_map(
+        params![market_id],
+        |row| Ok(MarketHoliday {
+            market_id: row.get_unwrap(0),
+            date: row.get_unwrap::<_, String>(1).parse().unwrap(),
+            name: row.get_unwrap(2),
+        }))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
@@ -0,0 +1,279 @@
+use r2d2_sqlite::rusqlite::{params, NO_PARAMS};
+use super::{DbConn, error::PersistanceError};
+use super::portfolio::DEFAULT_PORTFOLIO_ID;
+
+// Version of the storage schema, kept in the user_version of the database
+const SCHEMA_VERSION: u32 = 7;
+
+// Columns of the tables that are copied when adding them to the default portfolio
+const STOCK_COLUMNS: &str = "symbol, name, price, initial_price, market_id, currency, quantity, drip";
+const DIVIDEND_COLUMNS: &str = "id, symbol, amount, ex_date, pay_date, currency, quantity, paid";
+
+// Brings the tables of a storage created by an older version to the current schema
+pub fn migrate(db: &DbConn) -> Result<(), PersistanceError> {
+    let version: u32 = db.query_row("PRAGMA user_version", NO_PARAMS, |row| row.get(0))
+        .map_err(PersistanceError::InitializationError)?;
+
+    if version > SCHEMA_VERSION {
+        return Err(PersistanceError::UnsupportedSchemaVersion(version));
+    }
+
+    db.execute_batch("BEGIN").map_err(PersistanceError::InitializationError)?;
+
+    match migrate_from(db, version) {
+        Ok(_) => db.execute_batch("COMMIT").map_err(PersistanceError::InitializationError),
+        Err(e) => {
+            db.execute_batch("ROLLBACK").map_err(PersistanceError::InitializationError)?;
+            Err(e)
+        }
+    }
+}
+
+// Runs the migrations of every version after the given one, oldest first
+fn migrate_from(db: &DbConn, version: u32) -> Result<(), PersistanceError> {
+    if version < 1 {
+        // Markets were stored without their currency
+        add_columns(db, "market", &["currency VARCHAR(3) DEFAULT ''"])?;
+    }
+
+    if version < 2 {
+        // Prices were stored as floating point numbers
+        recreate_table(db, "stock", r"
+            symbol VARCHAR(4) PRIMARY KEY,
+            name VARCHAR(255),
+            price TEXT,
+            initial_price TEXT,
+            market_id INTEGER REFERENCES market(id)",
+            "symbol, name, price, initial_price, market_id",
+            "symbol, name, price, initial_price, market_id")?;
+    }
+
+    if version < 3 {
+        // Stocks were priced in the currency of their market
+        add_columns(db, "stock", &["currency VARCHAR(3)"])?;
+    }
+
+    if version < 4 {
+        // Holdings had no quantity
+        add_columns(db, "stock", &["quantity TEXT"])?;
+    }
+
+    if version < 5 {
+        // Dividends couldn't be reinvested
+        add_columns(db, "stock", &["drip INTEGER"])?;
+    }
+
+    if version < 6 {
+        // Everything belonged to a single portfolio, keyed by symbol alone
+        let portfolio_id = DEFAULT_PORTFOLIO_ID.to_string();
+        recreate_table(db, "stock", r"
+            portfolio_id INTEGER REFERENCES portfolio(id),
+            symbol VARCHAR(4),
+            name VARCHAR(255),
+            price TEXT,
+            initial_price TEXT,
+            market_id INTEGER REFERENCES market(id),
+            currency VARCHAR(3),
+            quantity TEXT,
+            drip INTEGER,
+            PRIMARY KEY (portfolio_id, symbol)",
+            &format!("portfolio_id, {}", STOCK_COLUMNS),
+            &format!("{}, {}", portfolio_id, STOCK_COLUMNS))?;
+        recreate_table(db, "dividend", r"
+            id INTEGER PRIMARY KEY,
+            portfolio_id INTEGER REFERENCES portfolio(id),
+            symbol VARCHAR(4),
+            amount TEXT,
+            ex_date TEXT,
+            pay_date TEXT,
+            currency VARCHAR(3),
+            quantity TEXT,
+            paid INTEGER,
+            UNIQUE (portfolio_id, symbol, ex_date)",
+            &format!("portfolio_id, {}", DIVIDEND_COLUMNS),
+            &format!("{}, {}", portfolio_id, DIVIDEND_COLUMNS))?;
+
+        for table in &["stock_transaction", "cash_movement", "corporate_action"] {
+            add_columns(db, table, &[&format!("portfolio_id INTEGER DEFAULT {}", portfolio_id)])?;
+        }
+    }
+
+    if version < 7 {
+        // Stocks had no tags nor company profile fields
+        add_columns(db, "stock", &["sector VARCHAR(64)", "industry VARCHAR(64)", "country VARCHAR(2)", "tags TEXT"])?;
+    }
+
+    db.execute_batch(&format!("PRAGMA user_version = {}", SCHEMA_VERSION))
+        .map_err(PersistanceError::InitializationError)
+}
+
+// Checks if an older version of the storage created the table
+fn table_exists(db: &DbConn, table: &str) -> Result<bool, PersistanceError> {
+    db.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |row| row.get::<_, i64>(0))
+        .map(|count| count > 0)
+        .map_err(PersistanceError::InitializationError)
+}
+
+// Adds the columns to an existing table, new tables are created with them
+fn add_columns(db: &DbConn, table: &str, columns: &[&str]) -> Result<(), PersistanceError> {
+    if !table_exists(db, table)? {
+        return Ok(());
+    }
+
+    for column in columns {
+        db.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {}", table, column))
+            .map_err(PersistanceError::InitializationError)?;
+    }
+
+    Ok(())
+}
+
+// Replaces an existing table with one of the new definition, copying its rows.
+// The new table is renamed last so the references of other tables still point to it.
+fn recreate_table(db: &DbConn, table: &str, definition: &str, columns: &str, values: &str) -> Result<(), PersistanceError> {
+    if !table_exists(db, table)? {
+        return Ok(());
+    }
+
+    db.execute_batch(&format!(
+        r"CREATE TABLE {0}_migrated ({1});
+        INSERT INTO {0}_migrated ({2}) SELECT {3} FROM {0};
+        DROP TABLE {0};
+        ALTER TABLE {0}_migrated RENAME TO {0};",
+        table, definition, columns, values))
+        .map_err(PersistanceError::InitializationError)
+}
+
+// Tables as the first version of the storage created them
+#[cfg(test)]
+const BASELINE_SCHEMA: &str = r"
+    CREATE TABLE market (
+        id INTEGER PRIMARY KEY,
+        symbol VARCHAR(4)
+    );
+    CREATE TABLE stock (
+        symbol VARCHAR(4) PRIMARY KEY,
+        name VARCHAR(255),
+        price REAL,
+        initial_price REAL,
+        market_id INTEGER REFERENCES market(id)
+    );
+    INSERT INTO market (id, symbol) VALUES (1, 'NYSE');
+    INSERT INTO stock VALUES ('KO', 'Coca-Cola', 60.5, 50.25, 1);";
+
+#[cfg(test)]
+use crate::decimal::Decimal;
+
+
+// Tables of a single portfolio, before every table had a portfolio
+#[cfg(test)]
+const SINGLE_PORTFOLIO_SCHEMA: &str = r"
+    PRAGMA user_version = 5;
+    CREATE TABLE stock (
+        symbol VARCHAR(4) PRIMARY KEY,
+        name VARCHAR(255),
+        price TEXT,
+        initial_price TEXT,
+        market_id INTEGER REFERENCES market(id),
+        currency VARCHAR(3),
+        quantity TEXT,
+        drip INTEGER
+    );
+    CREATE TABLE stock_transaction (
+        id INTEGER PRIMARY KEY,
+        symbol VARCHAR(4) REFERENCES stock(symbol),
+        kind VARCHAR(8),
+        quantity TEXT,
+        price TEXT,
+        commission TEXT,
+        currency VARCHAR(3),
+        date TEXT,
+        realized_gain TEXT
+    );
+    CREATE TABLE cash_movement (
+        id INTEGER PRIMARY KEY,
+        currency VARCHAR(3),
+        amount TEXT,
+        kind VARCHAR(16),
+        date TEXT,
+        note TEXT,
+        transaction_id INTEGER REFERENCES stock_transaction(id)
+    );
+    CREATE TABLE dividend (
+        id INTEGER PRIMARY KEY,
+        symbol VARCHAR(4) REFERENCES stock(symbol),
+        amount TEXT,
+        ex_date TEXT,
+        pay_date TEXT,
+        currency VARCHAR(3),
+        quantity TEXT,
+        paid INTEGER,
+        UNIQUE (symbol, ex_date)
+    );
+    INSERT INTO stock VALUES ('KO', 'Coca-Cola', '60.5', '50.25', 1, 'USD', '10', 1);
+    INSERT INTO stock_transaction VALUES (1, 'KO', 'buy', '10', '50.25', '0', 'USD', '2020-01-02', '0');
+    INSERT INTO cash_movement VALUES (1, 'USD', '1000', 'deposit', '2020-01-01', '', NULL);
+    INSERT INTO dividend VALUES (1, 'KO', '0.41', '2020-03-13', '2020-04-01', 'USD', '10', 0);";
+
+#[cfg(test)]
+fn get_connection_with_schema(schema: &str) -> DbConn {
+    let manager = r2d2_sqlite::SqliteConnectionManager::memory();
+    let pool = r2d2::Pool::builder().max_size(1).build(manager).unwrap();
+    let db_conn = pool.get().unwrap();
+    db_conn.execute_batch(schema).unwrap();
+    db_conn
+}
+
+#[test]
+fn test_migrate_baseline_storage() {
+    let db_conn = get_connection_with_schema(BASELINE_SCHEMA);
+    super::init_storage(&db_conn).unwrap();
+    super::init_storage(&db_conn).unwrap();
+
+    let stock = super::get_stored_stocks(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap().remove(0);
+    assert_eq!("Coca-Cola", stock.name);
+    assert_eq!(Decimal::new(6050, 2), stock.price);
+    assert_eq!(Decimal::new(5025, 2), stock.initial_price);
+    assert_eq!("NYSE", stock.market.symbol);
+    assert_eq!("", stock.market.currency);
+    assert_eq!("", stock.currency);
+    assert_eq!(Decimal::default(), stock.quantity);
+    assert!(!stock.drip);
+    assert_eq!("", stock.sector);
+    assert!(stock.tags.is_empty());
+
+    let price_type: String = db_conn.query_row("SELECT typeof(price) FROM stock", NO_PARAMS, |row| row.get(0)).unwrap();
+    assert_eq!("text", price_type);
+
+    super::update_market(&db_conn, &super::market::Market { id: 1, symbol: "NYSE".into(), currency: "USD".into() }).unwrap();
+    assert_eq!("USD", super::get_stored_markets(&db_conn).unwrap()[0].currency);
+    assert_eq!("USD", super::get_stored_stocks(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap()[0].currency());
+}
+
+#[test]
+fn test_migrate_single_portfolio_storage() {
+    let db_conn = get_connection_with_schema(SINGLE_PORTFOLIO_SCHEMA);
+    super::init_storage(&db_conn).unwrap();
+
+    let stock = super::get_stored_stocks(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap().remove(0);
+    assert_eq!(Decimal::from(10), stock.quantity);
+    assert!(stock.drip);
+    assert_eq!(1, super::get_transactions(&db_conn, DEFAULT_PORTFOLIO_ID, Some("KO")).unwrap().len());
+    assert_eq!(1, super::get_cash_movements(&db_conn, DEFAULT_PORTFOLIO_ID).unwrap().len());
+    assert_eq!(1, super::get_dividends(&db_conn, DEFAULT_PORTFOLIO_ID, Some("KO")).unwrap().len());
+
+    // The same symbol can now be held by another portfolio
+    super::add_portfolio(&db_conn, "ira").unwrap();
+    super::add_stock(&db_conn, 2, &stock).unwrap();
+    assert_eq!(vec![stock], super::get_stored_stocks(&db_conn, 2).unwrap());
+    assert!(super::get_transactions(&db_conn, 2, None).unwrap().is_empty());
+}
+
+#[test]
+fn test_migrate_newer_storage() {
+    let db_conn = get_connection_with_schema(&format!("PRAGMA user_version = {}", SCHEMA_VERSION + 1));
+    assert!(matches!(super::init_storage(&db_conn), Err(PersistanceError::UnsupportedSchemaVersion(_))));
+}
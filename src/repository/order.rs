@@ -0,0 +1,211 @@
+pub mod order_db;
+
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use super::transaction::TransactionKind;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderType {
+    // Fills at the next quote
+    #[default]
+    Market,
+    // Fills at the limit price or better
+    Limit,
+    // Becomes a market order once the quote reaches the stop price
+    Stop,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeInForce {
+    // Expires if it isn't filled the day it was placed
+    #[default]
+    Day,
+    // Good till canceled
+    Gtc,
+    // Immediate or cancel: expires if the first quote doesn't fill it
+    Ioc,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderStatus {
+    #[default]
+    Open,
+    Filled,
+    Canceled,
+    Expired,
+    // The portfolio didn't have the cash or the shares when it was filled
+    Rejected,
+}
+
+// Simulated order on a stock of a portfolio, filled against the quotes of the
+// price updates. Fills are recorded in the ledger like any other trade.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Order {
+    #[serde(default)]
+    pub id: u32,
+    #[serde(default)]
+    pub portfolio_id: u32,
+    pub symbol: String,
+    pub side: TransactionKind,
+    #[serde(default)]
+    pub order_type: OrderType,
+    #[serde(default)]
+    pub limit_price: Option<Decimal>,
+    #[serde(default)]
+    pub stop_price: Option<Decimal>,
+    pub quantity: Decimal,
+    #[serde(default)]
+    pub time_in_force: TimeInForce,
+    #[serde(default = "crate::repository::today")]
+    pub date: NaiveDate,
+    #[serde(default)]
+    pub status: OrderStatus,
+    #[serde(default)]
+    pub fill_price: Option<Decimal>,
+    #[serde(default)]
+    pub filled_on: Option<NaiveDate>,
+}
+
+impl OrderType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Market => "market",
+            Self::Limit => "limit",
+            Self::Stop => "stop",
+        }
+    }
+}
+
+impl From<&str> for OrderType {
+    fn from(order_type: &str) -> Self {
+        match order_type {
+            "limit" => Self::Limit,
+            "stop" => Self::Stop,
+            _ => Self::Market,
+        }
+    }
+}
+
+impl TimeInForce {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Gtc => "gtc",
+            Self::Ioc => "ioc",
+        }
+    }
+}
+
+impl From<&str> for TimeInForce {
+    fn from(time_in_force: &str) -> Self {
+        match time_in_force {
+            "gtc" => Self::Gtc,
+            "ioc" => Self::Ioc,
+            _ => Self::Day,
+        }
+    }
+}
+
+impl OrderStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Filled => "filled",
+            Self::Canceled => "canceled",
+            Self::Expired => "expired",
+            Self::Rejected => "rejected",
+        }
+    }
+}
+
+impl From<&str> for OrderStatus {
+    fn from(status: &str) -> Self {
+        match status {
+            "filled" => Self::Filled,
+            "canceled" => Self::Canceled,
+            "expired" => Self::Expired,
+            "rejected" => Self::Rejected,
+            _ => Self::Open,
+        }
+    }
+}
+
+impl From<&str> for Order {
+    fn from(json: &str) -> Self {
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+}
+
+impl Order {
+    pub fn is_valid(&self) -> bool {
+        let positive = |price: Option<Decimal>| price.map(|price| price > Decimal::zero()).unwrap_or(false);
+
+        self.quantity > Decimal::zero() && match self.order_type {
+            OrderType::Market => true,
+            OrderType::Limit => positive(self.limit_price),
+            OrderType::Stop => positive(self.stop_price),
+        }
+    }
+
+    // Whether a day order was placed before the date of the quote
+    pub fn is_expired(&self, date: NaiveDate) -> bool {
+        self.time_in_force == TimeInForce::Day && self.date < date
+    }
+
+    // Price the order fills at against a quote, if it does. Slippage, in percent,
+    // makes buys pay more and sells get less, but never past the limit.
+    pub fn fill_price(&self, price: Decimal, slippage: Decimal) -> Option<Decimal> {
+        let slipped = match self.side {
+            TransactionKind::Buy => price * (Decimal::from(100) + slippage) / Decimal::from(100),
+            TransactionKind::Sell => price * (Decimal::from(100) - slippage) / Decimal::from(100),
+        }.round_dp(4);
+
+        match (self.order_type, self.side) {
+            (OrderType::Market, _) => Some(slipped),
+            (OrderType::Limit, TransactionKind::Buy) => self.limit_price
+                .filter(|limit| price <= *limit)
+                .map(|limit| slipped.min(limit)),
+            (OrderType::Limit, TransactionKind::Sell) => self.limit_price
+                .filter(|limit| price >= *limit)
+                .map(|limit| slipped.max(limit)),
+            (OrderType::Stop, TransactionKind::Buy) => self.stop_price
+                .filter(|stop| price >= *stop)
+                .map(|_| slipped),
+            (OrderType::Stop, TransactionKind::Sell) => self.stop_price
+                .filter(|stop| price <= *stop)
+                .map(|_| slipped),
+        }
+    }
+}
+
+#[test]
+fn test_order_fill_price() {
+    let order = Order {
+        symbol: "AAPL".into(),
+        side: TransactionKind::Buy,
+        quantity: Decimal::from(10),
+        ..Order::default()
+    };
+    assert_eq!(Some(Decimal::new(1001, 1)), order.fill_price(Decimal::from(100), Decimal::new(1, 1)));
+
+    // Limits cap the slippage and wait for the price
+    let limit = Order { order_type: OrderType::Limit, limit_price: Some(Decimal::from(100)), ..order.clone() };
+    assert_eq!(None, limit.fill_price(Decimal::new(1001, 1), Decimal::zero()));
+    assert_eq!(Some(Decimal::from(100)), limit.fill_price(Decimal::from(100), Decimal::new(1, 1)));
+    let limit = Order { side: TransactionKind::Sell, ..limit };
+    assert_eq!(None, limit.fill_price(Decimal::from(99), Decimal::zero()));
+    assert_eq!(Some(Decimal::new(101898, 3)), limit.fill_price(Decimal::from(102), Decimal::new(1, 1)));
+
+    // Stops fill past the stop price
+    let stop = Order { side: TransactionKind::Sell, order_type: OrderType::Stop, stop_price: Some(Decimal::from(90)), ..order.clone() };
+    assert_eq!(None, stop.fill_price(Decimal::from(91), Decimal::zero()));
+    assert_eq!(Some(Decimal::from(89)), stop.fill_price(Decimal::from(89), Decimal::zero()));
+
+    assert!(!Order { order_type: OrderType::Limit, ..order.clone() }.is_valid());
+    assert!(order.is_expired(order.date.succ_opt().unwrap()));
+    assert!(!Order { time_in_force: TimeInForce::Gtc, ..order.clone() }.is_expired(order.date.succ_opt().unwrap()));
+}
@@ -0,0 +1,183 @@
+use crate::repository::{
+    error::PersistanceError
+};
+use super::{Order, OrderType, TimeInForce, OrderStatus};
+use crate::repository::transaction::TransactionKind;
+use r2d2_sqlite::rusqlite::{
+    params,
+    Row,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+const SELECT_ORDER: &str = r"
+    SELECT id, portfolio_id, symbol, side, order_type, limit_price, stop_price, quantity,
+            time_in_force, date, status, fill_price, filled_on
+        FROM paper_order";
+
+impl From<&Row<'_>> for Order {
+    fn from(row: &Row) -> Self {
+        Order {
+            id: row.get_unwrap(0),
+            portfolio_id: row.get_unwrap(1),
+            symbol: row.get_unwrap(2),
+            side: TransactionKind::from(row.get_unwrap::<_, String>(3).as_str()),
+            order_type: OrderType::from(row.get_unwrap::<_, String>(4).as_str()),
+            limit_price: row.get_unwrap(5),
+            stop_price: row.get_unwrap(6),
+            quantity: row.get_unwrap(7),
+            time_in_force: TimeInForce::from(row.get_unwrap::<_, String>(8).as_str()),
+            date: row.get_unwrap::<_, String>(9).parse().unwrap(),
+            status: OrderStatus::from(row.get_unwrap::<_, String>(10).as_str()),
+            fill_price: row.get_unwrap(11),
+            filled_on: row.get_unwrap::<_, Option<String>>(12).map(|date| date.parse().unwrap()),
+        }
+    }
+}
+
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS paper_order (
+            id INTEGER PRIMARY KEY,
+            portfolio_id INTEGER REFERENCES portfolio(id),
+            symbol VARCHAR(4),
+            side VARCHAR(8),
+            order_type VARCHAR(8),
+            limit_price TEXT,
+            stop_price TEXT,
+            quantity TEXT,
+            time_in_force VARCHAR(8),
+            date TEXT,
+            status VARCHAR(8),
+            fill_price TEXT,
+            filled_on TEXT
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+pub fn add(db: &DbConn, portfolio_id: u32, order: &Order) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT INTO
+            paper_order (portfolio_id, symbol, side, order_type, limit_price, stop_price, quantity, time_in_force, date, status)
+            values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10);",
+        params![
+            portfolio_id,
+            order.symbol,
+            order.side.as_str(),
+            order.order_type.as_str(),
+            order.limit_price,
+            order.stop_price,
+            order.quantity,
+            order.time_in_force.as_str(),
+            order.date.to_string(),
+            OrderStatus::Open.as_str()]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+// Stores the status of an order and what it was filled at
+pub fn update_status(db: &DbConn, order: &Order) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "UPDATE paper_order SET status = ?2, fill_price = ?3, filled_on = ?4 WHERE id = ?1",
+        params![
+            order.id,
+            order.status.as_str(),
+            order.fill_price,
+            order.filled_on.map(|date| date.to_string())]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+// Returns whether the portfolio had an open order with the id
+pub fn cancel(db: &DbConn, portfolio_id: u32, id: u32) -> Result<bool, PersistanceError> {
+    let result = db.execute(
+        "UPDATE paper_order SET status = ?3 WHERE id = ?1 AND portfolio_id = ?2 AND status = ?4",
+        params![id, portfolio_id, OrderStatus::Canceled.as_str(), OrderStatus::Open.as_str()]);
+
+    match result {
+        Ok(updated) => Ok(updated > 0),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+// Cancels the open orders of the portfolio on a symbol
+pub fn cancel_by_symbol(db: &DbConn, portfolio_id: u32, symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "UPDATE paper_order SET status = ?3 WHERE portfolio_id = ?1 AND symbol = ?2 AND status = ?4",
+        params![portfolio_id, symbol, OrderStatus::Canceled.as_str(), OrderStatus::Open.as_str()]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+pub fn rename_symbol(db: &DbConn, portfolio_id: u32, symbol: &str, new_symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "UPDATE paper_order SET symbol = ?1 WHERE portfolio_id = ?2 AND symbol = ?3",
+        params![new_symbol, portfolio_id, symbol]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+pub fn delete_all(db: &DbConn, portfolio_id: u32) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "DELETE FROM paper_order WHERE portfolio_id = ?1",
+        params![portfolio_id]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+// Returns the orders of the portfolio, only the open ones unless `all`, oldest first
+pub fn get_all(db: &DbConn, portfolio_id: u32, all: bool) -> Result<Vec<Order>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE portfolio_id = ?1 AND (?2 OR status = ?3) ORDER BY id", SELECT_ORDER)).unwrap();
+
+    let items = query.query_map(
+        params![portfolio_id, all, OrderStatus::Open.as_str()],
+        |row| Ok(Order::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+// Returns the open orders on a symbol of every portfolio, oldest first
+pub fn get_open_by_symbol(db: &DbConn, symbol: &str) -> Result<Vec<Order>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE symbol = ?1 AND status = ?2 ORDER BY id", SELECT_ORDER)).unwrap();
+
+    let items = query.query_map(
+        params![symbol, OrderStatus::Open.as_str()],
+        |row| Ok(Order::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+pub fn get_open_symbols(db: &DbConn) -> Result<Vec<String>, PersistanceError> {
+    let mut query = db.prepare("SELECT DISTINCT symbol FROM paper_order WHERE status = ?1 ORDER BY symbol").unwrap();
+
+    let items = query.query_map(
+        params![OrderStatus::Open.as_str()],
+        |row| row.get(0))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
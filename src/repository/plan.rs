@@ -0,0 +1,200 @@
+pub mod plan_db;
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+
+// Next contributions of every plan shown by the report
+pub const UPCOMING_CONTRIBUTIONS: usize = 3;
+
+// Amount invested in a stock on a day of every month, e.g. 200 EUR into VWCE every
+// 1st. Days past the end of a month fall on its last day.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct InvestmentPlan {
+    #[serde(default)]
+    pub id: u32,
+    pub symbol: String,
+    // In the currency of the stock
+    pub amount: Decimal,
+    #[serde(default = "InvestmentPlan::default_day")]
+    pub day: u32,
+    #[serde(default = "crate::repository::today")]
+    pub start: NaiveDate,
+    #[serde(default)]
+    pub end: Option<NaiveDate>,
+    // Deposit the amount before buying, for money coming from outside the portfolio
+    #[serde(default)]
+    pub deposit: bool,
+    // Filled with the currency of the stock when stored
+    #[serde(default)]
+    pub currency: String,
+    // Last day of the schedule a contribution was created for
+    #[serde(default)]
+    pub last_scheduled: Option<NaiveDate>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ContributionStatus {
+    // Waiting for a price on or after its day
+    #[default]
+    Pending,
+    Executed,
+    // The portfolio didn't have the cash, or the amount didn't buy anything
+    Failed,
+}
+
+// Contribution of a plan, scheduled on a day and made on the first trading day since
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Contribution {
+    #[serde(default)]
+    pub id: u32,
+    pub plan_id: u32,
+    pub symbol: String,
+    pub amount: Decimal,
+    pub currency: String,
+    pub scheduled: NaiveDate,
+    pub date: NaiveDate,
+    pub status: ContributionStatus,
+    pub quantity: Option<Decimal>,
+    pub price: Option<Decimal>,
+    // Day of the price it was bought at
+    pub executed_on: Option<NaiveDate>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct PlanTotal {
+    pub plan_id: u32,
+    pub symbol: String,
+    pub currency: String,
+    pub contributions: usize,
+    pub invested: Decimal,
+    pub quantity: Decimal,
+    pub average_price: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct PlanReport {
+    // Next contributions of every plan, by date
+    pub upcoming: Vec<Contribution>,
+    // Contributions made or waiting for a price, oldest first
+    pub contributions: Vec<Contribution>,
+    // Executed contributions of every plan
+    pub totals: Vec<PlanTotal>,
+}
+
+impl ContributionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Executed => "executed",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+impl From<&str> for ContributionStatus {
+    fn from(status: &str) -> Self {
+        match status {
+            "executed" => Self::Executed,
+            "failed" => Self::Failed,
+            _ => Self::Pending,
+        }
+    }
+}
+
+impl From<&str> for InvestmentPlan {
+    fn from(json: &str) -> Self {
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+}
+
+impl InvestmentPlan {
+    fn default_day() -> u32 {
+        1
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.amount > Decimal::zero()
+            && (1..=31).contains(&self.day)
+            && self.end.map(|end| end >= self.start).unwrap_or(true)
+    }
+
+    // Day of the schedule in a month
+    fn scheduled_on(&self, year: i32, month: u32) -> NaiveDate {
+        (1..=self.day).rev()
+            .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+            .unwrap()
+    }
+
+    // Days of the schedule from the start to the end, if any
+    fn dates(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        let months = (self.start.year() * 12 + self.start.month0() as i32)..;
+
+        months
+            .map(move |month| self.scheduled_on(month.div_euclid(12), month.rem_euclid(12) as u32 + 1))
+            .filter(move |date| *date >= self.start)
+            .take_while(move |date| self.end.map(|end| *date <= end).unwrap_or(true))
+    }
+
+    // Days of the schedule up to `date` that don't have a contribution yet
+    pub fn due(&self, date: NaiveDate) -> Vec<NaiveDate> {
+        self.dates()
+            .filter(|scheduled| Some(*scheduled) > self.last_scheduled)
+            .take_while(|scheduled| *scheduled <= date)
+            .collect()
+    }
+
+    // Next days of the schedule after `date`
+    pub fn upcoming(&self, date: NaiveDate, count: usize) -> Vec<NaiveDate> {
+        self.dates()
+            .filter(|scheduled| *scheduled > date && Some(*scheduled) > self.last_scheduled)
+            .take(count)
+            .collect()
+    }
+}
+
+// Adds up the executed contributions of every plan
+pub fn totals(plans: &[InvestmentPlan], contributions: &[Contribution]) -> Vec<PlanTotal> {
+    plans.iter()
+        .map(|plan| {
+            let executed: Vec<&Contribution> = contributions.iter()
+                .filter(|contribution| contribution.plan_id == plan.id && contribution.status == ContributionStatus::Executed)
+                .collect();
+            let invested: Decimal = executed.iter()
+                .map(|contribution| contribution.quantity.unwrap_or_default() * contribution.price.unwrap_or_default())
+                .sum();
+            let quantity: Decimal = executed.iter().map(|contribution| contribution.quantity.unwrap_or_default()).sum();
+
+            PlanTotal {
+                plan_id: plan.id,
+                symbol: plan.symbol.clone(),
+                currency: plan.currency.clone(),
+                contributions: executed.len(),
+                invested,
+                quantity,
+                average_price: invested.checked_div(quantity).map(|price| price.round_dp(4)),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_plan_schedule() {
+    let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    let mut plan = InvestmentPlan {
+        symbol: "VWCE".into(),
+        amount: Decimal::from(200),
+        day: 31,
+        start: date(2020, 1, 15),
+        end: Some(date(2020, 12, 31)),
+        ..InvestmentPlan::default()
+    };
+
+    // Short months get their last day
+    assert_eq!(vec![date(2020, 1, 31), date(2020, 2, 29), date(2020, 3, 31)], plan.due(date(2020, 4, 29)));
+    plan.last_scheduled = Some(date(2020, 2, 29));
+    assert_eq!(vec![date(2020, 3, 31)], plan.due(date(2020, 4, 29)));
+    assert_eq!(vec![date(2020, 11, 30), date(2020, 12, 31)], plan.upcoming(date(2020, 11, 1), 3));
+    assert!(!InvestmentPlan { day: 0, ..plan }.is_valid());
+}
@@ -0,0 +1,252 @@
+use chrono::NaiveDate;
+use crate::repository::{
+    error::PersistanceError
+};
+use super::{InvestmentPlan, Contribution, ContributionStatus};
+use r2d2_sqlite::rusqlite::{
+    params,
+    Row,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+const SELECT_PLAN: &str = r"
+    SELECT id, symbol, amount, day, start, end, deposit, currency, last_scheduled
+        FROM investment_plan";
+
+const SELECT_CONTRIBUTION: &str = r"
+    SELECT id, plan_id, symbol, amount, currency, scheduled, date, status, quantity, price, executed_on
+        FROM plan_contribution";
+
+fn parse_date(date: Option<String>) -> Option<NaiveDate> {
+    date.map(|date| date.parse().unwrap())
+}
+
+impl From<&Row<'_>> for InvestmentPlan {
+    fn from(row: &Row) -> Self {
+        InvestmentPlan {
+            id: row.get_unwrap(0),
+            symbol: row.get_unwrap(1),
+            amount: row.get_unwrap(2),
+            day: row.get_unwrap(3),
+            start: row.get_unwrap::<_, String>(4).parse().unwrap(),
+            end: parse_date(row.get_unwrap(5)),
+            deposit: row.get_unwrap(6),
+            currency: row.get_unwrap(7),
+            last_scheduled: parse_date(row.get_unwrap(8)),
+        }
+    }
+}
+
+impl From<&Row<'_>> for Contribution {
+    fn from(row: &Row) -> Self {
+        Contribution {
+            id: row.get_unwrap(0),
+            plan_id: row.get_unwrap(1),
+            symbol: row.get_unwrap(2),
+            amount: row.get_unwrap(3),
+            currency: row.get_unwrap(4),
+            scheduled: row.get_unwrap::<_, String>(5).parse().unwrap(),
+            date: row.get_unwrap::<_, String>(6).parse().unwrap(),
+            status: ContributionStatus::from(row.get_unwrap::<_, String>(7).as_str()),
+            quantity: row.get_unwrap(8),
+            price: row.get_unwrap(9),
+            executed_on: parse_date(row.get_unwrap(10)),
+        }
+    }
+}
+
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS investment_plan (
+            id INTEGER PRIMARY KEY,
+            portfolio_id INTEGER REFERENCES portfolio(id),
+            symbol VARCHAR(4),
+            amount TEXT,
+            day INTEGER,
+            start TEXT,
+            end TEXT,
+            deposit BOOLEAN,
+            currency VARCHAR(3),
+            last_scheduled TEXT
+        )", NO_PARAMS)
+        .map_err(PersistanceError::InitializationError)?;
+
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS plan_contribution (
+            id INTEGER PRIMARY KEY,
+            portfolio_id INTEGER REFERENCES portfolio(id),
+            plan_id INTEGER,
+            symbol VARCHAR(4),
+            amount TEXT,
+            currency VARCHAR(3),
+            scheduled TEXT,
+            date TEXT,
+            status VARCHAR(8),
+            quantity TEXT,
+            price TEXT,
+            executed_on TEXT
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+pub fn add(db: &DbConn, portfolio_id: u32, plan: &InvestmentPlan) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT INTO
+            investment_plan (portfolio_id, symbol, amount, day, start, end, deposit, currency, last_scheduled)
+            values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);",
+        params![
+            portfolio_id,
+            plan.symbol,
+            plan.amount,
+            plan.day,
+            plan.start.to_string(),
+            plan.end.map(|date| date.to_string()),
+            plan.deposit,
+            plan.currency,
+            plan.last_scheduled.map(|date| date.to_string())]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+pub fn set_last_scheduled(db: &DbConn, id: u32, date: NaiveDate) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "UPDATE investment_plan SET last_scheduled = ?2 WHERE id = ?1",
+        params![id, date.to_string()]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+// Deletes the plan along with its pending contributions. Returns whether the
+// portfolio had a plan with the id.
+pub fn delete(db: &DbConn, portfolio_id: u32, id: u32) -> Result<bool, PersistanceError> {
+    let result = db.execute(
+        "DELETE FROM investment_plan WHERE id = ?1 AND portfolio_id = ?2",
+        params![id, portfolio_id])
+        .and_then(|deleted| db.execute(
+            "DELETE FROM plan_contribution WHERE plan_id = ?1 AND status = ?2",
+            params![id, ContributionStatus::Pending.as_str()])
+            .map(|_| deleted));
+
+    match result {
+        Ok(deleted) => Ok(deleted > 0),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+// Deletes the plans of the portfolio on a symbol along with their pending contributions
+pub fn delete_by_symbol(db: &DbConn, portfolio_id: u32, symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "DELETE FROM investment_plan WHERE portfolio_id = ?1 AND symbol = ?2",
+        params![portfolio_id, symbol])
+        .and_then(|_| db.execute(
+            "DELETE FROM plan_contribution WHERE portfolio_id = ?1 AND symbol = ?2 AND status = ?3",
+            params![portfolio_id, symbol, ContributionStatus::Pending.as_str()]));
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+// Renames the plans on a symbol and their contributions
+pub fn rename_symbol(db: &DbConn, portfolio_id: u32, symbol: &str, new_symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "UPDATE investment_plan SET symbol = ?1 WHERE portfolio_id = ?2 AND symbol = ?3",
+        params![new_symbol, portfolio_id, symbol])
+        .and_then(|_| db.execute(
+            "UPDATE plan_contribution SET symbol = ?1 WHERE portfolio_id = ?2 AND symbol = ?3",
+            params![new_symbol, portfolio_id, symbol]));
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+pub fn delete_all(db: &DbConn, portfolio_id: u32) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "DELETE FROM investment_plan WHERE portfolio_id = ?1",
+        params![portfolio_id])
+        .and_then(|_| db.execute(
+            "DELETE FROM plan_contribution WHERE portfolio_id = ?1",
+            params![portfolio_id]));
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+pub fn get_all(db: &DbConn, portfolio_id: u32) -> Result<Vec<InvestmentPlan>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE portfolio_id = ?1 ORDER BY id", SELECT_PLAN)).unwrap();
+
+    let items = query.query_map(
+        params![portfolio_id],
+        |row| Ok(InvestmentPlan::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+pub fn add_contribution(db: &DbConn, portfolio_id: u32, contribution: &Contribution) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT INTO
+            plan_contribution (portfolio_id, plan_id, symbol, amount, currency, scheduled, date, status)
+            values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8);",
+        params![
+            portfolio_id,
+            contribution.plan_id,
+            contribution.symbol,
+            contribution.amount,
+            contribution.currency,
+            contribution.scheduled.to_string(),
+            contribution.date.to_string(),
+            contribution.status.as_str()]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+// Stores the status of a contribution and what it bought
+pub fn update_contribution(db: &DbConn, contribution: &Contribution) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "UPDATE plan_contribution SET status = ?2, quantity = ?3, price = ?4, executed_on = ?5 WHERE id = ?1",
+        params![
+            contribution.id,
+            contribution.status.as_str(),
+            contribution.quantity,
+            contribution.price,
+            contribution.executed_on.map(|date| date.to_string())]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+// Returns the contributions of the portfolio, only the ones with the status if given,
+// oldest first
+pub fn get_contributions(db: &DbConn, portfolio_id: u32, status: Option<ContributionStatus>) -> Result<Vec<Contribution>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE portfolio_id = ?1 AND (?2 IS NULL OR status = ?2) ORDER BY date, id", SELECT_CONTRIBUTION)).unwrap();
+
+    let items = query.query_map(
+        params![portfolio_id, status.map(|status| status.as_str())],
+        |row| Ok(Contribution::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
@@ -0,0 +1,14 @@
+pub mod portfolio_db;
+
+use serde::{Serialize, Deserialize};
+
+// Portfolio used by the connections that haven't selected another one
+pub const DEFAULT_PORTFOLIO_ID: u32 = 1;
+pub const DEFAULT_PORTFOLIO_NAME: &str = "default";
+
+// Named set of holdings, cash and ledger, e.g. a retirement or a paper account
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Portfolio {
+    pub id: u32,
+    pub name: String,
+}
@@ -0,0 +1,107 @@
+use crate::repository::{
+    error::PersistanceError
+};
+use super::{Portfolio, DEFAULT_PORTFOLIO_ID, DEFAULT_PORTFOLIO_NAME};
+use r2d2_sqlite::rusqlite::{
+    params,
+    OptionalExtension,
+    Row,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+impl From<&Row<'_>> for Portfolio {
+    fn from(row: &Row) -> Self {
+        Portfolio {
+            id: row.get_unwrap(0),
+            name: row.get_unwrap(1),
+        }
+    }
+}
+
+// Creates the table along with the default portfolio
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS portfolio (
+            id INTEGER PRIMARY KEY,
+            name VARCHAR(64) UNIQUE
+        )", NO_PARAMS)
+        .map_err(PersistanceError::InitializationError)?;
+
+    db.execute(
+        "INSERT OR IGNORE INTO portfolio (id, name) values (?1, ?2)",
+        params![DEFAULT_PORTFOLIO_ID, DEFAULT_PORTFOLIO_NAME])
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+pub fn add(db: &DbConn, name: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT INTO 
+            portfolio (name) 
+            values (?1);",
+        params![name]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+pub fn rename(db: &DbConn, id: u32, name: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(r"
+        UPDATE portfolio 
+            SET name = ?1
+            WHERE id = ?2",
+        params![name, id]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+pub fn delete(db: &DbConn, id: u32) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"DELETE FROM portfolio 
+            WHERE id = ?1;", 
+        params![id]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+pub fn get(db: &DbConn, id: u32) -> Result<Option<Portfolio>, PersistanceError> {
+    let result = db.query_row(
+        "SELECT id, name FROM portfolio WHERE id = ?1", 
+        params![id], 
+        |row| Ok(Portfolio::from(row)))
+        .optional();
+
+    result.map_err(PersistanceError::CouldNotInsert)
+}
+
+pub fn get_by_name(db: &DbConn, name: &str) -> Result<Option<Portfolio>, PersistanceError> {
+    let result = db.query_row(
+        "SELECT id, name FROM portfolio WHERE name = ?1", 
+        params![name], 
+        |row| Ok(Portfolio::from(row)))
+        .optional();
+
+    result.map_err(PersistanceError::CouldNotInsert)
+}
+
+pub fn get_all(db: &DbConn) -> Result<Vec<Portfolio>, PersistanceError> {
+    let mut query = db.prepare("SELECT id, name FROM portfolio ORDER BY id").unwrap();
+
+    let items = query.query_map(
+        NO_PARAMS, 
+        |row| Ok(Portfolio::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
@@ -0,0 +1,64 @@
+pub mod price_history_db;
+
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use super::corporate_action::{self, CorporateAction};
+
+// Prices of a stock during a day
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct PriceBar {
+    pub symbol: String,
+    pub date: NaiveDate,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    #[serde(default)]
+    pub volume: Decimal,
+}
+
+impl PriceBar {
+    // Bar of a day from a single quote, or the stored bar extended with it
+    pub fn with_quote(bar: Option<PriceBar>, symbol: &str, date: NaiveDate, price: Decimal) -> PriceBar {
+        match bar {
+            Some(bar) => PriceBar {
+                high: bar.high.max(price),
+                low: bar.low.min(price),
+                close: price,
+                ..bar
+            },
+            None => PriceBar {
+                symbol: symbol.to_owned(),
+                date,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                volume: Decimal::zero(),
+            }
+        }
+    }
+}
+
+// Expresses the stored prices in shares of today, undoing the splits made after every bar
+pub fn adjust_for_splits(bars: Vec<PriceBar>, actions: &[CorporateAction]) -> Vec<PriceBar> {
+    bars.into_iter()
+        .map(|bar| {
+            let factor = corporate_action::split_factor(actions, bar.date, NaiveDate::MAX);
+
+            if factor == Decimal::from(1) {
+                return bar;
+            }
+
+            PriceBar {
+                open: bar.open / factor,
+                high: bar.high / factor,
+                low: bar.low / factor,
+                close: bar.close / factor,
+                volume: bar.volume * factor,
+                ..bar
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,127 @@
+use crate::repository::{
+    error::PersistanceError
+};
+use super::PriceBar;
+use chrono::NaiveDate;
+use r2d2_sqlite::rusqlite::{
+    params,
+    OptionalExtension,
+    Row,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+const SELECT_BAR: &str = r"
+    SELECT symbol, date, open, high, low, close, volume
+        FROM price_history";
+
+impl From<&Row<'_>> for PriceBar {
+    fn from(row: &Row) -> Self {
+        PriceBar {
+            symbol: row.get_unwrap(0),
+            date: row.get_unwrap::<_, String>(1).parse().unwrap(),
+            open: row.get_unwrap(2),
+            high: row.get_unwrap(3),
+            low: row.get_unwrap(4),
+            close: row.get_unwrap(5),
+            volume: row.get_unwrap(6),
+        }
+    }
+}
+
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS price_history (
+            symbol VARCHAR(4),
+            date TEXT,
+            open TEXT,
+            high TEXT,
+            low TEXT,
+            close TEXT,
+            volume TEXT,
+            PRIMARY KEY (symbol, date)
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+// Stores the bar of a day, replacing the one already stored for that day
+pub fn add(db: &DbConn, bar: &PriceBar) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT OR REPLACE INTO 
+            price_history (symbol, date, open, high, low, close, volume) 
+            values (?1, ?2, ?3, ?4, ?5, ?6, ?7);",
+        params![
+            bar.symbol,
+            bar.date.to_string(),
+            bar.open,
+            bar.high,
+            bar.low,
+            bar.close,
+            bar.volume]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+pub fn get(db: &DbConn, symbol: &str, date: NaiveDate) -> Result<Option<PriceBar>, PersistanceError> {
+    let result = db.query_row(
+        &format!("{} WHERE symbol = ?1 AND date = ?2", SELECT_BAR), 
+        params![symbol, date.to_string()], 
+        |row| Ok(PriceBar::from(row)))
+        .optional();
+
+    result.map_err(PersistanceError::CouldNotInsert)
+}
+
+// Returns the bars of a stock, oldest first
+pub fn get_by_symbol(db: &DbConn, symbol: &str) -> Result<Vec<PriceBar>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE symbol = ?1 ORDER BY date", SELECT_BAR)).unwrap();
+
+    let items = query.query_map(
+        params![symbol], 
+        |row| Ok(PriceBar::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+// Copies the bars of a stock to a new symbol. The originals are kept since other
+// portfolios may still hold the stock under its previous symbol.
+pub fn copy_symbol(db: &DbConn, symbol: &str, new_symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT OR IGNORE INTO price_history (symbol, date, open, high, low, close, volume)
+            SELECT ?1, date, open, high, low, close, volume FROM price_history WHERE symbol = ?2",
+        params![new_symbol, symbol]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+// Returns the most recent bar of a stock
+pub fn get_latest(db: &DbConn, symbol: &str) -> Result<Option<PriceBar>, PersistanceError> {
+    let result = db.query_row(
+        &format!("{} WHERE symbol = ?1 ORDER BY date DESC LIMIT 1", SELECT_BAR), 
+        params![symbol], 
+        |row| Ok(PriceBar::from(row)))
+        .optional();
+
+    result.map_err(PersistanceError::CouldNotInsert)
+}
+
+// Returns the most recent bar of a stock before the date
+pub fn get_latest_before(db: &DbConn, symbol: &str, date: NaiveDate) -> Result<Option<PriceBar>, PersistanceError> {
+    let result = db.query_row(
+        &format!("{} WHERE symbol = ?1 AND date < ?2 ORDER BY date DESC LIMIT 1", SELECT_BAR), 
+        params![symbol, date.to_string()], 
+        |row| Ok(PriceBar::from(row)))
+        .optional();
+
+    result.map_err(PersistanceError::CouldNotInsert)
+}
@@ -0,0 +1,85 @@
+pub mod profile_db;
+
+use chrono::{Duration, NaiveDateTime};
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use super::stock::stock_api::{ProfileElement, KeyMetricsElement};
+
+// How long a fetched profile is used before asking the API again
+pub const PROFILE_TTL_HOURS: i64 = 24;
+
+// Company data and fundamentals of a stock
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct CompanyProfile {
+    pub symbol: String,
+    pub name: String,
+    pub currency: String,
+    pub exchange: String,
+    pub sector: String,
+    pub industry: String,
+    pub country: String,
+    pub description: String,
+    pub price: Option<Decimal>,
+    pub market_cap: Option<Decimal>,
+    pub pe_ratio: Option<Decimal>,
+    pub beta: Option<Decimal>,
+    // Percentage of the price paid as dividends in a year
+    pub dividend_yield: Option<Decimal>,
+    pub fetched_at: NaiveDateTime,
+}
+
+impl CompanyProfile {
+    pub fn new(symbol: &str, profile: ProfileElement, metrics: Option<KeyMetricsElement>, fetched_at: NaiveDateTime) -> CompanyProfile {
+        let metrics = metrics.unwrap_or(KeyMetricsElement { pe_ratio: None, dividend_yield: None, market_cap: None });
+        // The metrics give the yield as a ratio, the last dividend is the fallback
+        let dividend_yield = metrics.dividend_yield
+            .map(|ratio| ratio * Decimal::from(100))
+            .or_else(|| match (profile.last_div, profile.price) {
+                (Some(dividend), Some(price)) => (dividend * Decimal::from(100)).checked_div(price),
+                _ => None,
+            })
+            .map(|pct| pct.round_dp(2));
+
+        CompanyProfile {
+            symbol: symbol.to_owned(),
+            name: profile.company_name.unwrap_or_default(),
+            currency: profile.currency.unwrap_or_default(),
+            exchange: profile.exchange_short_name.unwrap_or_default(),
+            sector: profile.sector.unwrap_or_default(),
+            industry: profile.industry.unwrap_or_default(),
+            country: profile.country.unwrap_or_default(),
+            description: profile.description.unwrap_or_default(),
+            price: profile.price,
+            market_cap: profile.mkt_cap.or(metrics.market_cap),
+            pe_ratio: metrics.pe_ratio,
+            beta: profile.beta,
+            dividend_yield,
+            fetched_at,
+        }
+    }
+
+    // Whether it was fetched recently enough to be used instead of asking the API
+    pub fn is_fresh(&self, now: NaiveDateTime) -> bool {
+        now - self.fetched_at < Duration::hours(PROFILE_TTL_HOURS)
+    }
+}
+
+#[test]
+fn test_profile_from_api() {
+    let profile: ProfileElement = serde_json::from_str(r#"{
+        "symbol": "KO", "companyName": "The Coca-Cola Company", "currency": "USD", "exchangeShortName": "NYSE",
+        "sector": "Consumer Defensive", "industry": "Beverages", "country": "US", "description": "Soft drinks",
+        "price": 50.0, "mktCap": 214000000000, "beta": 0.6, "lastDiv": 1.64
+    }"#).unwrap();
+    let metrics: KeyMetricsElement = serde_json::from_str(r#"{"peRatioTTM": 23.5, "dividendYieldTTM": null}"#).unwrap();
+    let fetched_at = NaiveDateTime::parse_from_str("2020-05-04 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+
+    let profile = CompanyProfile::new("KO", profile, Some(metrics), fetched_at);
+    assert_eq!("The Coca-Cola Company", profile.name);
+    assert_eq!(Some(Decimal::new(235, 1)), profile.pe_ratio);
+    assert_eq!(Some(Decimal::new(328, 2)), profile.dividend_yield);
+    assert_eq!(Some(Decimal::from(214_000_000_000i64)), profile.market_cap);
+
+    assert!(profile.is_fresh(fetched_at + Duration::hours(23)));
+    assert!(!profile.is_fresh(fetched_at + Duration::hours(PROFILE_TTL_HOURS)));
+}
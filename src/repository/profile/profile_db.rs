@@ -0,0 +1,62 @@
+use crate::repository::{
+    error::PersistanceError
+};
+use super::CompanyProfile;
+use r2d2_sqlite::rusqlite::{
+    params,
+    OptionalExtension,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+// The profiles are cached as JSON, so new fields don't need a new column
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS company_profile (
+            symbol VARCHAR(4) PRIMARY KEY,
+            profile TEXT,
+            fetched_at TEXT
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+// Stores a profile, replacing the one cached before
+pub fn set(db: &DbConn, profile: &CompanyProfile) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT OR REPLACE INTO 
+            company_profile (symbol, profile, fetched_at) 
+            values (?1, ?2, ?3);",
+        params![
+            profile.symbol,
+            serde_json::to_string(profile).unwrap(),
+            profile.fetched_at.to_string()]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+pub fn get(db: &DbConn, symbol: &str) -> Result<Option<CompanyProfile>, PersistanceError> {
+    let result = db.query_row(
+        "SELECT profile FROM company_profile WHERE symbol = ?1", 
+        params![symbol], 
+        |row| row.get::<_, String>(0))
+        .optional();
+
+    result
+        .map(|profile| profile.and_then(|profile| serde_json::from_str(&profile).ok()))
+        .map_err(PersistanceError::CouldNotInsert)
+}
+
+pub fn delete(db: &DbConn, symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "DELETE FROM company_profile WHERE symbol = ?1",
+        params![symbol]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
@@ -0,0 +1,10 @@
+pub mod setting_db;
+
+pub const BASE_CURRENCY: &str = "base_currency";
+pub const DEFAULT_BASE_CURRENCY: &str = "USD";
+// Annual percent the Sharpe and Sortino ratios are measured against
+pub const RISK_FREE_RATE: &str = "risk_free_rate";
+// Percent the paper orders fill worse than the quote
+pub const SLIPPAGE: &str = "slippage";
+// Paid on every paper fill, in the currency of the stock
+pub const COMMISSION: &str = "commission";
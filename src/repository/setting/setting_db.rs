@@ -0,0 +1,42 @@
+use crate::repository::{
+    error::PersistanceError
+};
+use r2d2_sqlite::rusqlite::{
+    params,
+    OptionalExtension,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS setting (
+            key VARCHAR(64) PRIMARY KEY,
+            value TEXT
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+pub fn set(db: &DbConn, key: &str, value: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT OR REPLACE INTO 
+            setting (key, value) 
+            values (?1, ?2);",
+        params![key, value]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+pub fn get(db: &DbConn, key: &str) -> Result<Option<String>, PersistanceError> {
+    let result = db.query_row(
+        "SELECT value FROM setting WHERE key = ?1", 
+        params![key], 
+        |row| row.get(0))
+        .optional();
+
+    result.map_err(PersistanceError::CouldNotInsert)
+}
@@ -4,14 +4,61 @@ pub mod stock_api;
 use log::debug;
 use stock_api::StockListElement;
 use serde::{Serialize, Deserialize};
+use super::market::Market;
+use crate::decimal::Decimal;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
 pub struct Stock {
     pub symbol: String,
     pub name: String,
-    pub price: f32,
-    pub initial_price: f32,
-    pub market: u16 // Usar id o posar struct Market?
+    pub price: Decimal,
+    pub initial_price: Decimal,
+    // Shares held, `initial_price` being their average cost
+    #[serde(default)]
+    pub quantity: Decimal,
+    pub market: Market,
+    // Overrides the currency of the market when not empty
+    #[serde(default)]
+    pub currency: String,
+    // Reinvest the dividends instead of keeping them as cash
+    #[serde(default)]
+    pub drip: bool,
+    // Filled from the company profile
+    #[serde(default)]
+    pub sector: String,
+    #[serde(default)]
+    pub industry: String,
+    #[serde(default)]
+    pub country: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Stock {
+    // Currency the stock is quoted in
+    pub fn currency(&self) -> &str {
+        if self.currency.is_empty() {
+            &self.market.currency
+        } else {
+            &self.currency
+        }
+    }
+
+    // Whether the value and the cost of the shares held fit in a decimal
+    pub fn is_valid(&self) -> bool {
+        self.price.checked_mul(self.quantity).is_some()
+            && self.initial_price.checked_mul(self.quantity).is_some()
+    }
+
+    // Value of the shares held at the current price
+    pub fn market_value(&self) -> Decimal {
+        self.price * self.quantity
+    }
+
+    // What the shares held cost
+    pub fn cost(&self) -> Decimal {
+        self.initial_price * self.quantity
+    }
 }
 
 impl From<&str> for Stock {
@@ -27,8 +74,15 @@ impl From<&StockListElement> for Stock {
             symbol: stock.symbol.to_owned(),
             price: stock.price,
             name: stock.name.to_owned(),
-            initial_price: 0.0,
-            market: 0,
+            initial_price: Decimal::zero(),
+            quantity: Decimal::zero(),
+            market: Market {
+                symbol: stock.exchange.to_owned(),
+                ..Market::default()
+            },
+            currency: String::new(),
+            drip: false,
+            ..Stock::default()
         }
     }
 }
\ No newline at end of file
@@ -5,11 +5,16 @@ use hyper::{
 };
 use serde_json;
 use serde::Deserialize;
+use log::debug;
 use crate::repository::HttpClient;
+use crate::decimal::Decimal;
 
 enum Endpoint {
     RealTimePrice(String),
     StockList,
+    HistoricalPrices(String),
+    Profile(String),
+    KeyMetrics(String),
 }
 
 impl Endpoint {
@@ -18,7 +23,10 @@ impl Endpoint {
     pub fn to_uri(&self) -> Uri {
         let route = match self {
             Self::RealTimePrice(args) => "api/v3/stock/real-time-price/".to_owned() + args,
-            Self::StockList => "api/v3/company/stock/list".into()
+            Self::StockList => "api/v3/company/stock/list".into(),
+            Self::HistoricalPrices(args) => "api/v3/historical-price-full/".to_owned() + args,
+            Self::Profile(args) => "api/v3/profile/".to_owned() + args,
+            Self::KeyMetrics(args) => "api/v3/key-metrics-ttm/".to_owned() + args,
         };
 
         let base = String::from(Self::BASE_URL);
@@ -32,13 +40,71 @@ pub struct StockListElement {
     pub symbol: String,
     #[serde(default)]
     pub name: String,
-    pub price: f32,
+    pub price: Decimal,
+    #[serde(default)]
+    pub exchange: String,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct StockPriceResponse {
     pub symbol: String,
-    pub price: f32,
+    pub price: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistoricalPriceElement {
+    pub date: String,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    #[serde(default)]
+    pub volume: Decimal,
+}
+
+#[derive(Deserialize)]
+pub struct HistoricalPricesResponse {
+    #[serde(default)]
+    pub historical: Vec<HistoricalPriceElement>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileElement {
+    #[serde(default)]
+    pub company_name: Option<String>,
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub exchange_short_name: Option<String>,
+    #[serde(default)]
+    pub sector: Option<String>,
+    #[serde(default)]
+    pub industry: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub price: Option<Decimal>,
+    #[serde(default)]
+    pub mkt_cap: Option<Decimal>,
+    #[serde(default)]
+    pub beta: Option<Decimal>,
+    // Dividend per share of the last year
+    #[serde(default)]
+    pub last_div: Option<Decimal>,
+}
+
+// Trailing twelve months metrics
+#[derive(Debug, Deserialize)]
+pub struct KeyMetricsElement {
+    #[serde(default, alias = "peRatioTTM")]
+    pub pe_ratio: Option<Decimal>,
+    #[serde(default, alias = "dividendYieldTTM")]
+    pub dividend_yield: Option<Decimal>,
+    #[serde(default, alias = "marketCapTTM")]
+    pub market_cap: Option<Decimal>,
 }
 
 #[derive(Deserialize)]
@@ -73,7 +139,7 @@ pub async fn get_stock_price(client: &HttpClient, symbol: &str) -> Result<StockP
             match to_bytes(resp.body_mut()).await {
                 Ok(body) => {
                     let stock_price: StockPriceResponse = serde_json::from_slice(&body).unwrap();
-                    println!("{:?}", stock_price);
+                    debug!(target: "stock_api", "Price of {}: {}", stock_price.symbol, stock_price.price);
                     Ok(stock_price)
                 },
                 Err(e) => Err(e.into())
@@ -82,3 +148,59 @@ pub async fn get_stock_price(client: &HttpClient, symbol: &str) -> Result<StockP
         Err(e) => Err(e.into()),
     }
 }
+
+// Daily prices of a stock as quoted at the time, oldest last
+pub async fn get_historical_prices(client: &HttpClient, symbol: &str) -> Result<Vec<HistoricalPriceElement>, Box<dyn std::error::Error + Send + Sync>> {
+    let uri = Endpoint::HistoricalPrices(symbol.into()).to_uri();
+    let response = client.get(uri).await;
+
+    match response {
+        Ok(mut resp) => {
+            match to_bytes(resp.body_mut()).await {
+                Ok(body) => {
+                    let history: HistoricalPricesResponse = serde_json::from_slice(&body)?;
+                    Ok(history.historical)
+                },
+                Err(e) => Err(e.into())
+            }
+        },
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn get_profile(client: &HttpClient, symbol: &str) -> Result<Option<ProfileElement>, Box<dyn std::error::Error + Send + Sync>> {
+    let uri = Endpoint::Profile(symbol.into()).to_uri();
+    let response = client.get(uri).await;
+
+    match response {
+        Ok(mut resp) => {
+            match to_bytes(resp.body_mut()).await {
+                Ok(body) => {
+                    // Unknown symbols get an empty list
+                    let profiles: Vec<ProfileElement> = serde_json::from_slice(&body)?;
+                    Ok(profiles.into_iter().next())
+                },
+                Err(e) => Err(e.into())
+            }
+        },
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn get_key_metrics(client: &HttpClient, symbol: &str) -> Result<Option<KeyMetricsElement>, Box<dyn std::error::Error + Send + Sync>> {
+    let uri = Endpoint::KeyMetrics(symbol.into()).to_uri();
+    let response = client.get(uri).await;
+
+    match response {
+        Ok(mut resp) => {
+            match to_bytes(resp.body_mut()).await {
+                Ok(body) => {
+                    let metrics: Vec<KeyMetricsElement> = serde_json::from_slice(&body)?;
+                    Ok(metrics.into_iter().next())
+                },
+                Err(e) => Err(e.into())
+            }
+        },
+        Err(e) => Err(e.into()),
+    }
+}
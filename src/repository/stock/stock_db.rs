@@ -2,22 +2,43 @@ use crate::repository::{
     error::PersistanceError
 };
 use super::Stock;
+use crate::repository::market::Market;
+use crate::decimal::Decimal;
 
 use r2d2_sqlite::rusqlite::{ 
     params,
     NO_PARAMS,
+    OptionalExtension,
     Row,
 };
 use crate::repository::DbConn;
 
+const SELECT_STOCK: &str = r"
+    SELECT s.symbol, s.name, s.price, s.initial_price, s.market_id, m.symbol, m.currency, s.currency, s.quantity, s.drip, s.sector, s.industry, s.country, s.tags
+        FROM stock s
+        LEFT JOIN market m ON m.id = s.market_id";
+
 impl From<&Row<'_>> for Stock {
     fn from(row: &Row) -> Self {
         Stock {
             symbol: row.get_unwrap(0),
             name: row.get_unwrap(1),
-            price: row.get_unwrap::<_, f64>(2) as f32,
-            initial_price: row.get_unwrap::<_, f64>(3) as f32,
-            market: row.get_unwrap(4),
+            price: row.get_unwrap(2),
+            initial_price: row.get_unwrap(3),
+            quantity: row.get_unwrap::<_, Option<Decimal>>(8).unwrap_or_default(),
+            market: Market {
+                id: row.get_unwrap(4),
+                symbol: row.get_unwrap::<_, Option<String>>(5).unwrap_or_default(),
+                currency: row.get_unwrap::<_, Option<String>>(6).unwrap_or_default(),
+            },
+            currency: row.get_unwrap::<_, Option<String>>(7).unwrap_or_default(),
+            drip: row.get_unwrap::<_, Option<bool>>(9).unwrap_or_default(),
+            sector: row.get_unwrap::<_, Option<String>>(10).unwrap_or_default(),
+            industry: row.get_unwrap::<_, Option<String>>(11).unwrap_or_default(),
+            country: row.get_unwrap::<_, Option<String>>(12).unwrap_or_default(),
+            tags: row.get_unwrap::<_, Option<String>>(13)
+                .and_then(|tags| serde_json::from_str(&tags).ok())
+                .unwrap_or_default(),
         }
     }
 }
@@ -25,21 +46,27 @@ impl From<&Row<'_>> for Stock {
 pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
     db.execute(
         r"CREATE TABLE IF NOT EXISTS stock (
-            symbol VARCHAR(4) PRIMARY KEY,
+            portfolio_id INTEGER REFERENCES portfolio(id),
+            symbol VARCHAR(4),
             name VARCHAR(255),
-            price REAL,
-            initial_price REAL,
-            market_id INTEGER REFERENCES market(id)
+            price TEXT,
+            initial_price TEXT,
+            market_id INTEGER REFERENCES market(id),
+            currency VARCHAR(3),
+            quantity TEXT,
+            drip INTEGER,
+            sector VARCHAR(64),
+            industry VARCHAR(64),
+            country VARCHAR(2),
+            tags TEXT,
+            PRIMARY KEY (portfolio_id, symbol)
         )", NO_PARAMS)
-    .map(|x| ())
-    .map_err(|e| PersistanceError::InitializationError(e))
+    .map(|_| ())
+    .map_err(PersistanceError::InitializationError)
 }
 
-pub fn get_by_makret(db: &DbConn, market_id: u16) -> Result<Vec<Stock>, PersistanceError> {
-    let mut query = db.prepare(r"
-        SELECT symbol, name, price, initial_price, market_id
-            FROM stock
-            WHERE s.market_id = ?1").unwrap();
+pub fn get_by_market(db: &DbConn, market_id: u16) -> Result<Vec<Stock>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE s.market_id = ?1", SELECT_STOCK)).unwrap();
     
     let items = query.query_map(
         params![market_id], 
@@ -51,12 +78,12 @@ pub fn get_by_makret(db: &DbConn, market_id: u16) -> Result<Vec<Stock>, Persista
     Ok(items)
 }
 
-pub fn update_price(db: &DbConn, symbol: &str, price: f32) -> Result<(), PersistanceError> {
+pub fn update_price(db: &DbConn, symbol: &str, price: Decimal) -> Result<(), PersistanceError> {
     let result = db.execute(r"
         UPDATE stock
             SET price = ?1
             WHERE symbol = ?2",
-        params![symbol, price.to_string()]);
+        params![price, symbol]);
     
     match result {
         Ok(_) => Ok(()),
@@ -64,17 +91,25 @@ pub fn update_price(db: &DbConn, symbol: &str, price: f32) -> Result<(), Persist
     }
 }
 
-pub fn add(db: &DbConn, stock: &Stock) -> Result<(), PersistanceError> {
+pub fn add(db: &DbConn, portfolio_id: u32, stock: &Stock) -> Result<(), PersistanceError> {
     let result = db.execute(
         r"INSERT INTO 
-            stock (symbol, name, price, initial_price, market_id) 
-            values (?1, ?2, ?3, ?4, ?5);",
+            stock (portfolio_id, symbol, name, price, initial_price, market_id, currency, quantity, drip, sector, industry, country, tags) 
+            values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13);",
         params![
+            portfolio_id,
             stock.symbol, 
             stock.name, 
-            stock.price.to_string(), 
-            stock.initial_price.to_string(), 
-            &stock.market.to_string()]);
+            stock.price, 
+            stock.initial_price, 
+            stock.market.id,
+            stock.currency,
+            stock.quantity,
+            stock.drip,
+            stock.sector,
+            stock.industry,
+            stock.country,
+            serde_json::to_string(&stock.tags).unwrap()]);
 
     match result {
         Ok(_) => Ok(()),
@@ -82,11 +117,11 @@ pub fn add(db: &DbConn, stock: &Stock) -> Result<(), PersistanceError> {
     }
 }
 
-pub fn delete(db: &DbConn, id: &str) -> Result<(), PersistanceError> {
+pub fn delete(db: &DbConn, portfolio_id: u32, id: &str) -> Result<(), PersistanceError> {
     let result = db.execute(
         r"DELETE FROM stock 
-            WHERE symbol = ?1;", 
-        params![id]);
+            WHERE portfolio_id = ?1 AND symbol = ?2;", 
+        params![portfolio_id, id]);
 
     match result {
         Ok(_) => Ok(()),
@@ -94,16 +129,25 @@ pub fn delete(db: &DbConn, id: &str) -> Result<(), PersistanceError> {
     }
 }
 
-pub fn update(db: &DbConn, stock: &Stock) -> Result<(), PersistanceError> {
+pub fn update(db: &DbConn, portfolio_id: u32, stock: &Stock) -> Result<(), PersistanceError> {
     let result = db.execute(r"
         UPDATE stock 
-            SET name = ?1, price = ?2, initial_price = ?3, market_id = ?4
-            WHERE symbol = ?5",
+            SET name = ?1, price = ?2, initial_price = ?3, market_id = ?4, currency = ?5, quantity = ?6, drip = ?7,
+                sector = ?8, industry = ?9, country = ?10, tags = ?11
+            WHERE portfolio_id = ?12 AND symbol = ?13",
         params![
             stock.name,
-            stock.price.to_string(), 
-            stock.initial_price.to_string(), 
-            stock.market.to_string(), 
+            stock.price, 
+            stock.initial_price, 
+            stock.market.id, 
+            stock.currency,
+            stock.quantity,
+            stock.drip,
+            stock.sector,
+            stock.industry,
+            stock.country,
+            serde_json::to_string(&stock.tags).unwrap(),
+            portfolio_id,
             stock.symbol]);
 
     match result {
@@ -113,22 +157,21 @@ pub fn update(db: &DbConn, stock: &Stock) -> Result<(), PersistanceError> {
             
 }
 
-fn get(db: &DbConn, id: &str) -> Result<Option<Stock>, PersistanceError> {
+pub fn get(db: &DbConn, portfolio_id: u32, id: &str) -> Result<Option<Stock>, PersistanceError> {
     let result = db.query_row(
-        "SELECT symbol, name, price, initial_price, market_id FROM stock WHERE symbol = ?1", 
-        params![id], 
-        |row| Ok(Some(Stock::from(row))));
-    // TODO: Check if none is returned if id not found
-    result.map_err(|error| PersistanceError::CouldNotInsert(error))
+        &format!("{} WHERE s.portfolio_id = ?1 AND s.symbol = ?2", SELECT_STOCK), 
+        params![portfolio_id, id], 
+        |row| Ok(Stock::from(row)))
+        .optional();
+
+    result.map_err(PersistanceError::CouldNotInsert)
 }
 
-pub fn get_all(db: &DbConn) -> Result<Vec<Stock>, PersistanceError> {
-    let mut query = db.prepare(r"
-    SELECT symbol, name, price, initial_price, market_id
-        FROM stock").unwrap();
+pub fn get_all(db: &DbConn, portfolio_id: u32) -> Result<Vec<Stock>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE s.portfolio_id = ?1", SELECT_STOCK)).unwrap();
 
     let items = query.query_map(
-        NO_PARAMS, 
+        params![portfolio_id], 
         |row| Ok(Stock::from(row)))
         .unwrap()
         .map(|x| x.unwrap())
@@ -136,3 +179,14 @@ pub fn get_all(db: &DbConn) -> Result<Vec<Stock>, PersistanceError> {
 
     Ok(items)
 }
+
+pub fn rename_symbol(db: &DbConn, portfolio_id: u32, symbol: &str, new_symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "UPDATE stock SET symbol = ?1 WHERE portfolio_id = ?2 AND symbol = ?3",
+        params![new_symbol, portfolio_id, symbol]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
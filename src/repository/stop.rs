@@ -0,0 +1,108 @@
+pub mod stop_db;
+
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum StopKind {
+    StopLoss,
+    TrailingStop,
+    TakeProfit,
+}
+
+// Levels a holding is closed at. A trailing stop keeps the stop price that far
+// below the highest price since it was set, only ever moving it up.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct HoldingStop {
+    pub symbol: String,
+    #[serde(default)]
+    pub stop_price: Option<Decimal>,
+    // Percent below the price
+    #[serde(default)]
+    pub trailing_percent: Option<Decimal>,
+    #[serde(default)]
+    pub take_profit: Option<Decimal>,
+    // Record the sale of the whole holding when a level is crossed
+    #[serde(default)]
+    pub sell: bool,
+}
+
+// Level of a holding that was crossed, along with the shares sold if the stop sells
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct StopEvent {
+    pub portfolio_id: u32,
+    pub symbol: String,
+    pub kind: StopKind,
+    pub level: Decimal,
+    pub price: Decimal,
+    pub date: NaiveDate,
+    pub sold: Option<Decimal>,
+}
+
+impl From<&str> for HoldingStop {
+    fn from(json: &str) -> Self {
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+}
+
+impl HoldingStop {
+    pub fn is_valid(&self) -> bool {
+        let positive = |level: Option<Decimal>| level.map(|level| level > Decimal::zero()).unwrap_or(true);
+        let below_take_profit = match (self.stop_price, self.take_profit) {
+            (Some(stop_price), Some(take_profit)) => stop_price < take_profit,
+            _ => true,
+        };
+
+        (self.stop_price.is_some() || self.trailing_percent.is_some() || self.take_profit.is_some())
+            && positive(self.stop_price)
+            && positive(self.take_profit)
+            && self.trailing_percent.map(|percent| percent > Decimal::zero() && percent < Decimal::from(100)).unwrap_or(true)
+            && below_take_profit
+    }
+
+    // Raises the trailing stop with the price and returns the level it crossed, if any
+    pub fn update(&mut self, price: Decimal) -> Option<(StopKind, Decimal)> {
+        if let Some(percent) = self.trailing_percent {
+            let trailing = (price * (Decimal::from(100) - percent) / Decimal::from(100)).round_dp(4);
+            if self.stop_price.map(|stop_price| trailing > stop_price).unwrap_or(true) {
+                self.stop_price = Some(trailing);
+            }
+        }
+
+        match (self.stop_price, self.take_profit) {
+            (Some(stop_price), _) if price <= stop_price => {
+                let kind = if self.trailing_percent.is_some() { StopKind::TrailingStop } else { StopKind::StopLoss };
+                Some((kind, stop_price))
+            },
+            (_, Some(take_profit)) if price >= take_profit => Some((StopKind::TakeProfit, take_profit)),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_update_stop() {
+    let mut stop = HoldingStop {
+        symbol: "AAPL".into(),
+        trailing_percent: Some(Decimal::from(10)),
+        take_profit: Some(Decimal::from(150)),
+        ..HoldingStop::default()
+    };
+
+    // Ratchets up with the price and never down
+    assert_eq!(None, stop.update(Decimal::from(100)));
+    assert_eq!(Some(Decimal::from(90)), stop.stop_price);
+    assert_eq!(None, stop.update(Decimal::from(120)));
+    assert_eq!(None, stop.update(Decimal::from(110)));
+    assert_eq!(Some(Decimal::from(108)), stop.stop_price);
+    assert_eq!(Some((StopKind::TrailingStop, Decimal::from(108))), stop.update(Decimal::from(107)));
+
+    let mut stop = HoldingStop { trailing_percent: None, stop_price: Some(Decimal::from(90)), ..stop };
+    assert_eq!(Some((StopKind::TakeProfit, Decimal::from(150))), stop.update(Decimal::from(151)));
+    assert_eq!(Some((StopKind::StopLoss, Decimal::from(90))), stop.update(Decimal::from(90)));
+
+    assert!(!HoldingStop { symbol: "AAPL".into(), ..HoldingStop::default() }.is_valid());
+    assert!(!HoldingStop { stop_price: Some(Decimal::from(200)), ..stop }.is_valid());
+}
@@ -0,0 +1,122 @@
+use crate::repository::{
+    error::PersistanceError
+};
+use super::HoldingStop;
+use r2d2_sqlite::rusqlite::{
+    params,
+    Row,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+const SELECT_STOP: &str = r"
+    SELECT symbol, stop_price, trailing_percent, take_profit, sell, portfolio_id
+        FROM holding_stop";
+
+impl From<&Row<'_>> for HoldingStop {
+    fn from(row: &Row) -> Self {
+        HoldingStop {
+            symbol: row.get_unwrap(0),
+            stop_price: row.get_unwrap(1),
+            trailing_percent: row.get_unwrap(2),
+            take_profit: row.get_unwrap(3),
+            sell: row.get_unwrap(4),
+        }
+    }
+}
+
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS holding_stop (
+            portfolio_id INTEGER REFERENCES portfolio(id),
+            symbol VARCHAR(4),
+            stop_price TEXT,
+            trailing_percent TEXT,
+            take_profit TEXT,
+            sell BOOLEAN,
+            PRIMARY KEY (portfolio_id, symbol)
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+// Stores the levels of a holding, replacing the previous ones
+pub fn set(db: &DbConn, portfolio_id: u32, stop: &HoldingStop) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT OR REPLACE INTO
+            holding_stop (portfolio_id, symbol, stop_price, trailing_percent, take_profit, sell)
+            values (?1, ?2, ?3, ?4, ?5, ?6);",
+        params![
+            portfolio_id,
+            stop.symbol,
+            stop.stop_price,
+            stop.trailing_percent,
+            stop.take_profit,
+            stop.sell]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+// Returns whether the holding had levels
+pub fn delete(db: &DbConn, portfolio_id: u32, symbol: &str) -> Result<bool, PersistanceError> {
+    let result = db.execute(
+        "DELETE FROM holding_stop WHERE portfolio_id = ?1 AND symbol = ?2",
+        params![portfolio_id, symbol]);
+
+    match result {
+        Ok(deleted) => Ok(deleted > 0),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+pub fn rename_symbol(db: &DbConn, portfolio_id: u32, symbol: &str, new_symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "UPDATE holding_stop SET symbol = ?1 WHERE portfolio_id = ?2 AND symbol = ?3",
+        params![new_symbol, portfolio_id, symbol]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+pub fn delete_all(db: &DbConn, portfolio_id: u32) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "DELETE FROM holding_stop WHERE portfolio_id = ?1",
+        params![portfolio_id]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+pub fn get_all(db: &DbConn, portfolio_id: u32) -> Result<Vec<HoldingStop>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE portfolio_id = ?1 ORDER BY symbol", SELECT_STOP)).unwrap();
+
+    let items = query.query_map(
+        params![portfolio_id],
+        |row| Ok(HoldingStop::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+// Returns the levels of every portfolio along with the portfolio they belong to
+pub fn get_all_portfolios(db: &DbConn) -> Result<Vec<(u32, HoldingStop)>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} ORDER BY portfolio_id, symbol", SELECT_STOP)).unwrap();
+
+    let items = query.query_map(
+        NO_PARAMS,
+        |row| Ok((row.get_unwrap(5), HoldingStop::from(row))))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
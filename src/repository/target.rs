@@ -0,0 +1,32 @@
+pub mod target_db;
+
+use serde::{Serialize, Deserialize};
+use crate::allocation::AllocationDimension;
+use crate::decimal::Decimal;
+
+// Weight a holding, tag or sector should have in the portfolio
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct AllocationTarget {
+    pub dimension: AllocationDimension,
+    pub key: String,
+    // Percent of the holdings plus the cash
+    pub weight: Decimal,
+    // Percentage points the weight may drift before it's rebalanced
+    #[serde(default)]
+    pub tolerance: Decimal,
+}
+
+impl From<&str> for AllocationTarget {
+    fn from(json: &str) -> Self {
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+}
+
+impl AllocationTarget {
+    pub fn is_valid(&self) -> bool {
+        !self.key.is_empty()
+            && self.weight >= Decimal::zero()
+            && self.weight <= Decimal::from(100)
+            && self.tolerance >= Decimal::zero()
+    }
+}
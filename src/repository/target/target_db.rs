@@ -0,0 +1,126 @@
+use crate::repository::{
+    error::PersistanceError
+};
+use super::AllocationTarget;
+use crate::allocation::AllocationDimension;
+use r2d2_sqlite::rusqlite::{
+    params,
+    Row,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+impl From<&Row<'_>> for AllocationTarget {
+    fn from(row: &Row) -> Self {
+        AllocationTarget {
+            dimension: AllocationDimension::parse(&row.get_unwrap::<_, String>(0)).unwrap(),
+            key: row.get_unwrap(1),
+            weight: row.get_unwrap(2),
+            tolerance: row.get_unwrap(3),
+        }
+    }
+}
+
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS allocation_target (
+            portfolio_id INTEGER REFERENCES portfolio(id),
+            dimension VARCHAR(16),
+            key VARCHAR(64),
+            weight TEXT,
+            tolerance TEXT,
+            PRIMARY KEY (portfolio_id, dimension, key)
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+// Stores the target, replacing the one of the same holding, tag or sector
+pub fn set(db: &DbConn, portfolio_id: u32, target: &AllocationTarget) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT OR REPLACE INTO 
+            allocation_target (portfolio_id, dimension, key, weight, tolerance) 
+            values (?1, ?2, ?3, ?4, ?5);",
+        params![
+            portfolio_id,
+            target.dimension.as_str(),
+            target.key,
+            target.weight,
+            target.tolerance]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+pub fn delete(db: &DbConn, portfolio_id: u32, dimension: AllocationDimension, key: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"DELETE FROM allocation_target 
+            WHERE portfolio_id = ?1 AND dimension = ?2 AND key = ?3;", 
+        params![portfolio_id, dimension.as_str(), key]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+// Renames the targets keyed by a holding's symbol
+pub fn rename_symbol(db: &DbConn, portfolio_id: u32, symbol: &str, new_symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"UPDATE allocation_target SET key = ?1
+            WHERE portfolio_id = ?2 AND dimension = ?3 AND key = ?4;",
+        params![new_symbol, portfolio_id, AllocationDimension::Holding.as_str(), symbol]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+pub fn delete_all(db: &DbConn, portfolio_id: u32) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"DELETE FROM allocation_target 
+            WHERE portfolio_id = ?1;", 
+        params![portfolio_id]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+pub fn get_all(db: &DbConn, portfolio_id: u32) -> Result<Vec<AllocationTarget>, PersistanceError> {
+    let mut query = db.prepare(r"
+        SELECT dimension, key, weight, tolerance 
+            FROM allocation_target 
+            WHERE portfolio_id = ?1 
+            ORDER BY dimension, key").unwrap();
+
+    let items = query.query_map(
+        params![portfolio_id], 
+        |row| Ok(AllocationTarget::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+pub fn get_by_dimension(db: &DbConn, portfolio_id: u32, dimension: AllocationDimension) -> Result<Vec<AllocationTarget>, PersistanceError> {
+    let mut query = db.prepare(r"
+        SELECT dimension, key, weight, tolerance 
+            FROM allocation_target 
+            WHERE portfolio_id = ?1 AND dimension = ?2 
+            ORDER BY key").unwrap();
+
+    let items = query.query_map(
+        params![portfolio_id, dimension.as_str()], 
+        |row| Ok(AllocationTarget::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
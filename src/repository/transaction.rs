@@ -0,0 +1,87 @@
+pub mod transaction_db;
+
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use super::corporate_action::{self, CorporateAction};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionKind {
+    #[default]
+    Buy,
+    Sell,
+}
+
+// Trade of a stock, as recorded in the ledger
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Transaction {
+    #[serde(default)]
+    pub id: u32,
+    pub symbol: String,
+    #[serde(default)]
+    pub kind: TransactionKind,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    #[serde(default)]
+    pub commission: Decimal,
+    // Filled with the currency of the stock when recorded
+    #[serde(default)]
+    pub currency: String,
+    #[serde(default = "crate::repository::today")]
+    pub date: NaiveDate,
+    // Gain of a sell against the average cost of the position
+    #[serde(default)]
+    pub realized_gain: Decimal,
+}
+
+impl TransactionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Buy => "buy",
+            Self::Sell => "sell",
+        }
+    }
+}
+
+impl From<&str> for TransactionKind {
+    fn from(kind: &str) -> Self {
+        match kind {
+            "sell" => Self::Sell,
+            _ => Self::Buy,
+        }
+    }
+}
+
+impl From<&str> for Transaction {
+    fn from(json: &str) -> Self {
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+}
+
+// Shares held at the end of the day before `date`, according to the transactions
+// and the splits made after each of them
+pub fn quantity_held_before(transactions: &[Transaction], actions: &[CorporateAction], date: NaiveDate) -> Decimal {
+    let day_before = date.pred_opt().unwrap_or(date);
+
+    transactions.iter()
+        .filter(|transaction| transaction.date < date)
+        .map(|transaction| {
+            let quantity = match transaction.kind {
+                TransactionKind::Buy => transaction.quantity,
+                TransactionKind::Sell => -transaction.quantity,
+            };
+            quantity * corporate_action::split_factor(actions, transaction.date, day_before)
+        })
+        .sum()
+}
+
+impl Transaction {
+    // Cash the transaction moves: negative when buying, positive when selling
+    pub fn cash_amount(&self) -> Decimal {
+        match self.kind {
+            TransactionKind::Buy => -(self.quantity * self.price + self.commission),
+            TransactionKind::Sell => self.quantity * self.price - self.commission,
+        }
+    }
+}
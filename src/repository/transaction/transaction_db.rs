@@ -0,0 +1,108 @@
+use crate::repository::{
+    error::PersistanceError
+};
+use super::{Transaction, TransactionKind};
+use r2d2_sqlite::rusqlite::{
+    params,
+    Row,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+const SELECT_TRANSACTION: &str = r"
+    SELECT id, symbol, kind, quantity, price, commission, currency, date, realized_gain
+        FROM stock_transaction";
+
+impl From<&Row<'_>> for Transaction {
+    fn from(row: &Row) -> Self {
+        Transaction {
+            id: row.get_unwrap(0),
+            symbol: row.get_unwrap(1),
+            kind: TransactionKind::from(row.get_unwrap::<_, String>(2).as_str()),
+            quantity: row.get_unwrap(3),
+            price: row.get_unwrap(4),
+            commission: row.get_unwrap(5),
+            currency: row.get_unwrap(6),
+            date: row.get_unwrap::<_, String>(7).parse().unwrap(),
+            realized_gain: row.get_unwrap(8),
+        }
+    }
+}
+
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS stock_transaction (
+            id INTEGER PRIMARY KEY,
+            portfolio_id INTEGER REFERENCES portfolio(id),
+            symbol VARCHAR(4),
+            kind VARCHAR(8),
+            quantity TEXT,
+            price TEXT,
+            commission TEXT,
+            currency VARCHAR(3),
+            date TEXT,
+            realized_gain TEXT
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+// Stores a transaction, returning its id
+pub fn add(db: &DbConn, portfolio_id: u32, transaction: &Transaction) -> Result<u32, PersistanceError> {
+    let result = db.execute(
+        r"INSERT INTO 
+            stock_transaction (portfolio_id, symbol, kind, quantity, price, commission, currency, date, realized_gain) 
+            values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);",
+        params![
+            portfolio_id,
+            transaction.symbol,
+            transaction.kind.as_str(),
+            transaction.quantity,
+            transaction.price,
+            transaction.commission,
+            transaction.currency,
+            transaction.date.to_string(),
+            transaction.realized_gain]);
+
+    match result {
+        Ok(_) => Ok(db.last_insert_rowid() as u32),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+pub fn get_all(db: &DbConn, portfolio_id: u32) -> Result<Vec<Transaction>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE portfolio_id = ?1 ORDER BY date, id", SELECT_TRANSACTION)).unwrap();
+
+    let items = query.query_map(
+        params![portfolio_id], 
+        |row| Ok(Transaction::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+pub fn get_by_symbol(db: &DbConn, portfolio_id: u32, symbol: &str) -> Result<Vec<Transaction>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE portfolio_id = ?1 AND symbol = ?2 ORDER BY date, id", SELECT_TRANSACTION)).unwrap();
+
+    let items = query.query_map(
+        params![portfolio_id, symbol], 
+        |row| Ok(Transaction::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+pub fn rename_symbol(db: &DbConn, portfolio_id: u32, symbol: &str, new_symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "UPDATE stock_transaction SET symbol = ?1 WHERE portfolio_id = ?2 AND symbol = ?3",
+        params![new_symbol, portfolio_id, symbol]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
@@ -0,0 +1,81 @@
+pub mod watchlist_db;
+
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use super::price_history::PriceBar;
+
+// Named list of symbols followed without holding them
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Watchlist {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct WatchlistItem {
+    pub symbol: String,
+    #[serde(default)]
+    pub target_price: Option<Decimal>,
+    #[serde(default)]
+    pub note: String,
+}
+
+// Item of a watchlist along with the last known price
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct WatchlistEntry {
+    pub symbol: String,
+    pub target_price: Option<Decimal>,
+    pub note: String,
+    pub price: Option<Decimal>,
+    pub price_date: Option<NaiveDate>,
+    // What the price has to move to reach the target, absolute and as a percentage
+    pub distance_to_target: Option<Decimal>,
+    pub distance_to_target_pct: Option<Decimal>,
+}
+
+impl From<&str> for WatchlistItem {
+    fn from(json: &str) -> Self {
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+}
+
+impl WatchlistEntry {
+    pub fn new(item: WatchlistItem, last_bar: Option<PriceBar>) -> WatchlistEntry {
+        let price = last_bar.as_ref().map(|bar| bar.close);
+        let distance_to_target = match (item.target_price, price) {
+            (Some(target), Some(price)) => Some(target - price),
+            _ => None,
+        };
+        let distance_to_target_pct = match (distance_to_target, price) {
+            (Some(distance), Some(price)) => (distance * Decimal::from(100))
+                .checked_div(price)
+                .map(|pct| pct.round_dp(2)),
+            _ => None,
+        };
+
+        WatchlistEntry {
+            symbol: item.symbol,
+            target_price: item.target_price,
+            note: item.note,
+            price,
+            price_date: last_bar.map(|bar| bar.date),
+            distance_to_target,
+            distance_to_target_pct,
+        }
+    }
+}
+
+#[test]
+fn test_distance_to_target() {
+    let item = WatchlistItem { symbol: "NVDA".into(), target_price: Some(Decimal::from(90)), note: String::new() };
+    let bar = PriceBar { symbol: "NVDA".into(), close: Decimal::from(120), ..PriceBar::default() };
+
+    let entry = WatchlistEntry::new(item.clone(), Some(bar));
+    assert_eq!(Some(Decimal::from(-30)), entry.distance_to_target);
+    assert_eq!(Some(Decimal::from(-25)), entry.distance_to_target_pct);
+
+    let entry = WatchlistEntry::new(item, None);
+    assert_eq!(None, entry.price);
+    assert_eq!(None, entry.distance_to_target);
+}
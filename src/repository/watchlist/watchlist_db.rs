@@ -0,0 +1,186 @@
+use crate::repository::{
+    error::PersistanceError
+};
+use super::{Watchlist, WatchlistItem};
+use r2d2_sqlite::rusqlite::{
+    params,
+    OptionalExtension,
+    Row,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+impl From<&Row<'_>> for Watchlist {
+    fn from(row: &Row) -> Self {
+        Watchlist {
+            id: row.get_unwrap(0),
+            name: row.get_unwrap(1),
+        }
+    }
+}
+
+impl From<&Row<'_>> for WatchlistItem {
+    fn from(row: &Row) -> Self {
+        WatchlistItem {
+            symbol: row.get_unwrap(0),
+            target_price: row.get_unwrap(1),
+            note: row.get_unwrap(2),
+        }
+    }
+}
+
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS watchlist (
+            id INTEGER PRIMARY KEY,
+            name VARCHAR(64) UNIQUE
+        )", NO_PARAMS)
+        .map_err(PersistanceError::InitializationError)?;
+
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS watchlist_item (
+            watchlist_id INTEGER REFERENCES watchlist(id),
+            symbol VARCHAR(4),
+            target_price TEXT,
+            note TEXT,
+            PRIMARY KEY (watchlist_id, symbol)
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+pub fn add(db: &DbConn, name: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT INTO 
+            watchlist (name) 
+            values (?1);",
+        params![name]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+// Deletes a watchlist along with its items
+pub fn delete(db: &DbConn, id: u32) -> Result<(), PersistanceError> {
+    db.execute("DELETE FROM watchlist_item WHERE watchlist_id = ?1", params![id])
+        .map_err(PersistanceError::CouldNotDelete)?;
+
+    let result = db.execute(
+        r"DELETE FROM watchlist 
+            WHERE id = ?1;", 
+        params![id]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+pub fn get_by_name(db: &DbConn, name: &str) -> Result<Option<Watchlist>, PersistanceError> {
+    let result = db.query_row(
+        "SELECT id, name FROM watchlist WHERE name = ?1", 
+        params![name], 
+        |row| Ok(Watchlist::from(row)))
+        .optional();
+
+    result.map_err(PersistanceError::CouldNotInsert)
+}
+
+pub fn get_all(db: &DbConn) -> Result<Vec<Watchlist>, PersistanceError> {
+    let mut query = db.prepare("SELECT id, name FROM watchlist ORDER BY name").unwrap();
+
+    let items = query.query_map(
+        NO_PARAMS, 
+        |row| Ok(Watchlist::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+pub fn add_item(db: &DbConn, watchlist_id: u32, item: &WatchlistItem) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT INTO 
+            watchlist_item (watchlist_id, symbol, target_price, note) 
+            values (?1, ?2, ?3, ?4);",
+        params![watchlist_id, item.symbol, item.target_price, item.note]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+// Updates the target price and the note of an item, returning whether it exists
+pub fn update_item(db: &DbConn, watchlist_id: u32, item: &WatchlistItem) -> Result<bool, PersistanceError> {
+    let result = db.execute(r"
+        UPDATE watchlist_item 
+            SET target_price = ?1, note = ?2
+            WHERE watchlist_id = ?3 AND symbol = ?4",
+        params![item.target_price, item.note, watchlist_id, item.symbol]);
+
+    match result {
+        Ok(updated) => Ok(updated > 0),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+pub fn delete_item(db: &DbConn, watchlist_id: u32, symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"DELETE FROM watchlist_item 
+            WHERE watchlist_id = ?1 AND symbol = ?2;", 
+        params![watchlist_id, symbol]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+// Renames the items of every watchlist, dropping the ones of lists that already watch
+// the new symbol
+pub fn rename_symbol(db: &DbConn, symbol: &str, new_symbol: &str) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "UPDATE OR IGNORE watchlist_item SET symbol = ?1 WHERE symbol = ?2",
+        params![new_symbol, symbol])
+        .and_then(|_| db.execute("DELETE FROM watchlist_item WHERE symbol = ?1", params![symbol]));
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotUpdate(e))
+    }
+}
+
+pub fn get_items(db: &DbConn, watchlist_id: u32) -> Result<Vec<WatchlistItem>, PersistanceError> {
+    let mut query = db.prepare(r"
+        SELECT symbol, target_price, note
+            FROM watchlist_item
+            WHERE watchlist_id = ?1
+            ORDER BY symbol").unwrap();
+
+    let items = query.query_map(
+        params![watchlist_id], 
+        |row| Ok(WatchlistItem::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+// Returns the symbols of every watchlist, without repetitions
+pub fn get_symbols(db: &DbConn) -> Result<Vec<String>, PersistanceError> {
+    let mut query = db.prepare("SELECT DISTINCT symbol FROM watchlist_item ORDER BY symbol").unwrap();
+
+    let items = query.query_map(
+        NO_PARAMS, 
+        |row| row.get(0))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
@@ -0,0 +1,69 @@
+pub mod webhook_api;
+pub mod webhook_db;
+
+use std::time::Duration;
+use chrono::NaiveDateTime;
+use hyper::Uri;
+use serde::{Serialize, Deserialize};
+
+// Attempts at posting an event before giving up
+pub const MAX_ATTEMPTS: u32 = 4;
+// Wait before the first retry, doubled after every other one
+pub const BACKOFF: Duration = Duration::from_secs(2);
+// Wait for the response to an attempt before counting it as failed
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+// Address the events of an alert are posted to. The payload is signed with the
+// secret so the receiver can tell it comes from us.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Webhook {
+    #[serde(default)]
+    pub id: u32,
+    pub alert_id: u32,
+    pub url: String,
+    #[serde(default)]
+    pub secret: String,
+}
+
+// Attempt at posting an event to a webhook, with the status of the response if
+// there was one
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Delivery {
+    #[serde(default)]
+    pub id: u32,
+    pub webhook_id: u32,
+    pub alert_id: u32,
+    pub attempt: u32,
+    pub timestamp: NaiveDateTime,
+    pub status: Option<u16>,
+    #[serde(default)]
+    pub error: String,
+    pub delivered: bool,
+}
+
+impl From<&str> for Webhook {
+    fn from(json: &str) -> Self {
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+}
+
+impl Webhook {
+    pub fn is_valid(&self) -> bool {
+        let scheme = self.url.parse::<Uri>().ok()
+            .and_then(|uri| uri.scheme_str().map(str::to_owned));
+
+        matches!(scheme.as_deref(), Some("http") | Some("https")) && !self.secret.is_empty()
+    }
+}
+
+impl Delivery {
+    // Whether another attempt could succeed. Client errors other than too many
+    // requests would fail again.
+    pub fn is_retriable(&self) -> bool {
+        match self.status {
+            _ if self.delivered => false,
+            Some(status) if (400..500).contains(&status) => status == 429,
+            _ => true,
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use hyper::{
+    client::connect::Connect,
+    header::CONTENT_TYPE,
+    Body,
+    Client,
+    Method,
+    Request,
+};
+use openssl::{
+    hash::MessageDigest,
+    pkey::PKey,
+    sign::Signer,
+};
+use log::debug;
+
+// Header with the HMAC-SHA256 of the body, as `sha256=<hex>`
+pub const SIGNATURE_HEADER: &str = "X-Stocks-Signature";
+
+pub fn sign(secret: &str, body: &[u8]) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let key = PKey::hmac(secret.as_bytes())?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    signer.update(body)?;
+
+    let hex: String = signer.sign_to_vec()?.iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    Ok(format!("sha256={}", hex))
+}
+
+// Posts the signed JSON body, returning the status of the response
+pub async fn post<C>(client: &Client<C>, url: &str, secret: &str, body: Vec<u8>) -> Result<u16, Box<dyn std::error::Error + Send + Sync>>
+        where C: Connect + Clone + Send + Sync + 'static {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(url)
+        .header(CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, sign(secret, &body)?)
+        .body(Body::from(body))?;
+
+    let response = client.request(request).await?;
+    debug!(target: "webhook_api", "Posted to {}: {}", url, response.status());
+    Ok(response.status().as_u16())
+}
@@ -0,0 +1,165 @@
+use chrono::NaiveDateTime;
+use crate::repository::{
+    error::PersistanceError
+};
+use super::{Webhook, Delivery};
+use r2d2_sqlite::rusqlite::{
+    params,
+    Row,
+    NO_PARAMS
+};
+use crate::repository::DbConn;
+
+// As written by `to_string`
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+const SELECT_DELIVERY: &str = r"
+    SELECT id, webhook_id, alert_id, attempt, timestamp, status, error, delivered
+        FROM webhook_delivery";
+
+impl From<&Row<'_>> for Webhook {
+    fn from(row: &Row) -> Self {
+        Webhook {
+            id: row.get_unwrap(0),
+            alert_id: row.get_unwrap(1),
+            url: row.get_unwrap(2),
+            secret: row.get_unwrap(3),
+        }
+    }
+}
+
+impl From<&Row<'_>> for Delivery {
+    fn from(row: &Row) -> Self {
+        Delivery {
+            id: row.get_unwrap(0),
+            webhook_id: row.get_unwrap(1),
+            alert_id: row.get_unwrap(2),
+            attempt: row.get_unwrap(3),
+            timestamp: NaiveDateTime::parse_from_str(&row.get_unwrap::<_, String>(4), TIMESTAMP_FORMAT).unwrap(),
+            status: row.get_unwrap(5),
+            error: row.get_unwrap(6),
+            delivered: row.get_unwrap(7),
+        }
+    }
+}
+
+pub fn create_table_if_not_exists(db: &DbConn) -> Result<(), PersistanceError> {
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS webhook (
+            id INTEGER PRIMARY KEY,
+            alert_id INTEGER REFERENCES alert(id),
+            url TEXT,
+            secret TEXT
+        )", NO_PARAMS)
+        .map_err(PersistanceError::InitializationError)?;
+
+    db.execute(
+        r"CREATE TABLE IF NOT EXISTS webhook_delivery (
+            id INTEGER PRIMARY KEY,
+            webhook_id INTEGER,
+            alert_id INTEGER,
+            attempt INTEGER,
+            timestamp TEXT,
+            status INTEGER,
+            error TEXT,
+            delivered BOOLEAN
+        )", NO_PARAMS)
+        .map(|_| ())
+        .map_err(PersistanceError::InitializationError)
+}
+
+pub fn add(db: &DbConn, webhook: &Webhook) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT INTO
+            webhook (alert_id, url, secret)
+            values (?1, ?2, ?3);",
+        params![webhook.alert_id, webhook.url, webhook.secret]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+// Returns whether there was a webhook with the id
+pub fn delete(db: &DbConn, id: u32) -> Result<bool, PersistanceError> {
+    let result = db.execute(
+        "DELETE FROM webhook WHERE id = ?1",
+        params![id]);
+
+    match result {
+        Ok(deleted) => Ok(deleted > 0),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+pub fn delete_by_alert(db: &DbConn, alert_id: u32) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        "DELETE FROM webhook WHERE alert_id = ?1",
+        params![alert_id]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotDelete(e))
+    }
+}
+
+pub fn get_all(db: &DbConn) -> Result<Vec<Webhook>, PersistanceError> {
+    let mut query = db.prepare("SELECT id, alert_id, url, secret FROM webhook ORDER BY id").unwrap();
+
+    let items = query.query_map(
+        NO_PARAMS,
+        |row| Ok(Webhook::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+pub fn get_by_alert(db: &DbConn, alert_id: u32) -> Result<Vec<Webhook>, PersistanceError> {
+    let mut query = db.prepare("SELECT id, alert_id, url, secret FROM webhook WHERE alert_id = ?1 ORDER BY id").unwrap();
+
+    let items = query.query_map(
+        params![alert_id],
+        |row| Ok(Webhook::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
+
+pub fn add_delivery(db: &DbConn, delivery: &Delivery) -> Result<(), PersistanceError> {
+    let result = db.execute(
+        r"INSERT INTO
+            webhook_delivery (webhook_id, alert_id, attempt, timestamp, status, error, delivered)
+            values (?1, ?2, ?3, ?4, ?5, ?6, ?7);",
+        params![
+            delivery.webhook_id,
+            delivery.alert_id,
+            delivery.attempt,
+            delivery.timestamp.to_string(),
+            delivery.status,
+            delivery.error,
+            delivery.delivered]);
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(PersistanceError::CouldNotInsert(e))
+    }
+}
+
+// Returns the attempts, of an alert if given, oldest first
+pub fn get_deliveries(db: &DbConn, alert_id: Option<u32>) -> Result<Vec<Delivery>, PersistanceError> {
+    let mut query = db.prepare(&format!("{} WHERE ?1 IS NULL OR alert_id = ?1 ORDER BY id", SELECT_DELIVERY)).unwrap();
+
+    let items = query.query_map(
+        params![alert_id],
+        |row| Ok(Delivery::from(row)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    Ok(items)
+}
@@ -0,0 +1,389 @@
+use std::collections::{BTreeMap, BTreeSet};
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use crate::repository::{
+    transaction::{self, Transaction, TransactionKind},
+    corporate_action::CorporateAction,
+    dividend::Dividend,
+    price_history::PriceBar,
+};
+
+// Period the returns are calculated for, ending today unless it's a custom one
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum ReturnPeriod {
+    Day,
+    Week,
+    MonthToDate,
+    YearToDate,
+    Year,
+    Inception,
+    Custom(NaiveDate, NaiveDate),
+}
+
+impl ReturnPeriod {
+    pub fn parse(period: &str) -> Option<Self> {
+        match period.to_uppercase().as_str() {
+            "1D" => Some(Self::Day),
+            "1W" => Some(Self::Week),
+            "MTD" => Some(Self::MonthToDate),
+            "YTD" => Some(Self::YearToDate),
+            "1Y" => Some(Self::Year),
+            "ALL" | "INCEPTION" => Some(Self::Inception),
+            _ => None,
+        }
+    }
+
+    // Day the holdings are valued at to start the period, and its last day. `inception`
+    // is the day of the first transaction.
+    pub fn range(&self, today: NaiveDate, inception: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let start = match self {
+            Self::Day => today - Duration::days(1),
+            Self::Week => today - Duration::days(7),
+            Self::MonthToDate => today.with_day(1).unwrap() - Duration::days(1),
+            Self::YearToDate => NaiveDate::from_ymd_opt(today.year() - 1, 12, 31).unwrap(),
+            Self::Year => today.with_year(today.year() - 1).unwrap_or(today - Duration::days(365)),
+            Self::Inception => inception - Duration::days(1),
+            Self::Custom(from, to) => return (*from, *to),
+        };
+
+        (start, today)
+    }
+}
+
+// Holdings at the end of a day and the money put in and taken out of them that day
+#[derive(Debug, PartialEq, Clone)]
+pub struct ValuationPoint {
+    pub date: NaiveDate,
+    pub value: Decimal,
+    pub flows_in: Decimal,
+    pub flows_out: Decimal,
+}
+
+// Returns are percents, the money weighted one annualized
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct PeriodReturn {
+    pub start_value: Decimal,
+    pub end_value: Decimal,
+    // Money put in buying and taken out selling or as dividends
+    pub flows_in: Decimal,
+    pub flows_out: Decimal,
+    pub gain: Decimal,
+    pub time_weighted: Option<Decimal>,
+    pub money_weighted: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct HoldingReturn {
+    pub symbol: String,
+    pub currency: String,
+    #[serde(flatten)]
+    pub returns: PeriodReturn,
+}
+
+// The total is in the base currency
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Returns {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub base_currency: String,
+    pub total: PeriodReturn,
+    pub holdings: Vec<HoldingReturn>,
+    // Currencies without exchange rate, their holdings are left out of the total
+    pub missing_rates: Vec<String>,
+}
+
+// What the ledger knows about a stock, to value it on any day
+#[derive(Debug, PartialEq, Clone)]
+pub struct HoldingHistory {
+    pub symbol: String,
+    pub currency: String,
+    pub transactions: Vec<Transaction>,
+    pub actions: Vec<CorporateAction>,
+    // Only the paid ones
+    pub dividends: Vec<Dividend>,
+    // Closes as they were traded, the trade prices fill the days without one
+    pub prices: BTreeMap<NaiveDate, Decimal>,
+}
+
+impl HoldingHistory {
+    pub fn new(symbol: &str, currency: &str, transactions: Vec<Transaction>, actions: Vec<CorporateAction>, dividends: Vec<Dividend>, bars: &[PriceBar]) -> Self {
+        let mut prices: BTreeMap<NaiveDate, Decimal> = bars.iter()
+            .map(|bar| (bar.date, bar.close))
+            .collect();
+        for transaction in &transactions {
+            prices.entry(transaction.date).or_insert(transaction.price);
+        }
+
+        HoldingHistory {
+            symbol: symbol.to_owned(),
+            currency: currency.to_owned(),
+            transactions,
+            dividends: dividends.into_iter().filter(|dividend| dividend.paid).collect(),
+            actions,
+            prices,
+        }
+    }
+
+    // Shares held at the end of the day times the last known price
+    fn value_on(&self, date: NaiveDate) -> Decimal {
+        let quantity = transaction::quantity_held_before(&self.transactions, &self.actions, date + Duration::days(1));
+
+        match self.prices.range(..=date).next_back() {
+            Some((_, price)) => quantity * *price,
+            None => Decimal::zero(),
+        }
+    }
+
+    // Money put in and taken out of the holding on the day
+    fn flows_on(&self, date: NaiveDate) -> (Decimal, Decimal) {
+        let mut flows_in = Decimal::zero();
+        let mut flows_out = Decimal::zero();
+
+        for transaction in self.transactions.iter().filter(|transaction| transaction.date == date) {
+            match transaction.kind {
+                TransactionKind::Buy => flows_in -= transaction.cash_amount(),
+                TransactionKind::Sell => flows_out += transaction.cash_amount(),
+            }
+        }
+        for dividend in self.dividends.iter().filter(|dividend| dividend.pay_date == date) {
+            flows_out += dividend.gross().unwrap_or_default();
+        }
+
+        (flows_in, flows_out)
+    }
+
+    // Days the holding changes in value or gets money in or out during the period
+    fn dates(&self, start: NaiveDate, end: NaiveDate) -> BTreeSet<NaiveDate> {
+        let in_period = |date: &NaiveDate| *date > start && *date <= end;
+
+        let mut dates: BTreeSet<NaiveDate> = self.prices.keys().cloned().filter(in_period).collect();
+        dates.extend(self.dividends.iter().map(|dividend| dividend.pay_date).filter(in_period));
+        dates.insert(start);
+        dates.insert(end);
+        dates
+    }
+
+    fn point(&self, date: NaiveDate, start: NaiveDate) -> ValuationPoint {
+        let (flows_in, flows_out) = if date == start {
+            (Decimal::zero(), Decimal::zero())
+        } else {
+            self.flows_on(date)
+        };
+
+        ValuationPoint { date, value: self.value_on(date), flows_in, flows_out }
+    }
+}
+
+// Days any of the holdings has to be valued on during the period
+pub fn valuation_dates(histories: &[HoldingHistory], start: NaiveDate, end: NaiveDate) -> BTreeSet<NaiveDate> {
+    histories.iter()
+        .flat_map(|history| history.dates(start, end))
+        .collect()
+}
+
+// Growth of a unit invested when the holdings first had money, after every day.
+// Money put in or taken out doesn't count as growth: it comes in at the start of
+// the day and goes out at its end.
+pub fn growth_index(points: &[ValuationPoint]) -> Vec<(NaiveDate, Decimal)> {
+    let mut growth = Decimal::from(1);
+    let mut index = vec![];
+
+    for window in points.windows(2) {
+        let (previous, point) = (&window[0], &window[1]);
+
+        if let Some(day_growth) = (point.value + point.flows_out).checked_div(previous.value + point.flows_in) {
+            if index.is_empty() {
+                index.push((previous.date, growth));
+            }
+            growth = growth * day_growth;
+            index.push((point.date, growth));
+        }
+    }
+
+    index
+}
+
+// Chains the return of every day, so money put in or taken out doesn't count as gain
+pub fn time_weighted_return(points: &[ValuationPoint]) -> Option<Decimal> {
+    growth_index(points)
+        .last()
+        .map(|(_, growth)| ((*growth - Decimal::from(1)) * Decimal::from(100)).round_dp(2))
+}
+
+// Annual rate that makes the flows add up to zero. Money put in is negative.
+pub fn xirr(flows: &[(NaiveDate, Decimal)]) -> Option<Decimal> {
+    let first = flows.iter().map(|(date, _)| *date).min()?;
+    let flows: Vec<(f64, f64)> = flows.iter()
+        .map(|(date, amount)| ((*date - first).num_days() as f64 / 365.0, amount.to_f64()))
+        .collect();
+
+    let npv = |rate: f64| -> f64 {
+        flows.iter()
+            .map(|(years, amount)| amount / (1.0 + rate).powf(*years))
+            .sum()
+    };
+
+    let mut low = -0.9999;
+    let mut high = 1.0;
+    while npv(low).signum() == npv(high).signum() {
+        if high > 1e9 {
+            return None;
+        }
+        high *= 10.0;
+    }
+
+    for _ in 0..200 {
+        let middle = (low + high) / 2.0;
+        if npv(middle).signum() == npv(low).signum() {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+
+    Decimal::from_f64((low + high) / 2.0 * 100.0).map(|rate| rate.round_dp(2))
+}
+
+pub fn period_return(points: &[ValuationPoint]) -> PeriodReturn {
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return PeriodReturn::default(),
+    };
+
+    let mut flows = vec![(first.date, -first.value)];
+    let mut flows_in = Decimal::zero();
+    let mut flows_out = Decimal::zero();
+    for point in &points[1..] {
+        flows_in += point.flows_in;
+        flows_out += point.flows_out;
+        flows.push((point.date, point.flows_out - point.flows_in));
+    }
+    flows.push((last.date, last.value));
+
+    PeriodReturn {
+        start_value: first.value,
+        end_value: last.value,
+        flows_in,
+        flows_out,
+        gain: last.value - first.value - flows_in + flows_out,
+        time_weighted: time_weighted_return(points),
+        money_weighted: xirr(&flows),
+    }
+}
+
+// Valuation of all the holdings together in the base currency. The currencies
+// without rate are added to `missing_rates` and their holdings left out.
+pub fn portfolio_points<F>(histories: &[HoldingHistory], start: NaiveDate, end: NaiveDate, rate: &F, missing_rates: &mut BTreeSet<String>) -> Vec<ValuationPoint>
+        where F: Fn(&str, NaiveDate) -> Option<Decimal> {
+    valuation_dates(histories, start, end).into_iter()
+        .map(|date| {
+            let mut total = ValuationPoint { date, value: Decimal::zero(), flows_in: Decimal::zero(), flows_out: Decimal::zero() };
+
+            for history in histories {
+                let point = history.point(date, start);
+                match rate(&history.currency, date) {
+                    Some(rate) => {
+                        total.value += point.value * rate;
+                        total.flows_in += point.flows_in * rate;
+                        total.flows_out += point.flows_out * rate;
+                    },
+                    None => { missing_rates.insert(history.currency.clone()); }
+                }
+            }
+
+            total
+        })
+        .collect()
+}
+
+// Returns of every holding and of all of them together from `start` to `end`. `rate`
+// returns how many base currency units a unit of the given currency was worth on a day.
+pub fn calculate_returns<F>(histories: &[HoldingHistory], start: NaiveDate, end: NaiveDate, base_currency: &str, rate: F) -> Returns
+        where F: Fn(&str, NaiveDate) -> Option<Decimal> {
+    let mut missing_rates = BTreeSet::new();
+
+    let holdings = histories.iter()
+        .filter_map(|history| {
+            let points: Vec<ValuationPoint> = history.dates(start, end).into_iter()
+                .map(|date| history.point(date, start))
+                .collect();
+
+            let held = points.iter()
+                .any(|point| point.value != Decimal::zero() || point.flows_in != Decimal::zero() || point.flows_out != Decimal::zero());
+            if !held {
+                return None;
+            }
+
+            Some(HoldingReturn {
+                symbol: history.symbol.clone(),
+                currency: history.currency.clone(),
+                returns: period_return(&points),
+            })
+        })
+        .collect();
+
+    let total_points = portfolio_points(histories, start, end, &rate, &mut missing_rates);
+
+    Returns {
+        start,
+        end,
+        base_currency: base_currency.to_owned(),
+        total: period_return(&total_points),
+        holdings,
+        missing_rates: missing_rates.into_iter().collect(),
+    }
+}
+
+#[test]
+fn test_period_range() {
+    let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    let today = date(2021, 3, 15);
+    let inception = date(2019, 6, 1);
+
+    assert_eq!((date(2021, 3, 14), today), ReturnPeriod::Day.range(today, inception));
+    assert_eq!((date(2021, 3, 8), today), ReturnPeriod::Week.range(today, inception));
+    assert_eq!((date(2021, 2, 28), today), ReturnPeriod::MonthToDate.range(today, inception));
+    assert_eq!((date(2020, 12, 31), today), ReturnPeriod::YearToDate.range(today, inception));
+    assert_eq!((date(2020, 3, 15), today), ReturnPeriod::Year.range(today, inception));
+    assert_eq!((date(2019, 5, 31), today), ReturnPeriod::Inception.range(today, inception));
+    assert_eq!(Some(ReturnPeriod::YearToDate), ReturnPeriod::parse("ytd"));
+}
+
+#[test]
+fn test_calculate_returns() {
+    let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    let trade = |kind, quantity: i64, price: i64, date| Transaction {
+        symbol: "AAPL".into(),
+        kind,
+        quantity: Decimal::from(quantity),
+        price: Decimal::from(price),
+        currency: "USD".into(),
+        date,
+        ..Transaction::default()
+    };
+    let bar = |date, close: i64| PriceBar { symbol: "AAPL".into(), date, close: Decimal::from(close), ..PriceBar::default() };
+
+    // Doubles the position right before the price drops back to where it was bought
+    let history = HoldingHistory::new("AAPL", "USD",
+        vec![
+            trade(TransactionKind::Buy, 10, 100, date(2020, 1, 1)),
+            trade(TransactionKind::Buy, 10, 120, date(2020, 7, 1)),
+        ],
+        vec![],
+        vec![],
+        &[bar(date(2020, 6, 30), 120), bar(date(2020, 7, 1), 120), bar(date(2021, 1, 1), 100)]);
+
+    let returns = calculate_returns(&[history], date(2019, 12, 31), date(2021, 1, 1), "EUR", |_, _| Some(Decimal::new(5, 1)));
+
+    let holding = &returns.holdings[0].returns;
+    assert_eq!(Decimal::zero(), holding.start_value);
+    assert_eq!(Decimal::from(2000), holding.end_value);
+    assert_eq!(Decimal::from(2200), holding.flows_in);
+    assert_eq!(Decimal::from(-200), holding.gain);
+    assert_eq!(Some(Decimal::zero()), holding.time_weighted);
+    assert!(holding.money_weighted.unwrap() < Decimal::zero());
+    assert_eq!(Some(Decimal::from(10)), xirr(&[(date(2020, 1, 1), Decimal::from(-1000)), (date(2021, 12, 31), Decimal::from(1210))]));
+    assert_eq!(Decimal::from(1000), returns.total.end_value);
+    assert_eq!(Some(Decimal::zero()), returns.total.time_weighted);
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+
+const TRADING_DAYS: f64 = 252.0;
+// Share of the days the value at risk isn't exceeded
+const VAR_CONFIDENCE: f64 = 0.95;
+
+// Worst fall from a high, in percent
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Drawdown {
+    pub depth: Decimal,
+    pub peak: NaiveDate,
+    pub trough: NaiveDate,
+    // Day the high was reached again, if it was
+    pub recovery: Option<NaiveDate>,
+}
+
+// Volatility, returns and value at risk are percents, the first two annualized
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct RiskMetrics {
+    // Daily returns the metrics are based on
+    pub observations: usize,
+    pub volatility: Option<Decimal>,
+    pub annual_return: Option<Decimal>,
+    pub beta: Option<Decimal>,
+    pub max_drawdown: Option<Drawdown>,
+    pub sharpe: Option<Decimal>,
+    pub sortino: Option<Decimal>,
+    // Daily loss not exceeded on 95% of the days, also as an amount of the current value
+    pub value_at_risk: Option<Decimal>,
+    pub value_at_risk_amount: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct HoldingRisk {
+    pub symbol: String,
+    pub currency: String,
+    #[serde(flatten)]
+    pub metrics: RiskMetrics,
+}
+
+// The total is measured on the growth of the whole portfolio in the base currency
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Risk {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub base_currency: String,
+    pub benchmark: Option<String>,
+    pub risk_free_rate: Decimal,
+    pub total: RiskMetrics,
+    pub holdings: Vec<HoldingRisk>,
+    // Currencies without exchange rate, their holdings are left out of the total
+    pub missing_rates: Vec<String>,
+}
+
+fn to_decimal(value: f64) -> Option<Decimal> {
+    Decimal::from_f64(value).map(|value| value.round_dp(2))
+}
+
+// Return of every day against the day before, for a series of prices or values
+pub fn daily_returns(series: &[(NaiveDate, Decimal)]) -> Vec<(NaiveDate, f64)> {
+    series.windows(2)
+        .filter(|window| window[0].1 > Decimal::zero())
+        .map(|window| (window[1].0, window[1].1.to_f64() / window[0].1.to_f64() - 1.0))
+        .collect()
+}
+
+pub fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// Sample standard deviation
+pub fn std_dev(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+
+    let mean = mean(values);
+    let variance = values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    Some(variance.sqrt())
+}
+
+// How much the returns move with the ones of the benchmark, on the days both have one
+pub fn beta(returns: &[(NaiveDate, f64)], benchmark: &[(NaiveDate, f64)]) -> Option<f64> {
+    let benchmark: HashMap<NaiveDate, f64> = benchmark.iter().cloned().collect();
+    let (returns, benchmark): (Vec<f64>, Vec<f64>) = returns.iter()
+        .filter_map(|(date, value)| benchmark.get(date).map(|benchmark| (*value, *benchmark)))
+        .unzip();
+    if returns.len() < 2 {
+        return None;
+    }
+
+    let (returns_mean, benchmark_mean) = (mean(&returns), mean(&benchmark));
+    let covariance: f64 = returns.iter().zip(&benchmark)
+        .map(|(value, benchmark)| (value - returns_mean) * (benchmark - benchmark_mean))
+        .sum();
+    let variance: f64 = benchmark.iter()
+        .map(|benchmark| (benchmark - benchmark_mean).powi(2))
+        .sum();
+
+    if variance > 0.0 {
+        Some(covariance / variance)
+    } else {
+        None
+    }
+}
+
+pub fn max_drawdown(series: &[(NaiveDate, Decimal)]) -> Option<Drawdown> {
+    let (mut peak_date, mut peak) = *series.first()?;
+    let mut drawdown = Drawdown { depth: Decimal::zero(), peak: peak_date, trough: peak_date, recovery: None };
+    let mut worst = 0.0;
+
+    for (date, value) in series {
+        if *value > peak {
+            peak = *value;
+            peak_date = *date;
+        } else if peak > Decimal::zero() {
+            let depth = value.to_f64() / peak.to_f64() - 1.0;
+            if depth < worst {
+                worst = depth;
+                drawdown = Drawdown { depth: to_decimal(depth * 100.0)?, peak: peak_date, trough: *date, recovery: None };
+            }
+        }
+    }
+
+    let peak_value = series.iter().find(|(date, _)| *date == drawdown.peak).map(|(_, value)| *value)?;
+    drawdown.recovery = series.iter()
+        .find(|(date, value)| *date > drawdown.trough && *value >= peak_value)
+        .map(|(date, _)| *date);
+
+    Some(drawdown)
+}
+
+// Measures the risk of a series of prices or values. `risk_free_rate` is the annual
+// rate in percent the Sharpe and Sortino ratios are measured against and `value` what
+// the value at risk is applied to.
+pub fn measure(series: &[(NaiveDate, Decimal)], benchmark: Option<&[(NaiveDate, Decimal)]>, risk_free_rate: Decimal, value: Decimal) -> RiskMetrics {
+    let dated_returns = daily_returns(series);
+    let returns: Vec<f64> = dated_returns.iter().map(|(_, value)| *value).collect();
+    let volatility = std_dev(&returns).map(|std_dev| std_dev * TRADING_DAYS.sqrt());
+
+    let mut metrics = RiskMetrics {
+        observations: returns.len(),
+        max_drawdown: max_drawdown(series),
+        beta: benchmark
+            .and_then(|benchmark| beta(&dated_returns, &daily_returns(benchmark)))
+            .and_then(to_decimal),
+        ..RiskMetrics::default()
+    };
+
+    let volatility = match volatility {
+        Some(volatility) => volatility,
+        None => return metrics,
+    };
+
+    let risk_free_rate = risk_free_rate.to_f64() / 100.0;
+    let annual_return = mean(&returns) * TRADING_DAYS;
+    let daily_risk_free_rate = risk_free_rate / TRADING_DAYS;
+    let downside = (returns.iter()
+        .map(|value| (value - daily_risk_free_rate).min(0.0).powi(2))
+        .sum::<f64>() / returns.len() as f64).sqrt() * TRADING_DAYS.sqrt();
+
+    let mut sorted = returns.clone();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let value_at_risk = -sorted[((1.0 - VAR_CONFIDENCE) * sorted.len() as f64) as usize];
+
+    metrics.volatility = to_decimal(volatility * 100.0);
+    metrics.annual_return = to_decimal(annual_return * 100.0);
+    metrics.sharpe = if volatility > 0.0 { to_decimal((annual_return - risk_free_rate) / volatility) } else { None };
+    metrics.sortino = if downside > 0.0 { to_decimal((annual_return - risk_free_rate) / downside) } else { None };
+    metrics.value_at_risk = to_decimal(value_at_risk * 100.0);
+    metrics.value_at_risk_amount = Decimal::from_f64(value_at_risk).map(|value_at_risk| (value * value_at_risk).round_dp(2));
+    metrics
+}
+
+#[test]
+fn test_measure() {
+    let date = |day| NaiveDate::from_ymd_opt(2020, 1, day).unwrap();
+    let series: Vec<(NaiveDate, Decimal)> = [100, 110, 99, 108, 120, 96, 130]
+        .iter()
+        .enumerate()
+        .map(|(day, price)| (date(day as u32 + 1), Decimal::from(*price as i64)))
+        .collect();
+    // Moves half as much as the series, so the beta is 2
+    let benchmark: Vec<(NaiveDate, Decimal)> = std::iter::once((date(1), Decimal::from(1000)))
+        .chain(daily_returns(&series).iter()
+            .scan(1000.0, |value, (date, daily)| { *value *= 1.0 + daily / 2.0; Some((*date, Decimal::from_f64(*value).unwrap())) }))
+        .collect();
+
+    let metrics = measure(&series, Some(&benchmark), Decimal::from(2), Decimal::from(1000));
+
+    assert_eq!(6, metrics.observations);
+    assert_eq!(Some(Decimal::from(2)), metrics.beta);
+    assert_eq!(Some(Drawdown { depth: Decimal::from(-20), peak: date(5), trough: date(6), recovery: Some(date(7)) }), metrics.max_drawdown);
+    assert_eq!(Some(Decimal::from(20)), metrics.value_at_risk);
+    assert_eq!(Some(Decimal::from(200)), metrics.value_at_risk_amount);
+    assert!(metrics.volatility.unwrap() > Decimal::from(200));
+    assert!(metrics.sortino.unwrap() > metrics.sharpe.unwrap());
+}
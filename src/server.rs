@@ -1,7 +1,10 @@
 use std::{
+    collections::BTreeSet,
     net::{TcpListener, TcpStream},
     io::{Read, Write},
     str,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 use log::{debug, info, error};
 use crossbeam_channel::{
@@ -9,31 +12,81 @@ use crossbeam_channel::{
     bounded,
     Sender,
     Receiver, 
+    RecvTimeoutError,
+    TrySendError,
 };
-use crate::{Job, Operation, ByteOperations};
-use tokio;
+use chrono::NaiveDate;
+use crate::{Job, Operation, Request, ByteOperations};
+use crate::decimal::Decimal;
+use crate::repository::{alert::AlertEvent, stop::StopEvent, order::Order};
 use serde::{Serialize, Deserialize};
 
+// Events queued for each subscriber, the ones that don't fit are dropped
+const EVENTS_CAPACITY: usize = 1024;
+// How often a subscription waiting for events checks whether it's still wanted
+const SUBSCRIPTION_CHECK: Duration = Duration::from_secs(1);
+
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ResponseWrapper<'a> {
     pub response: &'a str
 }
 
+// Message pushed to the subscribed connections without them asking for it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Price { symbol: String, price: Decimal, date: NaiveDate },
+    Alert(AlertEvent),
+    Stop(StopEvent),
+    // Paper order that was filled, expired or rejected
+    Order(Order),
+}
+
+impl Event {
+    pub fn symbol(&self) -> &str {
+        match self {
+            Self::Price { symbol, .. } => symbol,
+            Self::Alert(alert) => &alert.symbol,
+            Self::Stop(stop) => &stop.symbol,
+            Self::Order(order) => &order.symbol,
+        }
+    }
+}
+
+// Events a connection wants. Only one forwarder writes them, so subscribing again
+// right after unsubscribing reuses it if it didn't stop yet.
+#[derive(Debug, Default)]
+struct Subscription {
+    // Symbols of the events, all of them when empty, or none if it didn't subscribe
+    symbols: Option<BTreeSet<String>>,
+    forwarding: bool,
+}
+
+type SharedSubscription = Arc<Mutex<Subscription>>;
 
+// Queues of the events of every forwarder
+type Subscribers = Arc<Mutex<Vec<Sender<Event>>>>;
 
-pub fn launch_tcp_server() ->  Receiver<(Vec<u8>, Sender<Vec<u8>>)> {
+// Serialized jobs along with where to send their responses
+type Jobs = Receiver<(Vec<u8>, Sender<Vec<u8>>)>;
+
+// Returns the receiver of the jobs and the sender of the events
+pub fn launch_tcp_server() ->  (Jobs, Sender<Event>) {
     let listener = TcpListener::bind("0.0.0.0:8888")
         .expect("Could not bind");
 
     let (tx, rx) = unbounded(); // Sends / recives  Sender<Vec<u8>
+    let (events_tx, events_rx) = unbounded();
+    let subscribers = Subscribers::default();
 
-    listen_socket(listener, tx); 
+    broadcast_events(events_rx, subscribers.clone());
+    listen_socket(listener, tx, subscribers); 
 
-    rx
+    (rx, events_tx)
 }
 
-fn listen_socket(listener: TcpListener, tx: Sender<(Vec<u8>, Sender<Vec<u8>>)>) {
+fn listen_socket(listener: TcpListener, tx: Sender<(Vec<u8>, Sender<Vec<u8>>)>, subscribers: Subscribers) {
     tokio::spawn(async move {
         let mut connection_id: u32 = 0;
 
@@ -41,8 +94,8 @@ fn listen_socket(listener: TcpListener, tx: Sender<(Vec<u8>, Sender<Vec<u8>>)>)
             match stream {
                 Ok(stream) => { 
                     let tx_task = tx.clone();
-                    process_connection(connection_id, stream, tx_task);
-                    connection_id = connection_id + 1;
+                    process_connection(connection_id, stream, tx_task, subscribers.clone());
+                    connection_id += 1;
                 },
                 Err(e) => error!(target: "Server", "Failed: {}", e)
             }
@@ -50,40 +103,141 @@ fn listen_socket(listener: TcpListener, tx: Sender<(Vec<u8>, Sender<Vec<u8>>)>)
     });
 }
 
-fn process_connection(connection_id: u32, mut stream: TcpStream, tx: Sender<(Vec<u8>, Sender<Vec<u8>>)>) {
+// Fans the events out to every subscription. Neither the updater nor the other
+// subscribers wait for a subscriber that falls behind: once its queue is full, the
+// events are dropped for it alone. The queues of the forwarders that stopped are removed.
+fn broadcast_events(events: Receiver<Event>, subscribers: Subscribers) {
+    tokio::task::spawn_blocking(move || {
+        for event in events.iter() {
+            subscribers.lock().unwrap().retain(|queue| match queue.try_send(event.clone()) {
+                Ok(_) => true,
+                Err(TrySendError::Full(event)) => {
+                    error!(target: "Server", "A subscriber is behind, dropping {:?} for it", event);
+                    true
+                },
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+        }
+    });
+}
+
+// Sets the symbols of the subscription, starting to forward the events unless they
+// already are
+fn subscribe(connection_id: u32, subscription: &SharedSubscription, symbols: &[String], subscribers: &Subscribers, writer: &Arc<Mutex<TcpStream>>) {
+    let mut current = subscription.lock().unwrap();
+    current.symbols = Some(symbols.iter().cloned().collect());
+
+    if !current.forwarding {
+        current.forwarding = true;
+        let (queue, reader) = bounded(EVENTS_CAPACITY);
+        subscribers.lock().unwrap().push(queue);
+        forward_events(connection_id, reader, subscription.clone(), writer.clone());
+    }
+}
+
+fn unsubscribe(subscription: &SharedSubscription) {
+    subscription.lock().unwrap().symbols = None;
+}
+
+// Writes the events of the subscribed symbols to the connection until it unsubscribes,
+// closes or can't be written to
+fn forward_events(connection_id: u32, reader: Receiver<Event>, subscription: SharedSubscription, writer: Arc<Mutex<TcpStream>>) {
+    tokio::task::spawn_blocking(move || {
+        let stop = || subscription.lock().unwrap().forwarding = false;
+
+        loop {
+            let event = match reader.recv_timeout(SUBSCRIPTION_CHECK) {
+                Ok(event) => Some(event),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => {
+                    stop();
+                    break;
+                },
+            };
+
+            // Stopping is decided under the lock, so a subscription made meanwhile starts
+            // a new forwarder
+            let event = {
+                let mut current = subscription.lock().unwrap();
+                match &current.symbols {
+                    Some(symbols) => event.filter(|event| symbols.is_empty() || symbols.contains(event.symbol())),
+                    None => {
+                        current.forwarding = false;
+                        break;
+                    }
+                }
+            };
+
+            if let Some(event) = event {
+                let mut payload = serde_json::to_vec(&event).unwrap();
+                payload.push(b'\n');
+                debug!(target: "Server", "Event for connection {}: {}", connection_id, String::from_utf8_lossy(&payload));
+
+                if let Err(e) = writer.lock().unwrap().write_all(&payload) {
+                    error!(target: "Server", "Couldn't send the event to connection {}: {}", connection_id, e);
+                    stop();
+                    break;
+                }
+            }
+        }
+
+        info!(target: "Server", "Connection {} stopped receiving events", connection_id);
+    });
+}
+
+fn process_connection(connection_id: u32, mut stream: TcpStream, tx: Sender<(Vec<u8>, Sender<Vec<u8>>)>, subscribers: Subscribers) {
     tokio::task::spawn_blocking(move || {
         info!(target: "Server", "New connection with id {}", connection_id);
         let mut buf = [0; 512];
-        let mut bytes_read = 0;
+        // Portfolio the commands apply to, the default one until another is selected
+        let mut selected_portfolio: Option<String> = None;
+        let subscription: SharedSubscription = Arc::new(Mutex::new(Subscription::default()));
+        // The responses share the stream with the events
+        let writer = Arc::new(Mutex::new(stream.try_clone().unwrap()));
+
+        loop {
+            let bytes_read = stream.read(&mut buf).unwrap();
+            if bytes_read == 0 {
+                break;
+            }
 
-        while {
-            bytes_read = stream.read(&mut buf).unwrap();
-            bytes_read != 0
-        } {
             let message = str::from_utf8(&buf[..bytes_read])
                 .unwrap()
                 .trim_end_matches('\n'); //Removes tailing new line;
             debug!(target: "Server", "Received message: {}", message);
 
-            let operation = Operation::from(message.to_string());
+            let request = Request::parse(message, selected_portfolio.as_deref());
+
+            if request.operation != Operation::Error {
+                let rx = send_job(connection_id, &request, &tx);
+                let response = wait_and_process_response(connection_id, &rx, &writer);
 
-            if operation != Operation::Error {
-                let rx = send_job(connection_id, &operation, &tx);
-                wait_and_process_response(connection_id, &rx, &stream);
+                match &request.operation {
+                    Operation::SelectPortfolio(name) if is_successful(&response) => {
+                        selected_portfolio = Some(name.clone());
+                    },
+                    // A new subscription replaces the symbols of the one running
+                    Operation::Subscribe(symbols) if is_successful(&response) => {
+                        subscribe(connection_id, &subscription, symbols, &subscribers, &writer);
+                    },
+                    Operation::Unsubscribe => unsubscribe(&subscription),
+                    _ => {}
+                }
             } else {
                 error!(target: "Server", "Operation not valid.")
             }
         }
-        
+
+        unsubscribe(&subscription);
         info!(target: "Server", "Connection {} closed", connection_id);
     });
 
 }
 
 fn send_job(connection_id: u32, 
-        operation: &Operation, 
+        request: &Request, 
         tx: &Sender<(Vec<u8>, Sender<Vec<u8>>)>) -> Receiver<Vec<u8>> {
-    let serialized_op = operation.to_bytes();
+    let serialized_op = request.to_bytes();
                 
     let job = Job {
         id: connection_id, 
@@ -95,7 +249,8 @@ fn send_job(connection_id: u32,
     rx2
 }
 
-fn wait_and_process_response(connection_id: u32, rx: &Receiver<Vec<u8>>, mut stream: &TcpStream) {
+// Writes the response of the job to the connection, returning it
+fn wait_and_process_response(connection_id: u32, rx: &Receiver<Vec<u8>>, writer: &Mutex<TcpStream>) -> Vec<u8> {
     loop {
         let serialized_job = rx.recv().unwrap();
         debug!(target: "Server", "Raw response: {:?}", serialized_job);
@@ -110,8 +265,124 @@ fn wait_and_process_response(connection_id: u32, rx: &Receiver<Vec<u8>>, mut str
             let to_str = str::from_utf8(sliced_payload).unwrap().to_string();
             info!(target: "Server", "Response: {}", to_str);
             
-            stream.write(sliced_payload).unwrap();
-            break;
+            writer.lock().unwrap().write_all(sliced_payload).unwrap();
+            return job.payload;
         } 
     }
-}
\ No newline at end of file
+}
+
+// Whether the response is the wrapped `true` of an operation that succeeded
+fn is_successful(response: &[u8]) -> bool {
+    serde_json::from_slice::<ResponseWrapper>(response)
+        .map(|wrapper| wrapper.response == "true")
+        .unwrap_or(false)
+}
+
+#[tokio::test]
+async fn test_forward_events() {
+    use std::io::{BufRead, BufReader};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+
+    let (queue, reader) = bounded(EVENTS_CAPACITY);
+    let subscription: SharedSubscription = Arc::new(Mutex::new(Subscription {
+        symbols: Some(vec!["AAPL".to_owned()].into_iter().collect()),
+        forwarding: true,
+    }));
+    forward_events(0, reader, subscription.clone(), Arc::new(Mutex::new(stream)));
+
+    let price = |symbol: &str, price: i64| Event::Price {
+        symbol: symbol.into(),
+        price: Decimal::from(price),
+        date: NaiveDate::from_ymd_opt(2020, 1, 2).unwrap(),
+    };
+    queue.send(price("MSFT", 200)).unwrap();
+    queue.send(price("AAPL", 100)).unwrap();
+
+    // Only the subscribed symbol gets through
+    let mut lines = BufReader::new(client).lines();
+    let event: Event = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+    assert_eq!(price("AAPL", 100), event);
+
+    // Once unsubscribed the connection stops getting events
+    unsubscribe(&subscription);
+    queue.send(price("AAPL", 101)).unwrap();
+    assert!(lines.next().is_none());
+}
+
+#[tokio::test]
+async fn test_subscribe_right_after_unsubscribing() {
+    use std::io::{BufRead, BufReader};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+
+    let subscribers = Subscribers::default();
+    let subscription = SharedSubscription::default();
+    let writer = Arc::new(Mutex::new(stream));
+    let symbols = vec!["AAPL".to_owned()];
+    subscribe(0, &subscription, &symbols, &subscribers, &writer);
+    unsubscribe(&subscription);
+    subscribe(0, &subscription, &symbols, &subscribers, &writer);
+    drop(writer);
+
+    let price = |price: i64| Event::Price {
+        symbol: "AAPL".into(),
+        price: Decimal::from(price),
+        date: NaiveDate::from_ymd_opt(2020, 1, 2).unwrap(),
+    };
+    assert_eq!(1, subscribers.lock().unwrap().len());
+    for queue in subscribers.lock().unwrap().iter() {
+        queue.send(price(100)).unwrap();
+        queue.send(price(101)).unwrap();
+    }
+
+    // The forwarder that was still running keeps going, without a second one
+    let mut lines = BufReader::new(client).lines();
+    let events: Vec<Event> = (0..2).map(|_| serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap()).collect();
+    assert_eq!(vec![price(100), price(101)], events);
+
+    unsubscribe(&subscription);
+    assert!(lines.next().is_none());
+    assert!(!subscription.lock().unwrap().forwarding);
+}
+
+#[tokio::test]
+async fn test_stalled_subscriber_doesnt_hold_up_the_others() {
+    use std::io::{BufRead, BufReader};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let connect = || {
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        (client, Arc::new(Mutex::new(stream)))
+    };
+    let (client, writer) = connect();
+    let (_stalled_client, stalled_writer) = connect();
+
+    let subscribers = Subscribers::default();
+    let (events, events_rx) = unbounded();
+    broadcast_events(events_rx, subscribers.clone());
+    subscribe(0, &SharedSubscription::default(), &[], &subscribers, &writer);
+    subscribe(1, &SharedSubscription::default(), &[], &subscribers, &stalled_writer);
+
+    // Holding the stream stalls its forwarder, as a client that stopped reading would
+    let _stalled = stalled_writer.lock().unwrap();
+
+    let price = |price: i64| Event::Price {
+        symbol: "AAPL".into(),
+        price: Decimal::from(price),
+        date: NaiveDate::from_ymd_opt(2020, 1, 2).unwrap(),
+    };
+
+    // More events than the stalled queue holds, and the other connection gets every one
+    let mut lines = BufReader::new(client).lines();
+    for event_price in 0..EVENTS_CAPACITY as i64 + 2 {
+        events.send(price(event_price)).unwrap();
+        let event: Event = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(price(event_price), event);
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use crate::repository::stock::Stock;
+
+// Holdings listed as top gainers and losers
+const TOP_MOVERS: usize = 3;
+
+// Profit and loss of a holding in its own currency
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct HoldingSummary {
+    pub symbol: String,
+    pub currency: String,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub market_value: Decimal,
+    pub cost: Decimal,
+    pub unrealized_gain: Decimal,
+    pub unrealized_gain_pct: Option<Decimal>,
+    // Known once a previous day is in the price history
+    pub previous_close: Option<Decimal>,
+    pub day_change: Option<Decimal>,
+    pub day_change_pct: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Mover {
+    pub symbol: String,
+    pub unrealized_gain: Decimal,
+    pub unrealized_gain_pct: Decimal,
+}
+
+// Totals are in the base currency
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Summary {
+    pub base_currency: String,
+    pub holdings: Vec<HoldingSummary>,
+    pub market_value: Decimal,
+    pub cost: Decimal,
+    pub unrealized_gain: Decimal,
+    pub unrealized_gain_pct: Option<Decimal>,
+    pub day_change: Decimal,
+    pub day_change_pct: Option<Decimal>,
+    // Biggest unrealized gains and losses in percent
+    pub top_gainers: Vec<Mover>,
+    pub top_losers: Vec<Mover>,
+    // Currencies without exchange rate, their holdings are left out of the totals
+    pub missing_rates: Vec<String>,
+}
+
+// Percentage `part` is of `whole`, rounded to two decimals
+fn percent(part: Decimal, whole: Decimal) -> Option<Decimal> {
+    (part * Decimal::from(100))
+        .checked_div(whole)
+        .map(|pct| pct.round_dp(2))
+}
+
+// Works out the profit and loss of the holdings from their stored prices. `previous_closes`
+// has the close of the day before of each symbol and `rate` how many base currency
+// units a unit of the given currency is worth.
+pub fn summarize<F>(stocks: &[Stock], previous_closes: &HashMap<String, Decimal>, base_currency: &str, rate: F) -> Summary
+        where F: Fn(&str) -> Option<Decimal> {
+    let mut missing_rates = BTreeSet::new();
+    let mut market_value = Decimal::zero();
+    let mut cost = Decimal::zero();
+    let mut day_change = Decimal::zero();
+    let mut previous_value = Decimal::zero();
+
+    let holdings: Vec<HoldingSummary> = stocks.iter()
+        .map(|stock| {
+            let value = stock.market_value();
+            let holding_cost = stock.cost();
+            let previous_close = previous_closes.get(&stock.symbol).cloned();
+            let holding_day_change = previous_close.map(|close| (stock.price - close) * stock.quantity);
+
+            match rate(stock.currency()) {
+                Some(rate) => {
+                    market_value += value * rate;
+                    cost += holding_cost * rate;
+                    if let (Some(close), Some(change)) = (previous_close, holding_day_change) {
+                        day_change += change * rate;
+                        previous_value += close * stock.quantity * rate;
+                    }
+                },
+                None => { missing_rates.insert(stock.currency().to_owned()); }
+            }
+
+            HoldingSummary {
+                symbol: stock.symbol.clone(),
+                currency: stock.currency().to_owned(),
+                quantity: stock.quantity,
+                price: stock.price,
+                market_value: value,
+                cost: holding_cost,
+                unrealized_gain: value - holding_cost,
+                unrealized_gain_pct: percent(value - holding_cost, holding_cost),
+                previous_close,
+                day_change: holding_day_change,
+                day_change_pct: previous_close.and_then(|close| percent(stock.price - close, close)),
+            }
+        })
+        .collect();
+
+    let mut movers: Vec<Mover> = holdings.iter()
+        .filter_map(|holding| holding.unrealized_gain_pct.map(|pct| Mover {
+            symbol: holding.symbol.clone(),
+            unrealized_gain: holding.unrealized_gain,
+            unrealized_gain_pct: pct,
+        }))
+        .collect();
+    movers.sort_by_key(|mover| mover.unrealized_gain_pct);
+
+    let top_losers = movers.iter()
+        .filter(|mover| mover.unrealized_gain_pct < Decimal::zero())
+        .take(TOP_MOVERS)
+        .cloned()
+        .collect();
+    let top_gainers = movers.iter()
+        .rev()
+        .filter(|mover| mover.unrealized_gain_pct > Decimal::zero())
+        .take(TOP_MOVERS)
+        .cloned()
+        .collect();
+
+    Summary {
+        base_currency: base_currency.to_owned(),
+        holdings,
+        market_value,
+        cost,
+        unrealized_gain: market_value - cost,
+        unrealized_gain_pct: percent(market_value - cost, cost),
+        day_change,
+        day_change_pct: percent(day_change, previous_value),
+        top_gainers,
+        top_losers,
+        missing_rates: missing_rates.into_iter().collect(),
+    }
+}
+
+fn format_amount(amount: Decimal) -> String {
+    amount.round_dp(2).to_string()
+}
+
+fn format_pct(pct: Option<Decimal>) -> String {
+    match pct {
+        Some(pct) if pct > Decimal::zero() => format!("+{}%", pct),
+        Some(pct) => format!("{}%", pct),
+        None => "-".to_owned(),
+    }
+}
+
+fn format_movers(movers: &[Mover]) -> String {
+    if movers.is_empty() {
+        return "-".to_owned();
+    }
+
+    movers.iter()
+        .map(|mover| format!("{} {}", mover.symbol, format_pct(Some(mover.unrealized_gain_pct))))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// Table of the holdings followed by the totals
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<8} {:>12} {:>12} {:>14} {:>14} {:>14} {:>9} {:>9}",
+            "Symbol", "Quantity", "Price", "Value", "Cost", "Gain", "Gain %", "Day %")?;
+
+        for holding in &self.holdings {
+            writeln!(f, "{:<8} {:>12} {:>12} {:>14} {:>14} {:>14} {:>9} {:>9}",
+                holding.symbol,
+                holding.quantity.round_dp(4),
+                format!("{} {}", format_amount(holding.price), holding.currency),
+                format_amount(holding.market_value),
+                format_amount(holding.cost),
+                format_amount(holding.unrealized_gain),
+                format_pct(holding.unrealized_gain_pct),
+                format_pct(holding.day_change_pct))?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Market value:    {} {}", format_amount(self.market_value), self.base_currency)?;
+        writeln!(f, "Cost:            {} {}", format_amount(self.cost), self.base_currency)?;
+        writeln!(f, "Unrealized gain: {} {} ({})", format_amount(self.unrealized_gain), self.base_currency, format_pct(self.unrealized_gain_pct))?;
+        writeln!(f, "Day change:      {} {} ({})", format_amount(self.day_change), self.base_currency, format_pct(self.day_change_pct))?;
+        writeln!(f, "Top gainers:     {}", format_movers(&self.top_gainers))?;
+        write!(f, "Top losers:      {}", format_movers(&self.top_losers))?;
+
+        if !self.missing_rates.is_empty() {
+            write!(f, "\nMissing rates:   {}", self.missing_rates.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_summarize() {
+    let stock = |symbol: &str, price: i64, initial_price: i64, currency: &str| Stock {
+        symbol: symbol.into(),
+        price: Decimal::from(price),
+        initial_price: Decimal::from(initial_price),
+        quantity: Decimal::from(10),
+        currency: currency.into(),
+        ..Stock::default()
+    };
+    let stocks = vec![
+        stock("AAPL", 120, 100, "USD"),
+        stock("KO", 45, 50, "USD"),
+        stock("SAP", 100, 100, "EUR"),
+        stock("7203", 2000, 1000, "JPY"),
+    ];
+    let previous_closes: HashMap<String, Decimal> = vec![
+        ("AAPL".to_owned(), Decimal::from(100)),
+        ("SAP".to_owned(), Decimal::from(80)),
+    ].into_iter().collect();
+
+    let summary = summarize(&stocks, &previous_closes, "USD", |currency| match currency {
+        "USD" => Some(Decimal::from(1)),
+        "EUR" => Some(Decimal::from(2)),
+        _ => None,
+    });
+
+    assert_eq!(Decimal::from(3650), summary.market_value);
+    assert_eq!(Decimal::from(3500), summary.cost);
+    assert_eq!(Decimal::from(150), summary.unrealized_gain);
+    assert_eq!(Some(Decimal::new(429, 2)), summary.unrealized_gain_pct);
+    assert_eq!(Decimal::from(600), summary.day_change);
+    assert_eq!(Some(Decimal::new(2308, 2)), summary.day_change_pct);
+    assert_eq!(Some(Decimal::from(20)), summary.holdings[0].day_change_pct);
+    assert_eq!(None, summary.holdings[1].day_change);
+    assert_eq!(vec!["7203", "AAPL"], summary.top_gainers.iter().map(|mover| mover.symbol.as_str()).collect::<Vec<_>>());
+    assert_eq!("KO", summary.top_losers[0].symbol);
+    assert_eq!(vec!["JPY".to_owned()], summary.missing_rates);
+
+    let text = summary.to_string();
+    assert!(text.contains("Unrealized gain: 150 USD (+4.29%)"));
+    assert!(text.contains("Top losers:      KO -10%"));
+}
@@ -0,0 +1,337 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use crate::repository::transaction::{Transaction, TransactionKind};
+use crate::repository::corporate_action::{self, CorporateAction};
+
+// Days before and after a sale at a loss in which buying the stock again makes it a wash sale
+pub const WASH_SALE_DAYS: i64 = 30;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Term {
+    // Held for a year or less
+    Short,
+    Long,
+}
+
+// Shares of a sale matched with one purchase. Amounts are in the currency of the stock.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct LotSale {
+    pub symbol: String,
+    pub currency: String,
+    // In shares of the day of the sale
+    pub quantity: Decimal,
+    pub acquired: NaiveDate,
+    pub sold: NaiveDate,
+    // Net of the commission of the sale
+    pub proceeds: Decimal,
+    // Adjusted basis: the purchase with its commission plus the losses of the wash sales it replaced
+    pub cost: Decimal,
+    // Loss disallowed because the stock was bought again, moved to the basis of the replacement shares
+    pub wash_sale: Decimal,
+    pub adjusted_gain: Decimal,
+    pub term: Term,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TaxTotal {
+    pub currency: String,
+    pub term: Term,
+    pub proceeds: Decimal,
+    pub cost: Decimal,
+    pub wash_sale: Decimal,
+    pub adjusted_gain: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TaxReport {
+    pub year: i32,
+    // Lots sold in the year, by date of the sale
+    pub sales: Vec<LotSale>,
+    pub totals: Vec<TaxTotal>,
+    // Symbols sold in the year beyond the shares bought in the ledger, e.g. received in
+    // a merger. The shares without a purchase are left out.
+    pub missing_lots: Vec<String>,
+}
+
+// Shares of a purchase still held. The quantity is in shares of the day of the purchase.
+#[derive(Debug, Clone)]
+struct Lot {
+    // Of the purchase in the transactions of the stock
+    position: usize,
+    acquired: NaiveDate,
+    // Earlier than the purchase for replacement shares, which keep the holding period
+    // of the shares sold at a loss
+    holding_start: NaiveDate,
+    quantity: Decimal,
+    cost: Decimal,
+    // Already took the loss of a wash sale
+    replacement: bool,
+}
+
+impl Term {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Short => "short",
+            Self::Long => "long",
+        }
+    }
+}
+
+impl Lot {
+    // Shares of the lot in shares of `date`, converting across the splits in between
+    fn shares_on(&self, actions: &[CorporateAction], date: NaiveDate) -> Decimal {
+        if date >= self.acquired {
+            self.quantity * corporate_action::split_factor(actions, self.acquired, date)
+        } else {
+            self.quantity / corporate_action::split_factor(actions, date, self.acquired)
+        }
+    }
+
+    // Takes `shares` of the `available` ones, leaving the rest in the lot
+    fn split_off(&mut self, shares: Decimal, available: Decimal) -> Lot {
+        let taken = if shares >= available {
+            self.clone()
+        } else {
+            Lot {
+                quantity: self.quantity * shares / available,
+                cost: self.cost * shares / available,
+                ..self.clone()
+            }
+        };
+
+        self.quantity -= taken.quantity;
+        self.cost -= taken.cost;
+        taken
+    }
+}
+
+// Long term when sold after the anniversary of the start of the holding period
+fn term(holding_start: NaiveDate, sold: NaiveDate) -> Term {
+    let anniversary = (1..=holding_start.day()).rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(holding_start.year() + 1, holding_start.month(), day))
+        .unwrap();
+
+    if sold > anniversary { Term::Long } else { Term::Short }
+}
+
+// Moves the loss of `shares` sold on `date` to the basis of the shares bought within the
+// wash sale window, other than the lots of the sale. Returns the part of the loss disallowed.
+fn wash(lots: &mut Vec<Lot>, actions: &[CorporateAction], sold: &Lot, shares: Decimal, loss: Decimal, date: NaiveDate, sale_lots: &BTreeSet<usize>) -> Decimal {
+    let window = Duration::days(WASH_SALE_DAYS);
+    let mut remaining = shares;
+    let mut disallowed = Decimal::zero();
+    let mut index = 0;
+
+    while index < lots.len() && remaining > Decimal::zero() {
+        let lot = &lots[index];
+        let available = lot.shares_on(actions, date);
+        let in_window = lot.acquired >= date - window && lot.acquired <= date + window;
+
+        if in_window && !lot.replacement && !sale_lots.contains(&lot.position) && available > Decimal::zero() {
+            let replaced = remaining.min(available);
+            let moved = loss * replaced / shares;
+            let mut replacement = lots[index].split_off(replaced, available);
+            replacement.cost += moved;
+            replacement.holding_start = replacement.acquired - (date - sold.holding_start);
+            replacement.replacement = true;
+
+            // Before what's left of the purchase, so they're still sold in order
+            lots.insert(index, replacement);
+            index += 1;
+            disallowed += moved;
+            remaining -= replaced;
+        }
+        index += 1;
+    }
+
+    disallowed
+}
+
+// Matches the sales of a stock with its purchases, first in first out. Transactions go
+// oldest first. Returns the lots sold and the days of the sales without enough purchases.
+fn match_lots(transactions: &[&Transaction], actions: &[CorporateAction]) -> (Vec<LotSale>, Vec<NaiveDate>) {
+    let mut lots: Vec<Lot> = transactions.iter()
+        .enumerate()
+        .filter(|(_, transaction)| transaction.kind == TransactionKind::Buy)
+        .map(|(position, transaction)| Lot {
+            position,
+            acquired: transaction.date,
+            holding_start: transaction.date,
+            quantity: transaction.quantity,
+            cost: -transaction.cash_amount(),
+            replacement: false,
+        })
+        .collect();
+    let mut sales = Vec::new();
+    let mut missing = Vec::new();
+
+    for (position, sale) in transactions.iter().enumerate() {
+        if sale.kind != TransactionKind::Sell {
+            continue;
+        }
+
+        let mut remaining = sale.quantity;
+        let mut sold = Vec::new();
+        for lot in lots.iter_mut().filter(|lot| lot.position < position) {
+            let available = lot.shares_on(actions, sale.date);
+            if remaining <= Decimal::zero() {
+                break;
+            } else if available > Decimal::zero() {
+                let shares = remaining.min(available);
+                sold.push((lot.split_off(shares, available), shares));
+                remaining -= shares;
+            }
+        }
+        if remaining > Decimal::zero() {
+            missing.push(sale.date);
+        }
+
+        let sale_lots: BTreeSet<usize> = sold.iter().map(|(lot, _)| lot.position).collect();
+        for (lot, shares) in sold {
+            let proceeds = sale.cash_amount() * shares / sale.quantity;
+            let loss = lot.cost - proceeds;
+            let wash_sale = if loss > Decimal::zero() {
+                wash(&mut lots, actions, &lot, shares, loss, sale.date, &sale_lots)
+            } else {
+                Decimal::zero()
+            };
+
+            sales.push(LotSale {
+                symbol: sale.symbol.clone(),
+                currency: sale.currency.clone(),
+                quantity: shares.round_dp(4),
+                acquired: lot.acquired,
+                sold: sale.date,
+                proceeds: proceeds.round_dp(4),
+                cost: lot.cost.round_dp(4),
+                wash_sale: wash_sale.round_dp(4),
+                adjusted_gain: (proceeds - lot.cost + wash_sale).round_dp(4),
+                term: term(lot.holding_start, sale.date),
+            });
+        }
+    }
+
+    (sales, missing)
+}
+
+// Works out the lots sold in a year from the whole ledger, so purchases and wash sales of
+// earlier years carry over. `actions` has the splits to match shares across.
+pub fn report(transactions: &[Transaction], actions: &[CorporateAction], year: i32) -> TaxReport {
+    let symbols: BTreeSet<&str> = transactions.iter().map(|transaction| transaction.symbol.as_str()).collect();
+    let mut sales = Vec::new();
+    let mut missing_lots = Vec::new();
+
+    for symbol in symbols {
+        let stock_transactions: Vec<&Transaction> = transactions.iter()
+            .filter(|transaction| transaction.symbol == symbol)
+            .collect();
+        let stock_actions: Vec<CorporateAction> = actions.iter()
+            .filter(|action| action.symbol == symbol)
+            .cloned()
+            .collect();
+
+        let (stock_sales, missing) = match_lots(&stock_transactions, &stock_actions);
+        if missing.iter().any(|date| date.year() == year) {
+            missing_lots.push(symbol.to_owned());
+        }
+        sales.extend(stock_sales.into_iter().filter(|sale| sale.sold.year() == year));
+    }
+    sales.sort_by_key(|sale| sale.sold);
+
+    let mut totals: BTreeMap<(String, Term), TaxTotal> = BTreeMap::new();
+    for sale in &sales {
+        let total = totals.entry((sale.currency.clone(), sale.term))
+            .or_insert_with(|| TaxTotal {
+                currency: sale.currency.clone(),
+                term: sale.term,
+                proceeds: Decimal::zero(),
+                cost: Decimal::zero(),
+                wash_sale: Decimal::zero(),
+                adjusted_gain: Decimal::zero(),
+            });
+        total.proceeds += sale.proceeds;
+        total.cost += sale.cost;
+        total.wash_sale += sale.wash_sale;
+        total.adjusted_gain += sale.adjusted_gain;
+    }
+
+    TaxReport {
+        year,
+        sales,
+        totals: totals.into_values().collect(),
+        missing_lots,
+    }
+}
+
+impl TaxReport {
+    // One row per lot sold, amounts rounded to cents
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("symbol,quantity,acquired,sold,proceeds,cost,wash_sale,adjusted_gain,term,currency\n");
+
+        for sale in &self.sales {
+            writeln!(csv, "{},{},{},{},{},{},{},{},{},{}",
+                sale.symbol,
+                sale.quantity,
+                sale.acquired,
+                sale.sold,
+                sale.proceeds.round_dp(2),
+                sale.cost.round_dp(2),
+                sale.wash_sale.round_dp(2),
+                sale.adjusted_gain.round_dp(2),
+                sale.term.as_str(),
+                sale.currency).unwrap();
+        }
+
+        csv
+    }
+}
+
+#[test]
+fn test_tax_report() {
+    use crate::repository::corporate_action::CorporateActionKind;
+
+    let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    let transaction = |symbol: &str, kind, quantity, price, date| Transaction {
+        symbol: symbol.into(),
+        kind,
+        quantity: Decimal::from(quantity),
+        price: Decimal::from(price),
+        currency: "USD".into(),
+        date,
+        ..Transaction::default()
+    };
+    let transactions = vec![
+        transaction("AAPL", TransactionKind::Buy, 10, 100, date(2021, 1, 4)),
+        transaction("AAPL", TransactionKind::Sell, 10, 80, date(2021, 3, 1)),
+        // Replaces half the shares sold at a loss
+        transaction("AAPL", TransactionKind::Buy, 5, 85, date(2021, 3, 15)),
+        transaction("AAPL", TransactionKind::Sell, 10, 45, date(2022, 1, 20)),
+        transaction("KO", TransactionKind::Sell, 10, 50, date(2022, 2, 1)),
+    ];
+    let actions = vec![
+        CorporateAction { symbol: "AAPL".into(), kind: CorporateActionKind::Split, date: date(2021, 6, 1), ratio: Decimal::from(2), ..CorporateAction::default() },
+    ];
+
+    let sales_2021 = report(&transactions, &actions, 2021);
+    assert_eq!(1, sales_2021.sales.len());
+    assert_eq!(Decimal::from(100), sales_2021.sales[0].wash_sale);
+    assert_eq!(Decimal::from(-100), sales_2021.sales[0].adjusted_gain);
+    assert_eq!(Term::Short, sales_2021.sales[0].term);
+    assert!(sales_2021.missing_lots.is_empty());
+
+    // The replacement shares were split, cost the loss more and keep its holding period
+    let sales_2022 = report(&transactions, &actions, 2022);
+    assert_eq!(vec!["KO".to_owned()], sales_2022.missing_lots);
+    assert_eq!(Decimal::from(525), sales_2022.sales[0].cost);
+    assert_eq!(Term::Long, sales_2022.sales[0].term);
+    assert_eq!(Decimal::from(-75), sales_2022.totals[0].adjusted_gain);
+    assert_eq!(
+        "symbol,quantity,acquired,sold,proceeds,cost,wash_sale,adjusted_gain,term,currency\nAAPL,10,2021-03-15,2022-01-20,450,525,0,-75,long,USD\n",
+        sales_2022.to_csv());
+}
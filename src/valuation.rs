@@ -0,0 +1,153 @@
+use std::collections::BTreeSet;
+use serde::{Serialize, Deserialize};
+use crate::decimal::Decimal;
+use crate::repository::{
+    stock::Stock,
+    cash::CashBalance,
+};
+
+// Value of a holding in its own currency and in the base currency
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct HoldingValuation {
+    pub symbol: String,
+    pub currency: String,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub initial_price: Decimal,
+    pub value: Decimal,
+    pub cost: Decimal,
+    pub gain: Decimal,
+    pub value_base: Option<Decimal>,
+    pub cost_base: Option<Decimal>,
+    pub gain_base: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct CashValuation {
+    pub currency: String,
+    pub balance: Decimal,
+    pub balance_base: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Valuation {
+    pub base_currency: String,
+    pub holdings: Vec<HoldingValuation>,
+    pub cash: Vec<CashValuation>,
+    pub holdings_base: Decimal,
+    pub cost_base: Decimal,
+    pub gain_base: Decimal,
+    pub cash_base: Decimal,
+    // Holdings plus cash
+    pub total_base: Decimal,
+    // Currencies without exchange rate, their holdings and cash are left out of the totals
+    pub missing_rates: Vec<String>,
+}
+
+// Values the stocks and the cash in the base currency. `rate` returns how many
+// base currency units a unit of the given currency is worth.
+pub fn value_portfolio<F>(stocks: &[Stock], cash: &[CashBalance], base_currency: &str, rate: F) -> Valuation
+        where F: Fn(&str) -> Option<Decimal> {
+    let mut missing_rates = BTreeSet::new();
+    let mut holdings_base = Decimal::zero();
+    let mut cost_base = Decimal::zero();
+    let mut cash_base = Decimal::zero();
+
+    let holdings = stocks.iter()
+        .map(|stock| {
+            let currency = stock.currency();
+            let rate = rate(currency);
+            let value = stock.market_value();
+            let cost = stock.cost();
+
+            match rate {
+                Some(rate) => {
+                    holdings_base += value * rate;
+                    cost_base += cost * rate;
+                },
+                None => { missing_rates.insert(currency.to_owned()); }
+            }
+
+            HoldingValuation {
+                symbol: stock.symbol.clone(),
+                currency: currency.to_owned(),
+                quantity: stock.quantity,
+                price: stock.price,
+                initial_price: stock.initial_price,
+                value,
+                cost,
+                gain: value - cost,
+                value_base: rate.map(|rate| value * rate),
+                cost_base: rate.map(|rate| cost * rate),
+                gain_base: rate.map(|rate| (value - cost) * rate),
+            }
+        })
+        .collect();
+
+    let cash = cash.iter()
+        .map(|balance| {
+            let rate = rate(&balance.currency);
+
+            match rate {
+                Some(rate) => cash_base += balance.balance * rate,
+                None => { missing_rates.insert(balance.currency.clone()); }
+            }
+
+            CashValuation {
+                currency: balance.currency.clone(),
+                balance: balance.balance,
+                balance_base: rate.map(|rate| balance.balance * rate),
+            }
+        })
+        .collect();
+
+    Valuation {
+        base_currency: base_currency.to_owned(),
+        holdings,
+        cash,
+        holdings_base,
+        cost_base,
+        gain_base: holdings_base - cost_base,
+        cash_base,
+        total_base: holdings_base + cash_base,
+        missing_rates: missing_rates.into_iter().collect(),
+    }
+}
+
+#[test]
+fn test_value_portfolio_converts_to_base() {
+    let stock = |symbol: &str, price: i64, initial_price: i64, currency: &str| Stock {
+        symbol: symbol.into(),
+        price: Decimal::from(price),
+        initial_price: Decimal::from(initial_price),
+        quantity: Decimal::from(2),
+        currency: currency.into(),
+        ..Stock::default()
+    };
+    let stocks = vec![
+        stock("AAPL", 110, 100, "USD"),
+        stock("SAP", 120, 100, "EUR"),
+        stock("7203", 2000, 1000, "JPY"),
+    ];
+    let cash = vec![
+        CashBalance { currency: "EUR".into(), balance: Decimal::from(50) },
+        CashBalance { currency: "USD".into(), balance: Decimal::from(100) },
+    ];
+
+    let valuation = value_portfolio(&stocks, &cash, "EUR", |currency| match currency {
+        "EUR" => Some(Decimal::from(1)),
+        "USD" => Some(Decimal::new(9, 1)),
+        _ => None,
+    });
+
+    assert_eq!(Decimal::from(198), valuation.holdings[0].value_base.unwrap());
+    assert_eq!(Decimal::from(20), valuation.holdings[0].gain);
+    assert_eq!(Decimal::from(18), valuation.holdings[0].gain_base.unwrap());
+    assert_eq!(None, valuation.holdings[2].value_base);
+    assert_eq!(Decimal::from(438), valuation.holdings_base);
+    assert_eq!(Decimal::from(380), valuation.cost_base);
+    assert_eq!(Decimal::from(58), valuation.gain_base);
+    assert_eq!(Decimal::from(140), valuation.cash_base);
+    assert_eq!(Decimal::from(578), valuation.total_base);
+    assert_eq!(vec!["JPY".to_owned()], valuation.missing_rates);
+}